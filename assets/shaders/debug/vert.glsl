@@ -0,0 +1,12 @@
+#version 410 core
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec4 color;
+
+uniform mat4 view_projection;
+
+out vec4 line_color;
+
+void main() {
+    line_color = color;
+    gl_Position = view_projection * vec4(pos, 1.0);
+}
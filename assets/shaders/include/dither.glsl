@@ -0,0 +1,47 @@
+// The threshold of an 8x8 Bayer matrix at a cell, from 0 up to 63/64.
+// Cells repeat every 8 along each axis.
+float bayer8(ivec2 cell) {
+    int dither[64] = int[64](
+         0, 32,  8, 40,  2, 34, 10, 42,
+        48, 16, 56, 24, 50, 18, 58, 26,
+        12, 44,  4, 36, 14, 46,  6, 38,
+        60, 28, 52, 20, 62, 30, 54, 22,
+         3, 35, 11, 43,  1, 33,  9, 41,
+        51, 19, 59, 27, 49, 17, 57, 25,
+        15, 47,  7, 39, 13, 45,  5, 37,
+        63, 31, 55, 23, 61, 29, 53, 21
+    );
+    // Calculate the id as x + y * row_width (8)
+    int id = (cell.x & 7) + ((cell.y & 7) << 3);
+    return float(dither[id]) / 64.0;
+}
+
+// The threshold of a 4x4 Bayer matrix at a cell, from 0 up to 15/16.
+float bayer4(ivec2 cell) {
+    int dither[16] = int[16](
+         0,  8,  2, 10,
+        12,  4, 14,  6,
+         3, 11,  1,  9,
+        15,  7, 13,  5
+    );
+    int id = (cell.x & 3) + ((cell.y & 3) << 2);
+    return float(dither[id]) / 16.0;
+}
+
+// The threshold of a 2x2 Bayer matrix at a cell, from 0 up to 3/4.
+float bayer2(ivec2 cell) {
+    int dither[4] = int[4](
+        0, 2,
+        3, 1
+    );
+    int id = (cell.x & 1) + ((cell.y & 1) << 1);
+    return float(dither[id]) / 4.0;
+}
+
+// The threshold of a 2x2, 4x4 or 8x8 Bayer matrix at a cell. Smaller matrices
+// give coarser patterns with fewer levels.
+float bayer(ivec2 cell, int size) {
+    if (size <= 2) return bayer2(cell);
+    if (size <= 4) return bayer4(cell);
+    return bayer8(cell);
+}
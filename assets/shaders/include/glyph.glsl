@@ -0,0 +1,20 @@
+#include "quad.glsl"
+
+// The uv of a corner of a glyph's quad, for a glyph index counted from the
+// top-left of the font atlas.
+//
+// atlas_scale = glyphs size as ratio of atlas width
+// given a 2x2 16px atlas with 8px glyphs,
+// the width would be 1/2 or glyph_size/atlas_width.
+//  ___
+// |_|_| ]-> atlas_scale
+// |_|_|
+vec2 glyph_uv(vec2 corner, int glyph, float glyph_size, float atlas_width) {
+    float atlas_scale = glyph_size / atlas_width;
+
+    // Calculate the 2d index of the character in the atlas
+    int glyphs_per_row = int(atlas_width / glyph_size);
+    vec2 glyph_id_coord = vec2(float(glyph % glyphs_per_row),
+                               float(glyph / glyphs_per_row));
+    return quad_corner_uv(corner, atlas_scale) + glyph_id_coord * atlas_scale;
+}
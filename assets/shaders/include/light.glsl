@@ -0,0 +1,9 @@
+// Finds a light's center and radius in pixels of the viewport, this accounts
+// for any camera zoom present in the view_projection.
+void light_bounds(vec4 origin, float intensity, mat4 view_projection, vec2 viewport_size,
+                  out vec2 center, out float radius) {
+    vec4 center_clip = view_projection * origin;
+    vec4 edge_clip = view_projection * (origin + vec4(intensity, 0.0, 0.0, 0.0));
+    center = (center_clip.xy * 0.5 + 0.5) * viewport_size;
+    radius = (edge_clip.x - center_clip.x) * 0.5 * viewport_size.x;
+}
@@ -0,0 +1,34 @@
+#include "dither.glsl"
+
+// The lighting modes of the LightRenderer, see LightingMode in renderer/light.rs
+#define LIGHTING_DITHERED   0
+#define LIGHTING_SMOOTH     1
+#define LIGHTING_POSTERIZED 2
+
+uniform sampler2D lightmap;
+uniform float lightmap_scale;
+uniform int lighting_mode;
+uniform int dither_size;
+uniform int bands;
+
+// The brightest channel of a lightmap color, so colored lights reach as far
+// as white ones
+float light_level(vec3 lightmap_color) {
+    return max(lightmap_color.r, max(lightmap_color.g, lightmap_color.b));
+}
+
+// How lit a fragment is drawn, from 0 (in shadow) to 1 (fully lit). Dithering
+// only gives 0 or 1, smooth lighting follows the light and posterized
+// lighting steps it into bands.
+float light_coverage(float light, vec2 frag_coord) {
+    light = clamp(light, 0.0, 1.0);
+    if (lighting_mode == LIGHTING_SMOOTH) {
+        return light;
+    }
+    if (lighting_mode == LIGHTING_POSTERIZED) {
+        float steps = float(max(bands, 1));
+        return ceil(light * steps - 0.5) / steps;
+    }
+    vec2 xy = floor(frag_coord) / lightmap_scale;
+    return light > bayer(ivec2(xy), dither_size) ? 1.0 : 0.0;
+}
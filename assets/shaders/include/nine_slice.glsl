@@ -0,0 +1,51 @@
+// A corner of one of the 3x3 slices of a panel
+struct PanelCorner {
+    // Offset from the panel's bottom-left corner, in world units
+    vec2 offset;
+    vec2 tex_coord;
+    // True if the slice has no area, e.g. the edges of a plain sprite
+    bool empty;
+};
+
+// Corner `i` of `slice`, counted from the bottom-left slice along each row.
+// Corners are in triangle strip order: bottom-left, top-left, bottom-right,
+// top-right.
+PanelCorner panel_corner(int slice, int i, uint id, vec2 dims, vec2 size, vec4 insets,
+                         int sheet_width, int sheet_tile_w, int sheet_border) {
+    float sh_width = float(sheet_width);
+    float tile_width = float(sheet_tile_w);
+
+    // The sprite's rect in atlas pixels, from the top-left of the atlas.
+    // The root is the bottom-left tile, so larger sprites extend up and right.
+    // Extruded sheets pad each tile by sheet_border pixels on every side.
+    int tile_pitch = sheet_tile_w + 2 * sheet_border;
+    uint spr_per_row = uint(sheet_width / tile_pitch);
+    vec2 tile = vec2(float(id % spr_per_row),
+                     float(id / spr_per_row));
+    float px_left   = tile.x * float(tile_pitch) + float(sheet_border);
+    float px_right  = px_left + dims.x * tile_width;
+    float px_bottom = tile.y * float(tile_pitch) + float(sheet_border) + tile_width;
+    float px_top    = px_bottom - dims.y * tile_width;
+
+    // Shrink the borders evenly if the panel is smaller than them
+    vec2 border = vec2(insets.x + insets.y, insets.z + insets.w);
+    vec2 shrink = min(vec2(1.0), size / max(border, vec2(1.0)));
+
+    // Grid lines of the 3x3 slices, in panel units (from the bottom-left)
+    float xs[4] = float[4](0.0, insets.x * shrink.x, size.x - insets.y * shrink.x, size.x);
+    float ys[4] = float[4](0.0, insets.w * shrink.y, size.y - insets.z * shrink.y, size.y);
+    // ..and the matching lines in the atlas, in uv-space
+    float us[4] = float[4](px_left, px_left + insets.x, px_right - insets.y, px_right);
+    float vs[4] = float[4](px_bottom, px_bottom - insets.w, px_top + insets.z, px_top);
+
+    int row = slice / 3;
+    int col = slice % 3;
+    int cx = col + (i / 2);
+    int cy = row + (i % 2);
+
+    PanelCorner corner;
+    corner.offset = vec2(xs[cx], ys[cy]);
+    corner.tex_coord = vec2(us[cx], vs[cy]) / sh_width;
+    corner.empty = xs[col + 1] <= xs[col] || ys[row + 1] <= ys[row];
+    return corner;
+}
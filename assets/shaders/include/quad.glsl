@@ -0,0 +1,14 @@
+// Corners of a unit quad, drawn as a triangle strip. Note this goes from
+// corner to corner, so the origin is centered:
+//  0 - bottom-left, 1 - top-left, 2 - bottom-right, 3 - top-right
+vec2 quad_corner(int i) {
+    return vec2((i & 2) != 0 ? 1.0 : -1.0,
+                (i & 1) != 0 ? 1.0 : -1.0);
+}
+
+// The uv of a quad corner in a square cell `size` wide, using (0, 0) as the
+// cell's top-left. The square is flipped vertically, to flip the texture.
+vec2 quad_corner_uv(vec2 corner, float size) {
+    return vec2(corner.x > 0.0 ? size : 0.0,
+                corner.y > 0.0 ? 0.0 : size);
+}
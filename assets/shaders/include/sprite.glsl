@@ -0,0 +1,80 @@
+#include "quad.glsl"
+
+// Unpacks the tile dimensions of a sprite from its sprite_data, see the
+// layout in sprite/vert.glsl. Zero dimensions default to 1x1.
+vec2 sprite_dims(uint sprite_data) {
+    return vec2(float(sprite_data & 0xFu) + 1.0,
+                float((sprite_data >> 4) & 0xFu) + 1.0);
+}
+
+// A corner of a sprite's quad
+struct SpriteCorner {
+    // Offset from the sprite's position, in world units
+    vec4 offset;
+    vec2 tex_coord;
+    // The uv rect of the whole sprite as (min.xy, max.xy), used by materials
+    // to keep distorted lookups inside of the sprite.
+    vec4 uv_bounds;
+};
+
+SpriteCorner sprite_corner(int i, uint sprite_id, vec2 dims, vec2 scale,
+                           int sheet_width, int sheet_tile_w, int sheet_border) {
+    float tile_width = float(sheet_tile_w);
+    vec2 tile_dims = vec2(tile_width, tile_width);
+    vec2 corner = quad_corner(i);
+
+    // Calculate a unit uv square using (0, 1) as top left.
+    //
+    //  y=1 _ _ _ x,y=1
+    //     |_|_|_|
+    //     |_|_|_|
+    //     |_|_|_|
+    // x,y=0      x=1
+    //
+    // We are looking to find the top-left sub-rect in the map. Each sub-rect's
+    // width can be calculated as a ratio of the rect size and width of the sheet.
+    // That is, The width of a sprite, sheet_tile_w, divided by the width of the sheet, sheet_width.
+    // This 'sheet_ratio' is used to create a square from the top-left, sheet_ratio wide.
+    float sheet_ratio = tile_width / float(sheet_width);
+    // Extruded sheets pad each tile by sheet_border pixels on every side, so
+    // tiles are further apart than they are wide.
+    int tile_pitch = sheet_tile_w + 2 * sheet_border;
+    float pitch_ratio = float(tile_pitch) / float(sheet_width);
+
+    // Calculate the 2d position of the sprite_id
+    uint spr_per_row = uint(sheet_width / tile_pitch);
+    vec2 uv_id = vec2(float(sprite_id % spr_per_row),
+                      float(sprite_id / spr_per_row));
+    // Scale that position by the spacing of the tiles in uv-space, skipping
+    // past the tile's padding
+    vec2 uv_offset = uv_id * pitch_ratio + float(sheet_border) / float(sheet_width);
+    // Bounds of the sprite, as spanned by the top-left and bottom-right uvs
+    vec2 uv_a = quad_corner_uv(quad_corner(1), sheet_ratio) * dims
+              + uv_offset - uv_offset*(dims - vec2(1.0, 1.0));
+    vec2 uv_b = quad_corner_uv(quad_corner(2), sheet_ratio) * dims
+              + uv_offset - uv_offset*(dims - vec2(1.0, 1.0));
+
+    // ====== Vertex Calculations ======
+    // TODO Rotation
+    //
+    // Each vertex is calculated using the middle of the quad as origin.
+    //
+    // The point is using the scale of the sprite tile and a unit quad.
+    // This position is then scaled by the size of the sprite in tiles, as
+    // well as the scaling data of the sprite.
+    // Finally it's translated so that the anchor of the scaling is along
+    // the bottom edge, on the left-most tile.
+    // =================================
+    vec2 point_offset = corner * tile_dims/2.0;
+    vec2 quad_scale = dims * scale;
+    vec2 quad_transl = vec2((dims.x-1.0) * tile_dims.x/2.0,
+                            (dims.y-1.0 + scale.y/2.0) * tile_dims.y/2.0);
+
+    SpriteCorner result;
+    result.offset = vec4(point_offset * quad_scale + quad_transl, 0.0, 0.0);
+    result.tex_coord = quad_corner_uv(corner, sheet_ratio) * dims
+                     + uv_offset
+                     - uv_offset*(dims - vec2(1.0, 1.0));
+    result.uv_bounds = vec4(min(uv_a, uv_b), max(uv_a, uv_b));
+    return result;
+}
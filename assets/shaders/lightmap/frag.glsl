@@ -5,8 +5,16 @@ out vec4 fragColor;
 in GS_OUT {
     float intensity_sq;
     vec2 center;
+    vec3 color;
+    // Spot lights shine along direction, fading out between the cosines of
+    // the inner and outer half angles in cone. Point lights have a cone of -1.
+    vec2 direction;
+    vec2 cone;
+    float falloff;
 } gs_out;
 
+// Scales the light, e.g. to split it between the samples of a soft shadow
+uniform float gain;
 
 void main() {
     vec2 xy = gl_FragCoord.xy; 
@@ -18,8 +26,14 @@ void main() {
     if (dist_sq >= gs_out.intensity_sq)
         discard; 
 
-    float lum = max(0.0, 1.0-(dist_sq/gs_out.intensity_sq));
-    lum *= lum;
+    float lum = pow(max(0.0, 1.0-(dist_sq/gs_out.intensity_sq)), gs_out.falloff);
 
-    fragColor = vec4(clamp(lum * 1.1, 0.0, 1.0), 0.0, 0.0, 1.0);
+    if (gs_out.cone.y > -1.0) {
+        float dist = sqrt(dist_sq);
+        float cos_angle = dist > 0.0 ? dot(delta_pos / dist, gs_out.direction) : 1.0;
+        lum *= smoothstep(gs_out.cone.y, gs_out.cone.x, cos_angle);
+    }
+
+    // Lights mix additively, so overlapping colors blend towards white
+    fragColor = vec4(gs_out.color * clamp(lum * 1.1, 0.0, 1.0) * gain, 1.0);
 }
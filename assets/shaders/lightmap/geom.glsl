@@ -1,38 +1,46 @@
 #version 410 core
+#include "quad.glsl"
+#include "light.glsl"
 layout (points) in;
 layout (triangle_strip, max_vertices = 4) out;
 
-uniform mat4 view;
-uniform mat4 projection;
-uniform float px_scale;
+uniform mat4 view_projection;
+uniform vec2 viewport_size;
 in VS_OUT {
     float intensity;
+    vec3 color;
+    vec2 direction;
+    vec2 cone;
+    float falloff;
 } vs_out[];
 
 out GS_OUT {
     float intensity_sq;
     vec2 center;
+    vec3 color;
+    vec2 direction;
+    vec2 cone;
+    float falloff;
 } gs_out;
 
 void main() {
     float intensity = vs_out[0].intensity;
     
-    // Calculate vertex offsets of a square to be drawn with triangle strip.
-    // Note this goes from corner to corner, so the origin is centered.
-    vec4 unit_quad_verts[4] = vec4[4](
-        vec4( -1.0, -1.0, 0.0, 0.0 ), // bottom-left
-        vec4( -1.0,  1.0, 0.0, 0.0 ), // top-left
-        vec4(  1.0, -1.0, 0.0, 0.0 ), // bottom-right
-        vec4(  1.0,  1.0, 0.0, 0.0 )  // top-right
-    );
+    vec2 center;
+    float radius;
+    light_bounds(gl_in[0].gl_Position, intensity, view_projection, viewport_size,
+                 center, radius);
 
-    float int_sq = vs_out[0].intensity * vs_out[0].intensity;
-    vec4 view_offset = vec4(view[3].xy, 0.0, 0.0) / px_scale;
     for (int i = 0; i < 4; ++i) {
-        gl_Position = projection * (gl_in[0].gl_Position + view_offset + unit_quad_verts[i] * intensity);
+        vec4 unit_quad = vec4(quad_corner(i), 0.0, 0.0);
+        gl_Position = view_projection * (gl_in[0].gl_Position + unit_quad * intensity);
         gl_Position.zw = vec2(0.0, 1.0);
-        gs_out.intensity_sq = int_sq;
-        gs_out.center = view_offset.xy + gl_in[0].gl_Position.xy;
+        gs_out.intensity_sq = radius * radius;
+        gs_out.center = center;
+        gs_out.color = vs_out[0].color;
+        gs_out.direction = vs_out[0].direction;
+        gs_out.cone = vs_out[0].cone;
+        gs_out.falloff = vs_out[0].falloff;
         EmitVertex();
     }
     EndPrimitive();
@@ -1,15 +1,60 @@
 #version 410 core
+#include "quad.glsl"
+#include "light.glsl"
+// Lights are passed as points to geom.glsl, or with INSTANCED defined, drawn
+// as a 4 vertex triangle strip per light with the RenderLight attributes
+// advancing once per instance.
 layout (location = 0) in vec2 pos;
 layout (location = 1) in float intensity;
+layout (location = 2) in vec3 color;
+layout (location = 3) in vec2 direction;
+layout (location = 4) in vec2 cone;
+layout (location = 5) in float falloff;
 
+#ifdef INSTANCED
 uniform mat4 view_projection;
-uniform float px_scale;
+uniform vec2 viewport_size;
 
+out GS_OUT {
+    float intensity_sq;
+    vec2 center;
+    vec3 color;
+    vec2 direction;
+    vec2 cone;
+    float falloff;
+} gs_out;
+#else
 out VS_OUT {
     float intensity;
+    vec3 color;
+    vec2 direction;
+    vec2 cone;
+    float falloff;
 } vs_out;
+#endif
 
 void main() {
-    vs_out.intensity = intensity/px_scale;
-    gl_Position = vec4(pos/px_scale, 0.0, 1.0);
+#ifdef INSTANCED
+    vec4 origin = vec4(pos, 0.0, 1.0);
+    vec2 center;
+    float radius;
+    light_bounds(origin, intensity, view_projection, viewport_size, center, radius);
+
+    vec4 unit_quad = vec4(quad_corner(gl_VertexID), 0.0, 0.0);
+    gl_Position = view_projection * (origin + unit_quad * intensity);
+    gl_Position.zw = vec2(0.0, 1.0);
+    gs_out.intensity_sq = radius * radius;
+    gs_out.center = center;
+    gs_out.color = color;
+    gs_out.direction = direction;
+    gs_out.cone = cone;
+    gs_out.falloff = falloff;
+#else
+    vs_out.intensity = intensity;
+    vs_out.color = color;
+    vs_out.direction = direction;
+    vs_out.cone = cone;
+    vs_out.falloff = falloff;
+    gl_Position = vec4(pos, 0.0, 1.0);
+#endif
 }
@@ -0,0 +1,35 @@
+#version 410 core
+out vec4 out_color;
+
+uniform sampler2D spritesheet_tex;
+uniform int sheet_width;
+
+// 0 is fully visible, 1 is fully dissolved
+uniform float dissolve;
+// Color of the burning edge and its width (as a fraction of dissolve)
+uniform vec4 edge_color;
+uniform float edge_width;
+
+in GS_OUT {
+    vec2 tex_coord;
+    vec4 color_adj;
+    vec4 uv_bounds;
+} gs_out;
+
+// Per-pixel hash noise, so the sprite dissolves a whole art pixel at a time
+float hash(vec2 p) {
+    return fract(sin(dot(p, vec2(127.1, 311.7))) * 43758.5453);
+}
+
+void main() {
+    vec4 tex_data = texture(spritesheet_tex, gs_out.tex_coord);
+    out_color = tex_data * gs_out.color_adj;
+
+    float noise = hash(floor(gs_out.tex_coord * float(sheet_width)));
+    if (out_color.a < 0.01 || noise < dissolve) {
+        discard;
+    }
+    if (noise < dissolve + edge_width) {
+        out_color.rgb = edge_color.rgb;
+    }
+}
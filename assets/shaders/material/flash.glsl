@@ -0,0 +1,23 @@
+#version 410 core
+out vec4 out_color;
+
+uniform sampler2D spritesheet_tex;
+
+// 0 leaves the sprite as is, 1 paints it entirely with flash_color
+uniform float flash;
+uniform vec4 flash_color;
+
+in GS_OUT {
+    vec2 tex_coord;
+    vec4 color_adj;
+    vec4 uv_bounds;
+} gs_out;
+
+void main() {
+    vec4 tex_data = texture(spritesheet_tex, gs_out.tex_coord);
+    out_color = tex_data * gs_out.color_adj;
+    out_color.rgb = mix(out_color.rgb, flash_color.rgb, flash * flash_color.a);
+    if (out_color.a < 0.01) {
+        discard;
+    }
+}
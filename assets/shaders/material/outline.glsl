@@ -0,0 +1,39 @@
+#version 410 core
+out vec4 out_color;
+
+uniform sampler2D spritesheet_tex;
+uniform int sheet_width;
+
+uniform vec4 outline_color;
+
+in GS_OUT {
+    vec2 tex_coord;
+    vec4 color_adj;
+    vec4 uv_bounds;
+} gs_out;
+
+// Samples alpha, treating anything outside of the sprite as transparent
+float alpha_at(vec2 uv) {
+    if (any(lessThan(uv, gs_out.uv_bounds.xy)) || any(greaterThan(uv, gs_out.uv_bounds.zw))) {
+        return 0.0;
+    }
+    return texture(spritesheet_tex, uv).a;
+}
+
+void main() {
+    vec4 tex_data = texture(spritesheet_tex, gs_out.tex_coord);
+    out_color = tex_data * gs_out.color_adj;
+
+    // Transparent pixels bordering an opaque one become the outline
+    if (out_color.a < 0.01) {
+        float texel = 1.0 / float(sheet_width);
+        float neighbours = alpha_at(gs_out.tex_coord + vec2( texel, 0.0))
+                         + alpha_at(gs_out.tex_coord + vec2(-texel, 0.0))
+                         + alpha_at(gs_out.tex_coord + vec2(0.0,  texel))
+                         + alpha_at(gs_out.tex_coord + vec2(0.0, -texel));
+        if (neighbours < 0.01) {
+            discard;
+        }
+        out_color = outline_color;
+    }
+}
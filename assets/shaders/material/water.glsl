@@ -0,0 +1,33 @@
+#version 410 core
+out vec4 out_color;
+
+uniform sampler2D spritesheet_tex;
+uniform int sheet_width;
+uniform float time;
+
+// Distortion strength in art pixels, and the speed/frequency of the waves
+uniform float amplitude;
+uniform float speed;
+uniform float frequency;
+
+in GS_OUT {
+    vec2 tex_coord;
+    vec4 color_adj;
+    vec4 uv_bounds;
+} gs_out;
+
+void main() {
+    float texel = 1.0 / float(sheet_width);
+    vec2 uv = gs_out.tex_coord;
+    vec2 wave = vec2(
+        sin(uv.y * frequency * float(sheet_width) + time * speed),
+        cos(uv.x * frequency * float(sheet_width) + time * speed)
+    );
+    // Keep the lookup inside of the sprite to avoid sampling its neighbours
+    uv = clamp(uv + wave * amplitude * texel, gs_out.uv_bounds.xy, gs_out.uv_bounds.zw);
+
+    out_color = texture(spritesheet_tex, uv) * gs_out.color_adj;
+    if (out_color.a < 0.01) {
+        discard;
+    }
+}
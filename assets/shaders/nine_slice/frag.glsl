@@ -0,0 +1,19 @@
+#version 410 core
+out vec4 out_color;
+
+uniform sampler2D spritesheet_tex;
+// Set when the sheet's colors are multiplied by their alpha
+uniform bool premultiplied;
+
+in GS_OUT {
+    vec2 tex_coord;
+    vec4 color_adj;
+} gs_out;
+
+void main() {
+    out_color = texture(spritesheet_tex, gs_out.tex_coord) * gs_out.color_adj;
+    if (premultiplied) { out_color.rgb *= gs_out.color_adj.a; }
+    if (out_color.a < 0.01) {
+        discard;
+    }
+}
@@ -0,0 +1,43 @@
+#version 410 core
+#include "nine_slice.glsl"
+layout (points) in;
+layout (triangle_strip, max_vertices = 36) out;
+
+uniform mat4 view_projection;
+uniform int sheet_width;
+uniform int sheet_tile_w;
+uniform int sheet_border;
+
+in VS_OUT {
+    vec4 color;
+    vec2 size;
+    uint id;
+    vec2 dims;
+    vec4 insets;
+} vs_out[];
+
+out GS_OUT {
+    vec2 tex_coord;
+    vec4 color_adj;
+} gs_out;
+
+void main() {
+    vec4 origin = gl_in[0].gl_Position;
+    for (int slice = 0; slice < 9; ++slice) {
+        // Skip slices with no area, e.g. the edges of a plain sprite
+        if (panel_corner(slice, 0, vs_out[0].id, vs_out[0].dims, vs_out[0].size,
+                         vs_out[0].insets, sheet_width, sheet_tile_w,
+                         sheet_border).empty) { continue; }
+
+        for (int i = 0; i < 4; ++i) {
+            PanelCorner corner = panel_corner(slice, i, vs_out[0].id, vs_out[0].dims,
+                                              vs_out[0].size, vs_out[0].insets,
+                                              sheet_width, sheet_tile_w, sheet_border);
+            gl_Position = view_projection * (origin + vec4(corner.offset, 0.0, 0.0));
+            gs_out.tex_coord = corner.tex_coord;
+            gs_out.color_adj = vs_out[0].color;
+            EmitVertex();
+        }
+        EndPrimitive();
+    }
+}
@@ -0,0 +1,65 @@
+#version 410 core
+#include "sprite.glsl"
+#include "nine_slice.glsl"
+// Panels are passed as points to geom.glsl, or with INSTANCED defined, drawn
+// as 9 instances of a 4 vertex triangle strip, one per slice, with these
+// attributes advancing once every 9 instances.
+layout (location = 0) in vec3 pos;
+// pos      - the bottom-left corner of the panel
+layout (location = 1) in vec2 size;
+// size     - the width and height of the panel, in units
+layout (location = 2) in vec4 color;
+// color    - multiplies the sprite's color
+layout (location = 3) in uint sprite_id;
+// sprite_id - The location of the sprite in the sheet, see sprite/vert.glsl
+layout (location = 4) in uint sprite_data;
+// sprite_data - Packed as in sprite/vert.glsl, only dims are used
+layout (location = 5) in uint insets;
+// insets   - Border sizes in pixels, packed from the least significant byte
+//            as left, right, top, bottom
+
+#ifdef INSTANCED
+uniform mat4 view_projection;
+uniform int sheet_width;
+uniform int sheet_tile_w;
+uniform int sheet_border;
+
+out GS_OUT {
+    vec2 tex_coord;
+    vec4 color_adj;
+} gs_out;
+#else
+out VS_OUT {
+    vec4 color;
+    vec2 size;
+    uint id;
+    vec2 dims;
+    vec4 insets;
+} vs_out;
+#endif
+
+void main() {
+    vec2 dims = sprite_dims(sprite_data);
+    vec4 unpacked = vec4(float(insets & 0xFFu),
+                         float((insets >> 8) & 0xFFu),
+                         float((insets >> 16) & 0xFFu),
+                         float((insets >> 24) & 0xFFu));
+#ifdef INSTANCED
+    // Slices with no area collapse to a point, drawing nothing
+    PanelCorner corner = panel_corner(gl_InstanceID % 9, gl_VertexID, sprite_id, dims,
+                                      size, unpacked, sheet_width, sheet_tile_w,
+                                      sheet_border);
+    vec2 offset = corner.empty ? vec2(0.0) : corner.offset;
+    gl_Position = view_projection * (vec4(pos, 1.0) + vec4(offset, 0.0, 0.0));
+    gs_out.tex_coord = corner.tex_coord;
+    gs_out.color_adj = color;
+#else
+    vs_out.id = sprite_id;
+    vs_out.dims = dims;
+    vs_out.insets = unpacked;
+    vs_out.size = size;
+    vs_out.color = color;
+
+    gl_Position = vec4(pos, 1.0);
+#endif
+}
@@ -0,0 +1,52 @@
+#version 410 core
+out vec4 fragColor;
+in vec2 uv_pos;
+
+uniform sampler2D source;
+uniform vec2 resolution;
+// Pixels glow by its alpha as well as their luminance
+uniform sampler2D emissive;
+uniform bool use_emissive;
+
+// Luminance above which pixels start to glow
+uniform float threshold;
+// How strongly the glow is added back to the image
+uniform float intensity;
+// Glow radius in pixels
+uniform float radius;
+
+float luminance(vec3 c) {
+    return dot(c, vec3(0.2126, 0.7152, 0.0722));
+}
+
+vec3 bright(vec2 uv) {
+    // Only pixels past the threshold glow, fading in to avoid a hard edge
+    vec3 c = texture(source, uv).rgb;
+    float glow = smoothstep(threshold, 1.0, luminance(c));
+    if (use_emissive) {
+        glow = max(glow, texture(emissive, uv).a);
+    }
+    return c * glow;
+}
+
+void main() {
+    vec4 color = texture(source, uv_pos);
+    vec2 texel = 1.0 / resolution;
+
+    // Sample along a golden angle spiral for an even, single-pass blur
+    const int SAMPLES = 24;
+    const float GOLDEN_ANGLE = 2.39996323;
+    vec3 glow = vec3(0.0);
+    float total = 0.0;
+    for (int i = 0; i < SAMPLES; ++i) {
+        float r = sqrt((float(i) + 0.5) / float(SAMPLES));
+        float theta = float(i) * GOLDEN_ANGLE;
+        vec2 offset = vec2(cos(theta), sin(theta)) * r * radius * texel;
+        float weight = 1.0 - r;
+        glow += bright(uv_pos + offset) * weight;
+        total += weight;
+    }
+    glow /= total;
+
+    fragColor = vec4(color.rgb + glow * intensity, 1.0);
+}
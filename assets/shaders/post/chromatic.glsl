@@ -0,0 +1,20 @@
+#version 410 core
+out vec4 fragColor;
+in vec2 uv_pos;
+
+uniform sampler2D source;
+uniform vec2 resolution;
+
+// Maximum channel separation in pixels, reached at the screen edges
+uniform float offset;
+
+void main() {
+    // Separate more towards the edges of the screen
+    vec2 dir = uv_pos - vec2(0.5);
+    vec2 shift = dir * 2.0 * offset / resolution;
+
+    float r = texture(source, uv_pos + shift).r;
+    vec4 g = texture(source, uv_pos);
+    float b = texture(source, uv_pos - shift).b;
+    fragColor = vec4(r, g.g, b, g.a);
+}
@@ -0,0 +1,32 @@
+#version 410 core
+out vec4 fragColor;
+in vec2 uv_pos;
+
+uniform sampler2D source;
+// A strip LUT: `lut_size` square slices of blue, laid out left to right.
+// Within each slice red increases along x and green along y.
+uniform sampler2D lut;
+uniform float lut_size;
+// Blend between the original (0) and graded (1) colors
+uniform float strength;
+
+vec3 sample_lut(vec3 color) {
+    float max_id = lut_size - 1.0;
+    float blue = color.b * max_id;
+    float slice0 = floor(blue);
+    float slice1 = min(slice0 + 1.0, max_id);
+
+    // Sample texel centers to avoid bleeding between slices
+    vec2 texel = vec2(1.0 / (lut_size * lut_size), 1.0 / lut_size);
+    vec2 inner = vec2(color.r * max_id, color.g * max_id) * texel + texel * 0.5;
+
+    vec2 uv0 = vec2(slice0 / lut_size, 0.0) + inner;
+    vec2 uv1 = vec2(slice1 / lut_size, 0.0) + inner;
+    return mix(texture(lut, uv0).rgb, texture(lut, uv1).rgb, blue - slice0);
+}
+
+void main() {
+    vec4 color = texture(source, uv_pos);
+    vec3 graded = sample_lut(clamp(color.rgb, 0.0, 1.0));
+    fragColor = vec4(mix(color.rgb, graded, strength), color.a);
+}
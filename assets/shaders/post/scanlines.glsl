@@ -0,0 +1,35 @@
+#version 410 core
+out vec4 fragColor;
+in vec2 uv_pos;
+
+uniform sampler2D source;
+uniform vec2 resolution;
+uniform float time;
+
+// Darkness of the gaps between lines
+uniform float intensity;
+// Barrel distortion of the screen, 0 for a flat screen
+uniform float curvature;
+// Strength of the slow brightness roll
+uniform float flicker;
+
+void main() {
+    // Barrel distortion
+    vec2 uv = uv_pos * 2.0 - 1.0;
+    uv *= 1.0 + curvature * dot(uv, uv);
+    uv = uv * 0.5 + 0.5;
+
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        fragColor = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec4 color = texture(source, uv);
+
+    // Darken the edge of every source row, so lines stay aligned to art pixels
+    float row = fract(uv.y * resolution.y);
+    float line = 1.0 - intensity * pow(abs(row - 0.5) * 2.0, 2.0);
+
+    float roll = 1.0 - flicker * (0.5 + 0.5 * sin(uv.y * 6.0 - time * 2.0));
+    fragColor = vec4(color.rgb * line * roll, color.a);
+}
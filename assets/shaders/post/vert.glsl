@@ -0,0 +1,13 @@
+#version 410 core
+// Reads the layout of a QuadVertex, see quad/vert.glsl
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec2 uv;
+
+uniform mat4 transform;
+
+out vec2 uv_pos;
+
+void main() {
+    uv_pos = uv;
+    gl_Position = transform * vec4(pos, 1.0);
+}
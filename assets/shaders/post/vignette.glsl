@@ -0,0 +1,26 @@
+#version 410 core
+out vec4 fragColor;
+in vec2 uv_pos;
+
+uniform sampler2D source;
+uniform vec2 resolution;
+
+// How dark the edges get
+uniform float strength;
+// Distance from the center (0.5 is the screen edge) where darkening starts
+uniform float radius;
+// Width of the transition from clear to dark
+uniform float softness;
+uniform vec3 vignette_color;
+
+void main() {
+    vec4 color = texture(source, uv_pos);
+
+    // Correct for aspect so the vignette is round
+    vec2 delta = uv_pos - vec2(0.5);
+    delta.x *= resolution.x / resolution.y;
+
+    float dist = length(delta);
+    float shade = smoothstep(radius, radius + softness, dist) * strength;
+    fragColor = vec4(mix(color.rgb, vignette_color, shade), color.a);
+}
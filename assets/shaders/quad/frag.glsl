@@ -0,0 +1,17 @@
+#version 410 core
+out vec4 out_color;
+
+in vec2 tex_coord;
+in vec4 vert_color;
+
+uniform sampler2D tex;
+uniform bool use_texture;
+
+void main() {
+    vec4 color = vert_color;
+    if (use_texture) {
+        color *= texture(tex, tex_coord);
+    }
+    if (color.a < 0.01) discard;
+    out_color = color;
+}
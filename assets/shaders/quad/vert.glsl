@@ -0,0 +1,15 @@
+#version 410 core
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec2 uv;
+layout (location = 2) in vec4 color;
+
+uniform mat4 transform;
+
+out vec2 tex_coord;
+out vec4 vert_color;
+
+void main() {
+    tex_coord = uv;
+    vert_color = color;
+    gl_Position = transform * vec4(pos, 1.0);
+}
@@ -0,0 +1,8 @@
+#version 410 core
+
+out vec4 fragColor;
+
+void main() {
+    // Color writes are masked, only the stencil is marked
+    fragColor = vec4(0.0);
+}
@@ -0,0 +1,11 @@
+#version 410 core
+// Shadow volumes are drawn into the lightmap's stencil buffer only, see
+// renderer/shadow.rs.
+layout (location = 0) in vec2 pos;
+
+uniform mat4 view_projection;
+
+void main() {
+    gl_Position = view_projection * vec4(pos, 0.0, 1.0);
+    gl_Position.zw = vec2(0.0, 1.0);
+}
@@ -0,0 +1,19 @@
+#version 410 core
+#include "lighting.glsl"
+
+out vec4 fragColor;
+
+in vec2 uv_pos;
+
+// Tints the lit fragments by the color of the light reaching them, as far as
+// they are lit. Drawn with multiplicative blending.
+void main() {
+    vec3 lightmap_color = texture(lightmap, uv_pos).rgb;
+    float light = light_level(lightmap_color);
+    float coverage = light_coverage(light, gl_FragCoord.xy);
+    if (coverage <= 0.0){
+        discard;
+    }
+    // Only the light's hue, its brightness is already shown by the shadow mask
+    fragColor = vec4(mix(vec3(1.0), lightmap_color / light, coverage), 1.0);
+}
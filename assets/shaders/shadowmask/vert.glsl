@@ -1,12 +1,13 @@
 #version 410 core
-layout (location = 0) in vec2 pos;
+// Quads covering the scene, with the QuadVertex layout of the render backends
+layout (location = 0) in vec3 pos;
 layout (location = 1) in vec2 tex_pos;
 
-uniform float lightmap_scale;
+uniform mat4 transform;
 
 out vec2 uv_pos;
 
 void main() {
-    uv_pos = tex_pos;///lightmap_scale;
-    gl_Position = vec4(pos, -0.001, 1.0);
+    uv_pos = tex_pos;
+    gl_Position = transform * vec4(pos, 1.0);
 }
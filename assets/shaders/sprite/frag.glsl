@@ -2,15 +2,30 @@
 out vec4 out_color;
 
 uniform sampler2D spritesheet_tex;
+uniform sampler2D palette_tex;
+// Set when the sheet's colors are multiplied by their alpha
+uniform bool premultiplied;
 
 in GS_OUT {
     vec2 tex_coord;
     vec4 color_adj;
+    vec4 uv_bounds;
 } gs_out;
+flat in uint sprite_flags;
+flat in uint palette_row;
 
 void main() {
     vec4 tex_data = texture(spritesheet_tex, gs_out.tex_coord);
+    // Indexed sprites look up their color in a palette row
+    if ((sprite_flags & 1u) != 0u) {
+        int index = int(tex_data.r * 255.0 + 0.5);
+        vec4 pal = texelFetch(palette_tex, ivec2(index, int(palette_row)), 0);
+        tex_data = vec4(pal.rgb, pal.a * tex_data.a);
+        // Palettes hold straight colors
+        if (premultiplied) { tex_data.rgb *= tex_data.a; }
+    }
     out_color = tex_data * gs_out.color_adj;
+    if (premultiplied) { out_color.rgb *= gs_out.color_adj.a; }
     if (out_color.a < 0.01) {
         discard;
     }
@@ -1,4 +1,8 @@
 #version 410 core
+#include "sprite.glsl"
+// Sprites are passed as points to geom.glsl, or with INSTANCED defined, drawn
+// as a 4 vertex triangle strip per sprite, with these attributes advancing
+// once per instance.
 layout (location = 0) in vec3 pos;
 // pos      - the world position of the sprite to be drawn
 //            z-values will not change the scale, only the draw order
@@ -17,38 +21,73 @@ layout (location = 4) in uint sprite_id;
 //              ...
 //
 layout (location = 5) in uint sprite_data;
-// Sprite data must be packed as:
+// Sprite data must be packed as (from the least significant byte):
 // [ 0x00 0x00 0x00 0x00 ]
 //   |--| |--| |-------|
-//  flags dims reserved
+//   dims flags  palette
 // Where:
-//  dims  - How many tiles wide and tall the sprite is with the right half
-//          of the byte being x and the left being y. Zero defaults to 1x1.
+//  dims  - How many tiles wide and tall the sprite is with the low half
+//          of the byte being x and the high being y. Zero defaults to 1x1.
 //
 //  flags  - Flags applying directly to this sprite
+//           0x1 - indexed, the art holds palette indices in its red channel
+//
+//  palette - The row of the palette texture used by indexed sprites
 
 uniform mat4 view_projection;
 uniform int sheet_width;
 uniform int sheet_tile_w;
+uniform int sheet_border;
 
-
+#ifdef INSTANCED
+/// Fragment data, named as the geometry shader's so fragment shaders work
+/// with either path
+out GS_OUT {
+    vec2 tex_coord;
+    vec4 color_adj;
+    vec4 uv_bounds;
+} gs_out;
+flat out uint sprite_flags;
+flat out uint palette_row;
+#else
 out VS_OUT {
     vec4 color;
     vec2 scale;
     uint id;
     vec2 dims;
+    uint flags;
+    uint palette;
 } vs_out;
+#endif
 
 void main() {
-    vs_out.id = sprite_id;
     // Unpack sprite data
-    vs_out.dims = vec2(float((sprite_data >> 24) & 0xF) + 1.0,
-                       float((sprite_data >> 28) & 0xF) + 1.0);
+    vec2 dims = sprite_dims(sprite_data);
+    uint flags = (sprite_data >> 8) & 0xFFu;
+    uint palette = sprite_data >> 16;
+
+#ifdef INSTANCED
+    // The corner of the quad, in strip order
+    SpriteCorner corner = sprite_corner(gl_VertexID, sprite_id, dims, scale,
+                                        sheet_width, sheet_tile_w, sheet_border);
+    gl_Position = view_projection * (vec4(pos, 1.0) + corner.offset);
+
+    gs_out.tex_coord = corner.tex_coord;
+    gs_out.color_adj = color;
+    gs_out.uv_bounds = corner.uv_bounds;
+    sprite_flags = flags;
+    palette_row = palette;
+#else
+    vs_out.id = sprite_id;
+    vs_out.dims = dims;
+    vs_out.flags = flags;
+    vs_out.palette = palette;
     
     // Forward attributes to geometry shader
     vs_out.scale = scale; 
     vs_out.color = color;
 
     gl_Position = vec4(pos, 1.0);
+#endif
     // TODO rotation
 }
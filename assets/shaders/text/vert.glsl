@@ -3,25 +3,48 @@
  * Jayden Dumouchel 2022
  *
  * Passes along vertex attribute info to the geometry shader 
- * to build a usable quad.
+ * to build a usable quad. With INSTANCED defined, the quad is instead
+ * drawn as a 4 vertex triangle strip per glyph, with the RenderChar
+ * attributes advancing once per instance.
  *
  * Ascii values passed here are modified to exclude the 
  * first 32 (non-renderable) characters.
  */
 #version 420 core
+#include "glyph.glsl"
 layout (location = 0) in vec3 translation;
 layout (location = 1) in float size_in;
 layout (location = 2) in vec4 color_in;
 layout (location = 3) in int ascii_val_in;
 
+#ifdef INSTANCED
+uniform mat4 view_projection;
+uniform float glyph_size;
+uniform float atlas_width;
+
+out GS_OUT {
+    vec2 tex_coord;
+    vec4 tint;
+} gs_out;
+#else
 out vec4 color;
 out float size;
 out int ascii_val;
+#endif
 
 void main() {
+#ifdef INSTANCED
+    vec2 corner = quad_corner(gl_VertexID);
+    vec4 scaled_vert_offset = vec4(corner, 0.0, 0.0) * (glyph_size/2.0) * size_in;
+    gl_Position = view_projection * (vec4(translation, 1.0) + scaled_vert_offset);
+
+    gs_out.tex_coord = glyph_uv(corner, ascii_val_in - 32, glyph_size, atlas_width);
+    gs_out.tint = color_in;
+#else
     gl_Position = vec4(translation, 1.0);
 
     ascii_val = ascii_val_in - 32;
     color = color_in;
     size = size_in; 
+#endif
 }
@@ -13,10 +13,18 @@ use stoneng::ecs::{
     component,
 };
 use stoneng::event::{KeyEvent, KeyCode};
+use stoneng::renderer::{
+    upscale::ScalingMode,
+    post::{PostPass, PostEffect},
+    material::{MaterialShader, BuiltinMaterial},
+    debug::DebugDraw,
+};
 use stoneng::{
     self, 
-    model::spritesheet::SpriteSheet,
-    controller::player,
+    model::{spritesheet::SpriteSheet, particle::ParticlePresets, light::LightAnimPresets},
+    controller::{player, camera::CameraEffects},
+    math::Easing,
+    shader::UniformValue,
     event,
 };
 
@@ -34,12 +42,15 @@ macro_rules! unwrap_or_return {
 
 pub struct RustyLantern<'a> {
     spritesheet:        SpriteSheet,
+    particles:          ParticlePresets,
+    lights:             LightAnimPresets,
     world:              Option<World>,
     dispatcher:         Option<Dispatcher<'a, 'a>>,
     time:               std::time::Instant,
 
     cursor:             Option<Entity>,
     cursor_pos:         (f64, f64),
+    aim_dir:            Vec2,
     player_contr:             Option<player::PlayerController>,
 }
 
@@ -47,12 +58,16 @@ impl<'a> RustyLantern<'a> {
     pub fn new() -> Self {
         Self {
             spritesheet: SpriteSheet::from_layout("assets/textures/sprites.ron".into()).unwrap(),
-            world: None,
+            particles: ParticlePresets::from_layout("assets/particles/presets.ron".into()).unwrap(),
+            lights: LightAnimPresets::from_layout("assets/lights/presets.ron".into()).unwrap(),
+            // Created early so the engine can add the window resources before init
+            world: Some(World::new()),
             dispatcher: None,
             time: std::time::Instant::now(),
 
             cursor: None,
             cursor_pos: (0.0, 0.0),
+            aim_dir: vec2(1.0, 0.0),
             player_contr: None,
         }
     }
@@ -61,38 +76,68 @@ impl<'a> RustyLantern<'a> {
 
 impl<'a> stoneng::EngineCore for RustyLantern<'a> {
     fn init(&mut self){
-        // Setup ECS
-        let mut world = World::new();
+        // Setup ECS, the engine has already inserted the window resources
+        let mut world = self.world.take().unwrap_or_else(World::new);
         world.insert(resource::DeltaTime(0.0));
-        world.insert(resource::WindowSize(800.0, 600.0));
         world.insert(resource::View(0.0 ,0.0, 0.0));
+        world.insert(resource::VirtualResolution::new(160, 120, ScalingMode::PixelPerfect));
+        // The game starts at midnight with the clock stopped, raise the rate for a day cycle
+        let day = resource::AmbientSchedule::from_layout("assets/lights/day.ron".into()).unwrap();
+        world.insert(resource::TimeOfDay::new(0.0, 0.0).with_schedule(day));
+        
+        // Post processing, only the vignette is on by default
+        let mut post = resource::PostProcessing::new();
+        post.push(PostPass::new("vignette", PostEffect::vignette()));
+        post.push(PostPass::new("bloom", PostEffect::bloom()));
+        post.push(PostPass::new("aberration", PostEffect::chromatic_aberration()));
+        post.push(PostPass::new("scanlines", PostEffect::scanlines()));
+        for pass in post.passes.iter_mut().skip(1) { pass.enabled = false; }
+        world.insert(post);
 
         let mut dispatcher = DispatcherBuilder::new()
             .with(system::movement::VelocitySys, "velocity", &[])
             .with(system::sprite::AnimSpriteSys, "anim_sprite", &[])
+            .with(system::camera::CameraEffectSys, "camera_effects", &[])
+            .with(system::particle::ParticleSys, "particles", &["velocity"])
+            .with(system::light::LightAnimSys, "light_anim", &[])
+            .with(system::light::TimeOfDaySys, "time_of_day", &[])
+            .with(system::tile::AutotileSys::default(), "autotile", &[])
+            .with(system::sprite::ParallaxSys, "parallax", &[])
             .with_thread_local(system::RenderSys::default())
+            .with_thread_local(system::sprite::ParallaxRenderSys::background())
             .with_thread_local(system::sprite::SpriteRenderSys::default())
+            .with_thread_local(system::sprite::PanelRenderSys::default())
             .with_thread_local(system::text::TextRenderSys::default())
             .with_thread_local(system::sprite::TileRenderSys::default())
+            .with_thread_local(system::particle::ParticleRenderSys::default())
+            .with_thread_local(system::sprite::ParallaxRenderSys::foreground())
             .with_thread_local(system::light::LightRenderSys::default())
+            .with_thread_local(system::debug::DebugDrawSys::default())
+            .with_thread_local(system::post::PostProcessSys::default())
+            .with_thread_local(system::PresentSys::default())
             .build();
  
         dispatcher.setup(&mut world);
+        // Debug shapes are toggled with F3
+        world.write_resource::<DebugDraw>().enabled = false;
         let tile = self.spritesheet.sprites
                 .get("human").unwrap()
                 .variants.get("unarmed").unwrap()
                 .clone();
-        let mut pos = component::Position { x: 32.0, y: 32.0, z: -5.0 };
-        let scale = component::Scale { x: 5.0, y: 5.0 };
+        let mut pos = component::Position { x: 6.0, y: 6.0, z: -5.0 };
+        let scale = component::Scale { x: 1.0, y: 1.0 };
         world.create_entity()
             .with(pos.clone())
             .with(scale.clone())
             .with(component::Color::default())
             .with(component::Sprite::from(tile.clone())) 
+            .with(component::Material::new(
+                    MaterialShader::builtin(BuiltinMaterial::Outline).unwrap())
+                .with("outline_color", UniformValue::Vec4(1.0, 0.2, 0.2, 1.0)))
             .build();
 
-        pos.x = 100.0;
-        pos.y = 100.0;
+        pos.x = 20.0;
+        pos.y = 20.0;
         let player_anim = tile.animations.get("idle"); 
         let player_entity = world.create_entity()
                 .with(pos)
@@ -100,17 +145,20 @@ impl<'a> stoneng::EngineCore for RustyLantern<'a> {
                 .with(component::Color::default())
                 .with(component::Sprite::from(tile.clone()))
                 .with(component::Animation::from(player_anim))
-                .with(component::PointLight { intensity: 400.0 })
+                .with(component::PointLight::new(40.0))
+                .with(component::SpotLight::new(140.0, 0.5, 1.1).with_color(1.0, 0.9, 0.7))
+                .with(component::LightAnimation::new(self.lights.get("lantern").unwrap())
+                    .fading_in(0.5))
                 .with(component::Velocity { x: 0.0, y: 0.0 })
                 .with(component::Text{ 
-                    content: String::from("Bobert"), size: 2.0, offset: (-25.0, 45.0) 
+                    content: String::from("Bobert"), size: 1.0, offset: (-15.0, 10.0) 
                 })
                 .build();
 
         self.player_contr = Some(
             player::PlayerController::new(
                 player_entity, 
-                player::MovementType::Instant(250.0)
+                player::MovementType::Instant(50.0)
                 )
             );
 
@@ -121,9 +169,11 @@ impl<'a> stoneng::EngineCore for RustyLantern<'a> {
         self.cursor = Some(
             world.create_entity()
                 .with(component::Position { x:0.0, y:0.0, z:1.0 })
-                .with(component::Scale { x: 3.0, y: 3.0 })
+                .with(component::Scale { x: 1.0, y: 1.0 })
                 .with(component::Sprite::from(cursor_sprite))
                 .with(component::Color::default())
+                .with(component::ParticleEmitter::stopped(
+                        self.particles.get("sparks").unwrap(), &self.spritesheet).unwrap())
                 .build()
         );
         
@@ -166,7 +216,7 @@ impl<'a> stoneng::EngineCore for RustyLantern<'a> {
         *dt_res = resource::DeltaTime(dt);
             // windowsize
         let win = world.read_resource::<resource::WindowSize>();
-        let (win_x, win_y) = (win.0, win.1);
+        let resolution = world.read_resource::<resource::VirtualResolution>();
 
         // Unwrap relevant entities       
         let player_contr = unwrap_or_return!(&mut self.player_contr);
@@ -181,8 +231,10 @@ impl<'a> stoneng::EngineCore for RustyLantern<'a> {
         {
             // Update cursor
             let mut cursor_pos = positions.get_mut(*cursor).unwrap();
-            cursor_pos.x = self.cursor_pos.0 as f32 + view_x;
-            cursor_pos.y = win_y - self.cursor_pos.1 as f32 + view_y;
+            let (target_x, target_y) = resolution.window_to_target(
+                &win, (self.cursor_pos.0 as f32, self.cursor_pos.1 as f32));
+            cursor_pos.x = target_x + view_x;
+            cursor_pos.y = target_y + view_y;
             cursor_vec = vec2(cursor_pos.x, cursor_pos.y);
         }
         
@@ -190,6 +242,24 @@ impl<'a> stoneng::EngineCore for RustyLantern<'a> {
         let player_pos = positions.get(player_contr.player).unwrap();
         let player_vec = vec2(player_pos.x, player_pos.y);
         let aim_dir = (cursor_vec-player_vec).normalize();
+        if !f32::is_nan(aim_dir.x) { self.aim_dir = aim_dir; }
+
+        // Point the lantern's beam along the aim
+        {
+            let mut spots = world.write_storage::<component::SpotLight>();
+            if let Some(beam) = spots.get_mut(player_contr.player) {
+                beam.aim((self.aim_dir.x, self.aim_dir.y));
+            }
+        }
+
+        // Visualize aiming
+        {
+            let mut debug = world.write_resource::<DebugDraw>();
+            let aim_end = player_vec + self.aim_dir * 15.0;
+            debug.arrow((player_vec.x, player_vec.y), (aim_end.x, aim_end.y), (1.0, 1.0, 0.0, 1.0));
+            debug.rect((player_vec.x - 5.0, player_vec.y), (player_vec.x + 5.0, player_vec.y + 10.0),
+                       (0.0, 1.0, 0.0, 1.0));
+        }
         
         // Determine walking/idle
         let vels = world.read_component::<component::Velocity>();
@@ -256,11 +326,28 @@ impl<'a> stoneng::EngineCore for RustyLantern<'a> {
             }
             
             if state {
+                // Toggle post processing passes
+                let pass = match key {
+                    KeyCode::Key1 => Some("vignette"),
+                    KeyCode::Key2 => Some("bloom"),
+                    KeyCode::Key3 => Some("aberration"),
+                    KeyCode::Key4 => Some("scanlines"),
+                    _ => None,
+                };
+                if let Some(pass) = pass {
+                    world.write_resource::<resource::PostProcessing>().toggle(pass);
+                }
+
+                if key == KeyCode::F3 {
+                    let mut debug = world.write_resource::<DebugDraw>();
+                    debug.enabled = !debug.enabled;
+                }
+
                 let dv = match key {
-                    KeyCode::Right => (10.0, 0.0),
-                    KeyCode::Left => (-10.0, 0.0),
-                    KeyCode::Up => (0.0, 10.0),
-                    KeyCode::Down => (0.0, -10.0),
+                    KeyCode::Right => (2.0, 0.0),
+                    KeyCode::Left => (-2.0, 0.0),
+                    KeyCode::Up => (0.0, 2.0),
+                    KeyCode::Down => (0.0, -2.0),
                     _ => (0.0, 0.0),
                 };
 
@@ -271,17 +358,36 @@ impl<'a> stoneng::EngineCore for RustyLantern<'a> {
         }
     }
 
-    fn mouse_btn(&mut self, event: event::MouseBtnEvent){}
+    fn mouse_btn(&mut self, event: event::MouseBtnEvent){
+        let world = unwrap_or_return!(&mut self.world);
+
+        // Recoil away from the cursor when firing
+        if event.button == event::MouseButton::Left && event.state == ElementState::Pressed {
+            let mut effects = world.write_resource::<CameraEffects>();
+            effects.kick((-self.aim_dir.x, -self.aim_dir.y), 2.0, 0.2, Easing::QuadOut);
+            effects.shake(0.4, 0.3, Easing::Linear);
+            effects.zoom_pulse(0.03, 0.15, Easing::CubicOut);
+
+            // Sparks at the cursor
+            let cursor = unwrap_or_return!(self.cursor);
+            if let Some(pos) = world.read_storage::<component::Position>().get(cursor) {
+                let mut debug = world.write_resource::<DebugDraw>();
+                debug.circle((pos.x, pos.y), 6.0, (1.0, 0.3, 0.3, 1.0)).lasting(0.5);
+                debug.label((pos.x, pos.y + 10.0), "bang", (1.0, 0.3, 0.3, 1.0)).lasting(0.5);
+            }
+            let mut emitters = world.write_component::<component::ParticleEmitter>();
+            if let Some(emitter) = emitters.get_mut(cursor) {
+                emitter.start();
+            }
+        }
+    }
 
     fn cursor_moved(&mut self, x: f64, y: f64) {
         self.cursor_pos = (x, y);
     }
 
-    fn resized(&mut self, x: u32, y: u32) {
-        if let Some(world) = &self.world {
-            let mut win = world.write_resource::<resource::WindowSize>();
-            *win = resource::WindowSize(x as f32, y as f32);
-        }
+    fn world(&mut self) -> Option<&mut World> {
+        self.world.as_mut()
     }
 }
 
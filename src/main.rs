@@ -4,7 +4,8 @@ use stoneng::Config;
 use game::RustyLantern;
 
 fn main() {
-    let config = Config::default(); 
+    let mut config = Config::default(); 
+    config.resizable = true;
     stoneng::start(config, move || {
         RustyLantern::new()
     });
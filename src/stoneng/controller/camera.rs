@@ -0,0 +1,203 @@
+use crate::math::{self, Easing};
+use crate::ecs::resource::View;
+use crate::renderer::Camera;
+
+/// The kind of a single camera effect, along with its strength.
+#[derive(Debug, Clone, Copy)]
+pub enum CameraEffectKind {
+    /// Adds trauma that is eased out over the effect's duration.
+    Shake { trauma: f32 },
+    /// Displaces the camera along a direction and eases back to rest.
+    Kick { direction: (f32, f32), distance: f32 },
+    /// Multiplies the zoom by `1 + amount` and eases back to rest.
+    ZoomPulse { amount: f32 },
+}
+
+/// A single timed camera effect.
+#[derive(Debug, Clone, Copy)]
+pub struct CameraEffect {
+    pub kind:       CameraEffectKind,
+    /// How many seconds the effect lasts
+    pub duration:   f32,
+    /// The curve used to fade the effect out over its duration
+    pub easing:     Easing,
+    elapsed:        f32,
+}
+impl CameraEffect {
+    pub fn new(kind: CameraEffectKind, duration: f32, easing: Easing) -> Self {
+        Self { kind, duration, easing, elapsed: 0.0 }
+    }
+
+    /// The remaining strength of the effect, from 1 (start) to 0 (finished).
+    pub fn strength(&self) -> f32 {
+        if self.duration <= 0.0 { return 0.0; }
+        1.0 - self.easing.ease(self.elapsed / self.duration)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+/// A smooth transition of the camera's base zoom.
+#[derive(Debug, Clone, Copy)]
+struct ZoomTransition {
+    from:       f32,
+    to:         f32,
+    duration:   f32,
+    elapsed:    f32,
+    easing:     Easing,
+}
+
+/// Camera effects layered on top of the logical camera position.
+///
+/// This is stored as a world resource so that any system can trigger an
+/// effect. The effects never modify `View`; instead renderers combine the two
+/// with `apply` to find the camera they draw with. Gameplay code can keep
+/// reading `View` as the "true" camera position.
+///
+/// `CameraEffectSys` must be dispatched to advance the effects each frame.
+#[derive(Debug, Clone)]
+pub struct CameraEffects {
+    /// The furthest, in world units, that shaking can displace the camera
+    pub max_shake:      f32,
+    /// How quickly the shake noise changes, in samples per second
+    pub shake_frequency: f32,
+
+    effects:    Vec<CameraEffect>,
+    zoom:       f32,
+    transition: Option<ZoomTransition>,
+    time:       f32,
+
+    /// The offset and zoom computed by the last `tick`
+    offset:     (f32, f32),
+    zoom_scale: f32,
+}
+impl Default for CameraEffects {
+    fn default() -> Self {
+        Self {
+            max_shake: 2.0,
+            shake_frequency: 25.0,
+            effects: Vec::new(),
+            zoom: 1.0,
+            transition: None,
+            time: 0.0,
+            offset: (0.0, 0.0),
+            zoom_scale: 1.0,
+        }
+    }
+}
+
+impl CameraEffects {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a trauma-based shake. Trauma stacks between effects and is clamped to 1,
+    /// with the displacement scaling by trauma squared.
+    pub fn shake(&mut self, trauma: f32, duration: f32, easing: Easing) {
+        self.push(CameraEffect::new(CameraEffectKind::Shake { trauma }, duration, easing));
+    }
+
+    /// Kicks the camera `distance` units along `direction`, easing back over `duration`.
+    ///
+    /// The direction is normalized, a zero direction results in no kick.
+    pub fn kick(&mut self, direction: (f32, f32), distance: f32, duration: f32, easing: Easing) {
+        let len = (direction.0 * direction.0 + direction.1 * direction.1).sqrt();
+        if len <= f32::EPSILON { return; }
+        let direction = (direction.0 / len, direction.1 / len);
+        self.push(CameraEffect::new(
+            CameraEffectKind::Kick { direction, distance }, duration, easing));
+    }
+
+    /// Pulses the zoom by `amount` (e.g. 0.1 for 10% closer) and eases back.
+    pub fn zoom_pulse(&mut self, amount: f32, duration: f32, easing: Easing) {
+        self.push(CameraEffect::new(CameraEffectKind::ZoomPulse { amount }, duration, easing));
+    }
+
+    /// Smoothly transitions the base zoom to `zoom` over `duration` seconds.
+    pub fn zoom_to(&mut self, zoom: f32, duration: f32, easing: Easing) {
+        if duration <= 0.0 {
+            self.zoom = zoom;
+            self.transition = None;
+            return;
+        }
+        self.transition = Some(ZoomTransition {
+            from: self.zoom, to: zoom, duration, elapsed: 0.0, easing,
+        });
+    }
+
+    /// Adds an arbitrary effect.
+    pub fn push(&mut self, effect: CameraEffect) {
+        self.effects.push(effect);
+    }
+
+    /// Removes all active effects, leaving the base zoom as is.
+    pub fn clear(&mut self) {
+        self.effects.clear();
+    }
+
+    /// The base zoom, excluding any pulses.
+    pub fn zoom(&self) -> f32 { self.zoom }
+
+    /// The current trauma level, in `[0, 1]`.
+    pub fn trauma(&self) -> f32 {
+        self.effects.iter()
+            .map(|e| match e.kind {
+                CameraEffectKind::Shake { trauma } => trauma * e.strength(),
+                _ => 0.0,
+            })
+            .sum::<f32>()
+            .clamp(0.0, 1.0)
+    }
+
+    /// Advances all effects by `dt` seconds and recomputes the camera offset.
+    pub fn tick(&mut self, dt: f32) {
+        self.time += dt;
+
+        // Base zoom transition
+        if let Some(tr) = &mut self.transition {
+            tr.elapsed += dt;
+            let t = tr.easing.ease(tr.elapsed / tr.duration);
+            self.zoom = math::lerp(tr.from, tr.to, t);
+            if tr.elapsed >= tr.duration { self.transition = None; }
+        }
+
+        for effect in self.effects.iter_mut() {
+            effect.elapsed += dt;
+        }
+        self.effects.retain(|e| !e.is_finished());
+
+        // Shake, sampled from smooth noise so the motion isn't jittery
+        let trauma = self.trauma();
+        let shake = self.max_shake * trauma * trauma;
+        let sample = self.time * self.shake_frequency;
+        let mut offset = (shake * math::noise1d(sample, 0), shake * math::noise1d(sample, 1));
+
+        let mut zoom_scale = 1.0;
+        for effect in self.effects.iter() {
+            match effect.kind {
+                CameraEffectKind::Kick { direction, distance } => {
+                    let d = distance * effect.strength();
+                    offset.0 += direction.0 * d;
+                    offset.1 += direction.1 * d;
+                },
+                CameraEffectKind::ZoomPulse { amount } => {
+                    zoom_scale *= 1.0 + amount * effect.strength();
+                },
+                CameraEffectKind::Shake { .. } => {},
+            }
+        }
+
+        self.offset = offset;
+        self.zoom_scale = zoom_scale;
+    }
+
+    /// Combines the logical camera position with the active effects.
+    pub fn apply(&self, view: &View) -> Camera {
+        Camera {
+            pos:  (view.0 + self.offset.0, view.1 + self.offset.1, view.2),
+            zoom: self.zoom * self.zoom_scale,
+        }
+    }
+}
@@ -1,3 +1,3 @@
 pub mod player;
-mod camera;
+pub mod camera;
 
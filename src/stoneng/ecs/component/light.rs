@@ -0,0 +1,270 @@
+use std::sync::{Arc, atomic::{AtomicU32, Ordering}};
+use specs::{Component, DenseVecStorage};
+
+use crate::{
+    ecs::component::Position,
+    model::light::{LightAnimPreset, LightSignal},
+    renderer::{light::RenderLight, shadow::RenderOccluder},
+};
+
+/// Seeds handed to new LightAnimations, so their noise doesn't flicker in step.
+static NEXT_LIGHT_SEED: AtomicU32 = AtomicU32::new(1);
+
+#[derive(Debug, Component, Clone, Copy)]
+#[storage(DenseVecStorage)]
+pub struct PointLight {
+    /// The radius of the light in world units
+    pub intensity: f32,
+    /// RGB from 0-1
+    pub color: (f32, f32, f32),
+}
+impl PointLight {
+    /// A white light.
+    pub fn new(intensity: f32) -> Self {
+        Self { intensity, color: (1.0, 1.0, 1.0) }
+    }
+
+    pub fn with_color(mut self, r: f32, g: f32, b: f32) -> Self {
+        self.color = (r, g, b);
+        self
+    }
+}
+impl From<(&Position, &PointLight)> for RenderLight {
+    fn from(data: (&Position, &PointLight)) -> Self {
+        let (p, l) = data;
+        RenderLight::point((p.x, p.y), l.intensity, l.color)
+    }
+}
+
+/// A light shining in a cone, such as a lantern's beam.
+///
+/// The cone can follow an entity's aim by updating it with `aim`.
+#[derive(Debug, Component, Clone, Copy)]
+#[storage(DenseVecStorage)]
+pub struct SpotLight {
+    /// How far the light reaches in world units
+    pub range: f32,
+    /// RGB from 0-1
+    pub color: (f32, f32, f32),
+    /// The direction the light shines in, in radians counter-clockwise from +x
+    pub angle: f32,
+    /// The full width in radians of the cone's fully lit center
+    pub inner_angle: f32,
+    /// The full width in radians of the cone, the light fades out between
+    /// the inner and outer angles
+    pub outer_angle: f32,
+    /// The exponent of the falloff with distance, 2 matches a PointLight.
+    /// Higher values fade sooner, lower values keep the light bright for longer.
+    pub falloff: f32,
+}
+impl SpotLight {
+    /// A white light shining along +x.
+    pub fn new(range: f32, inner_angle: f32, outer_angle: f32) -> Self {
+        Self { range, color: (1.0, 1.0, 1.0), angle: 0.0, inner_angle, outer_angle, falloff: 2.0 }
+    }
+
+    pub fn with_color(mut self, r: f32, g: f32, b: f32) -> Self {
+        self.color = (r, g, b);
+        self
+    }
+
+    pub fn with_falloff(mut self, falloff: f32) -> Self {
+        self.falloff = falloff;
+        self
+    }
+
+    /// Points the light along a direction. Zero vectors leave it unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// # use stoneng::ecs::component::SpotLight;
+    /// let mut light = SpotLight::new(120.0, 0.5, 1.0);
+    /// light.aim((0.0, 2.0));
+    /// assert!((light.angle - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+    /// light.aim((0.0, 0.0));
+    /// assert!((light.angle - std::f32::consts::FRAC_PI_2).abs() < 1e-6);
+    /// ```
+    pub fn aim(&mut self, direction: (f32, f32)) {
+        if direction.0 == 0.0 && direction.1 == 0.0 { return; }
+        if direction.0.is_nan() || direction.1.is_nan() { return; }
+        self.angle = direction.1.atan2(direction.0);
+    }
+}
+impl From<(&Position, &SpotLight)> for RenderLight {
+    fn from(data: (&Position, &SpotLight)) -> Self {
+        let (p, l) = data;
+        RenderLight::spot((p.x, p.y), l.range, l.color, l.angle,
+                          (l.inner_angle / 2.0, l.outer_angle / 2.0), l.falloff)
+    }
+}
+
+/// Blocks light, casting shadows from the LightRenderSys.
+///
+/// The occluder is a rectangle centered on the entity's Position. Wall tiles
+/// occlude without one.
+#[derive(Debug, Component, Clone, Copy)]
+#[storage(DenseVecStorage)]
+pub struct Occluder {
+    /// Size of the rectangle in world units
+    pub size:   (f32, f32),
+    /// Offset of the rectangle's center from the Position
+    pub offset: (f32, f32),
+}
+impl Occluder {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self { size: (width, height), offset: (0.0, 0.0) }
+    }
+}
+impl From<(&Position, &Occluder)> for RenderOccluder {
+    fn from(data: (&Position, &Occluder)) -> Self {
+        let (p, o) = data;
+        RenderOccluder::centered((p.x + o.offset.0, p.y + o.offset.1), o.size)
+    }
+}
+
+/// Animates the PointLight or SpotLight on the same entity, flickering,
+/// pulsing or fading it.
+///
+/// The LightAnimSys advances the animation and the LightRenderSys applies it
+/// when drawing, leaving the light's own settings untouched.
+#[derive(Debug, Component, Clone)]
+#[storage(DenseVecStorage)]
+pub struct LightAnimation {
+    pub preset: Arc<LightAnimPreset>,
+    /// Seconds since the animation started
+    pub time:   f32,
+    /// Playback speed, 1 is real time
+    pub speed:  f32,
+    /// Picks the animation's noise, different seeds flicker differently
+    pub seed:   u32,
+
+    /// The current fade multiplier, from 0 (off) to 1
+    fade:           f32,
+    fade_target:    f32,
+    /// Change in fade per second
+    fade_rate:      f32,
+
+    brightness: f32,
+    radius:     f32,
+    tint:       (f32, f32, f32),
+}
+impl LightAnimation {
+    pub fn new(preset: Arc<LightAnimPreset>) -> Self {
+        let mut animation = Self {
+            preset,
+            time: 0.0,
+            speed: 1.0,
+            seed: NEXT_LIGHT_SEED.fetch_add(1, Ordering::Relaxed),
+            fade: 1.0,
+            fade_target: 1.0,
+            fade_rate: 0.0,
+            brightness: 1.0,
+            radius: 1.0,
+            tint: (1.0, 1.0, 1.0),
+        };
+        animation.advance(0.0);
+        animation
+    }
+
+    /// Starts the light off and fades it in over `seconds`.
+    pub fn fading_in(mut self, seconds: f32) -> Self {
+        self.fade = 0.0;
+        self.fade_in(seconds);
+        self
+    }
+
+    /// Fades the light to full strength over `seconds`.
+    pub fn fade_in(&mut self, seconds: f32) {
+        self.fade_to(1.0, seconds);
+    }
+
+    /// Fades the light out over `seconds`.
+    pub fn fade_out(&mut self, seconds: f32) {
+        self.fade_to(0.0, seconds);
+    }
+
+    /// Fades the light to `target`, from 0 (off) to 1, over `seconds`.
+    /// A duration of 0 jumps straight to the target.
+    pub fn fade_to(&mut self, target: f32, seconds: f32) {
+        self.fade_target = target.clamp(0.0, 1.0);
+        if seconds > 0.0 {
+            self.fade_rate = (self.fade_target - self.fade).abs() / seconds;
+        } else {
+            self.fade = self.fade_target;
+            self.fade_rate = 0.0;
+        }
+    }
+
+    /// The current fade multiplier, from 0 (off) to 1.
+    pub fn fade(&self) -> f32 {
+        self.fade
+    }
+
+    /// Whether the light has finished fading out.
+    pub fn is_faded_out(&self) -> bool {
+        self.fade <= 0.0 && self.fade_target <= 0.0
+    }
+
+    /// Moves the animation and fade forwards by `dt` seconds.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use stoneng::{ecs::component::LightAnimation, model::light::LightAnimPreset};
+    /// let mut light = LightAnimation::new(Arc::new(LightAnimPreset::default()))
+    ///     .fading_in(2.0);
+    /// light.advance(1.0);
+    /// assert_eq!(light.fade(), 0.5);
+    /// assert_eq!(light.brightness(), 0.5);
+    ///
+    /// light.fade_out(0.5);
+    /// light.advance(1.0);
+    /// assert!(light.is_faded_out());
+    /// ```
+    pub fn advance(&mut self, dt: f32) {
+        self.time += dt * self.speed;
+        let step = self.fade_rate * dt;
+        self.fade = if self.fade < self.fade_target {
+            (self.fade + step).min(self.fade_target)
+        } else {
+            (self.fade - step).max(self.fade_target)
+        };
+
+        let preset = &self.preset;
+        let curve_time = preset.curve_time(self.time);
+        let signal_time = |signal: &LightSignal| match signal {
+            LightSignal::Curve(_) => curve_time,
+            _ => self.time,
+        };
+        self.brightness = preset.brightness.sample(signal_time(&preset.brightness), self.seed).max(0.0);
+        // Offset the seed so that brightness and radius don't move together
+        self.radius = preset.radius.sample(signal_time(&preset.radius), self.seed ^ 0x5bd1_e995).max(0.0);
+        self.tint = preset.color.as_ref()
+            .and_then(|curve| curve.sample(curve_time))
+            .unwrap_or((1.0, 1.0, 1.0));
+    }
+
+    /// The current multiplier on the light's color, including the fade.
+    pub fn brightness(&self) -> f32 {
+        self.brightness * self.fade
+    }
+
+    /// The current multiplier on the light's reach.
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// The current multiplier on each of the light's color channels.
+    pub fn tint(&self) -> (f32, f32, f32) {
+        self.tint
+    }
+
+    /// Applies the current state of the animation to a light being drawn.
+    pub fn apply(&self, light: &mut RenderLight) {
+        let brightness = self.brightness();
+        light.intensity *= self.radius;
+        light.color.0 *= self.tint.0 * brightness;
+        light.color.1 *= self.tint.1 * brightness;
+        light.color.2 *= self.tint.2 * brightness;
+    }
+}
@@ -0,0 +1,72 @@
+use std::sync::Arc;
+use specs::{Component, DenseVecStorage};
+
+use crate::{
+    ecs::component::Color,
+    renderer::{backend::TextureHandle, material::MaterialShader},
+    shader::UniformValue,
+};
+
+/// Draws an entity's Sprite with a custom shader and uniform values.
+///
+/// Sprites sharing an equal Material (same shader and values) are drawn
+/// together in a single batch.
+#[derive(Debug, Component, Clone)]
+#[storage(DenseVecStorage)]
+pub struct Material {
+    pub shader:     Arc<MaterialShader>,
+    /// Values set on the shader before drawing, by uniform name
+    pub uniforms:   Vec<(String, UniformValue)>,
+    /// Extra textures, by sampler name. These are bound to texture units
+    /// from 2 upwards, as units 0 and 1 hold the sprite sheet and palettes.
+    /// They must belong to the backend drawing the sprites.
+    pub textures:   Vec<(String, TextureHandle)>,
+}
+impl Material {
+    pub fn new(shader: Arc<MaterialShader>) -> Self {
+        Self { shader, uniforms: Vec::new(), textures: Vec::new() }
+    }
+
+    /// Sets a uniform value, replacing any previous value of the same name.
+    pub fn set(&mut self, name: &str, value: UniformValue) {
+        match self.uniforms.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value,
+            None => self.uniforms.push((name.into(), value)),
+        }
+    }
+
+    pub fn set_float(&mut self, name: &str, value: f32) {
+        self.set(name, UniformValue::Float(value));
+    }
+
+    pub fn set_color(&mut self, name: &str, color: Color) {
+        self.set(name, UniformValue::Vec4(color.r, color.g, color.b, color.a));
+    }
+
+    /// Sets an extra texture, replacing any previous texture of the same name.
+    pub fn set_texture(&mut self, name: &str, texture: TextureHandle) {
+        match self.textures.iter_mut().find(|(n, _)| n == name) {
+            Some((_, t)) => *t = texture,
+            None => self.textures.push((name.into(), texture)),
+        }
+    }
+
+    /// Builder style version of `set`.
+    pub fn with(mut self, name: &str, value: UniformValue) -> Self {
+        self.set(name, value);
+        self
+    }
+
+    /// Builder style version of `set_texture`.
+    pub fn with_texture(mut self, name: &str, texture: TextureHandle) -> Self {
+        self.set_texture(name, texture);
+        self
+    }
+}
+impl PartialEq for Material {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.shader, &other.shader) &&
+        self.uniforms == other.uniforms &&
+        self.textures == other.textures
+    }
+}
@@ -2,12 +2,13 @@ pub mod transform;
 pub mod sprite;
 pub mod physics;
 pub mod tile;
+pub mod material;
+pub mod particle;
+pub mod parallax;
+pub mod light;
 
 use specs::{Component, DenseVecStorage};
-use crate::renderer::{
-    light::RenderLight,
-    text::RenderString,
-};
+use crate::renderer::text::RenderString;
 
 pub use transform::Position as Position;
 pub use transform::Scale as Scale;
@@ -16,28 +17,25 @@ pub use transform::Rotation as Rotation;
 pub use sprite::Color as Color;
 pub use sprite::Sprite as Sprite;
 pub use sprite::Animation as Animation;
+pub use sprite::Palette as Palette;
+pub use sprite::Panel as Panel;
 
 pub use physics::Velocity as Velocity;
 
 pub use tile::Tile as Tile;
 pub use tile::Floor as Floor;
 pub use tile::Wall as Wall;
+pub use tile::Autotile as Autotile;
 
+pub use material::Material as Material;
 
-#[derive(Debug, Component, Clone, Copy)]
-#[storage(DenseVecStorage)]
-pub struct PointLight {
-    pub intensity: f32,
-}
-impl From<(&Position, &PointLight)> for RenderLight {
-    fn from(data: (&Position, &PointLight)) -> Self {
-        let (p, l) = data;
-        Self {
-            pos: (p.x, p.y),
-            intensity: l.intensity
-        }
-    }
-}
+pub use particle::ParticleEmitter as ParticleEmitter;
+pub use parallax::ParallaxLayer as ParallaxLayer;
+
+pub use light::PointLight as PointLight;
+pub use light::SpotLight as SpotLight;
+pub use light::LightAnimation as LightAnimation;
+pub use light::Occluder as Occluder;
 
 
 #[derive(Debug, Component, Clone)]
@@ -0,0 +1,73 @@
+use std::sync::Arc;
+use specs::{Component, DenseVecStorage, World, WorldExt, Builder, Entity};
+
+use crate::{
+    EngineError,
+    model::{
+        spritesheet::{SpriteSheet, SpriteSchema},
+        parallax::{ParallaxSettings, ParallaxScene},
+    },
+};
+
+/// A backdrop or foreground sprite, repeated across the screen and moving at a
+/// fraction of the camera's speed.
+///
+/// Drawn by the background or foreground ParallaxRenderSys, depending on its
+/// depth, and scrolled by the ParallaxSys.
+#[derive(Debug, Component, Clone)]
+#[storage(DenseVecStorage)]
+pub struct ParallaxLayer {
+    pub schema:     Arc<SpriteSchema>,
+    pub settings:   ParallaxSettings,
+    /// Size of one repetition of the sprite, in world units
+    pub(crate) tile_size:   (f32, f32),
+    /// Distance moved by auto-scrolling, wrapped to the tile size
+    pub(crate) scrolled:    (f32, f32),
+}
+
+impl ParallaxLayer {
+    /// Creates a layer, looking up its sprite in the SpriteSheet.
+    pub fn new(settings: ParallaxSettings, sheet: &SpriteSheet) -> Result<Self, EngineError> {
+        let mut schema = sheet.sprites.get(&settings.sprite)
+            .ok_or_else(|| EngineError::AnimationError(
+                format!("Parallax sprite {:?} not found in sheet", settings.sprite)
+            ))?
+            .clone();
+        if let Some(variant) = &settings.variant {
+            schema = schema.variants.get(variant)
+                .ok_or_else(|| EngineError::AnimationError(
+                    format!("Parallax sprite {:?} has no variant {:?}", settings.sprite, variant)
+                ))?
+                .clone();
+        }
+
+        let tile = sheet.tile_width as f32;
+        let (dim_x, dim_y) = schema.dimensions;
+        let tile_size = (
+            (tile * (dim_x as f32 + 1.0) * settings.scale.0).abs().max(1.0),
+            (tile * (dim_y as f32 + 1.0) * settings.scale.1).abs().max(1.0),
+        );
+
+        Ok(Self { schema, settings, tile_size, scrolled: (0.0, 0.0) })
+    }
+
+    /// Creates an entity for each layer of a scene.
+    pub fn spawn_scene(scene: &ParallaxScene, sheet: &SpriteSheet, world: &mut World)
+            -> Result<Vec<Entity>, EngineError> {
+        scene.layers.iter()
+            .map(|settings| {
+                let layer = Self::new(settings.clone(), sheet)?;
+                Ok(world.create_entity().with(layer).build())
+            })
+            .collect()
+    }
+
+    /// The world position of the layer's first sprite for a camera at `cam_pos`.
+    pub fn origin(&self, cam_pos: (f32, f32)) -> (f32, f32) {
+        let s = &self.settings;
+        (
+            cam_pos.0 * (1.0 - s.factor.0) + s.offset.0 + self.scrolled.0,
+            cam_pos.1 * (1.0 - s.factor.1) + s.offset.1 + self.scrolled.1,
+        )
+    }
+}
@@ -0,0 +1,144 @@
+use std::sync::Arc;
+use specs::{Component, DenseVecStorage};
+
+use crate::{
+    EngineError,
+    model::{
+        spritesheet::{SpriteSheet, AnimationSchema},
+        particle::EmitterPreset,
+    },
+};
+
+/// A single simulated particle. Positions are in world space, so particles stay
+/// behind when their emitter moves.
+#[derive(Debug, Clone, Copy)]
+pub struct Particle {
+    pub pos:        (f32, f32),
+    pub z:          f32,
+    pub vel:        (f32, f32),
+    pub age:        f32,
+    pub lifetime:   f32,
+}
+impl Particle {
+    /// How far through its life the particle is, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        if self.lifetime > 0.0 { (self.age / self.lifetime).min(1.0) } else { 1.0 }
+    }
+}
+
+/// Spawns and holds short-lived sprites around an entity's Position.
+///
+/// Particles are simulated by the ParticleSys and drawn by the ParticleRenderSys.
+#[derive(Debug, Component, Clone)]
+#[storage(DenseVecStorage)]
+pub struct ParticleEmitter {
+    pub preset:     Arc<EmitterPreset>,
+    /// Whether new particles are spawned at the preset's rate
+    pub emitting:   bool,
+    /// Offset of the spawn point from the entity's Position
+    pub offset:     (f32, f32),
+
+    /// The sprite id drawn for each particle
+    pub(crate) sprite_id:   u32,
+    pub(crate) animation:   Option<Arc<AnimationSchema>>,
+    pub(crate) particles:   Vec<Particle>,
+    /// Fractional particles carried between frames
+    pub(crate) spawn_debt:  f32,
+    pub(crate) pending:     u32,
+    pub(crate) elapsed:     f32,
+}
+
+impl ParticleEmitter {
+    /// Creates an emitter, looking up the preset's sprite in the SpriteSheet.
+    ///
+    /// The emitter starts emitting immediately, including its burst.
+    pub fn new(preset: Arc<EmitterPreset>, sheet: &SpriteSheet) -> Result<Self, EngineError> {
+        let mut schema = sheet.sprites.get(&preset.sprite)
+            .ok_or_else(|| EngineError::AnimationError(
+                format!("Particle sprite {:?} not found in sheet", preset.sprite)
+            ))?
+            .clone();
+        if let Some(variant) = &preset.variant {
+            schema = schema.variants.get(variant)
+                .ok_or_else(|| EngineError::AnimationError(
+                    format!("Particle sprite {:?} has no variant {:?}", preset.sprite, variant)
+                ))?
+                .clone();
+        }
+        let animation = match &preset.animation {
+            Some(name) => Some(schema.animations.get(name)
+                .ok_or_else(|| EngineError::AnimationError(
+                    format!("Particle sprite {:?} has no animation {:?}", preset.sprite, name)
+                ))?
+                .clone()),
+            None => None,
+        };
+
+        Ok(Self {
+            emitting: true,
+            offset: (0.0, 0.0),
+            sprite_id: schema.root,
+            animation,
+            particles: Vec::with_capacity(preset.max_particles.min(1024)),
+            spawn_debt: 0.0,
+            pending: preset.burst,
+            elapsed: 0.0,
+            preset,
+        })
+    }
+
+    /// Creates an emitter that is not yet emitting, to be triggered later by
+    /// `burst` or `start`.
+    pub fn stopped(preset: Arc<EmitterPreset>, sheet: &SpriteSheet) -> Result<Self, EngineError> {
+        let mut emitter = Self::new(preset, sheet)?;
+        emitter.emitting = false;
+        emitter.pending = 0;
+        Ok(emitter)
+    }
+
+    /// Spawns `count` particles on the next update.
+    pub fn burst(&mut self, count: u32) {
+        self.pending += count;
+    }
+
+    /// Restarts emission, including the preset's burst and duration.
+    pub fn start(&mut self) {
+        self.emitting = true;
+        self.elapsed = 0.0;
+        self.pending += self.preset.burst;
+    }
+
+    /// Stops spawning particles. Existing particles live out their lifetime.
+    pub fn stop(&mut self) {
+        self.emitting = false;
+    }
+
+    /// Removes all alive particles.
+    pub fn clear(&mut self) {
+        self.particles.clear();
+        self.pending = 0;
+    }
+
+    /// True once the emitter has stopped and all of its particles have died.
+    pub fn is_finished(&self) -> bool {
+        !self.emitting && self.pending == 0 && self.particles.is_empty()
+    }
+
+    pub fn particles(&self) -> &[Particle] { &self.particles[..] }
+
+    /// The sprite id for a particle, advancing through the animation if there is one.
+    pub(crate) fn particle_sprite(&self, particle: &Particle) -> u32 {
+        let anim = match &self.animation {
+            Some(anim) if anim.frames > 1 => anim,
+            _ => return self.sprite_id,
+        };
+        let frames = anim.frames as u32;
+        let frame = if anim.frame_time > 0.0 {
+            let frame = (particle.age / anim.frame_time) as u32;
+            if anim.loops { frame % frames } else { frame.min(frames - 1) }
+        } else {
+            ((particle.progress() * frames as f32) as u32).min(frames - 1)
+        };
+        anim.root + frame
+    }
+}
@@ -4,8 +4,15 @@ use specs::{Component, VecStorage, DenseVecStorage};
 
 use crate::{
     ecs::component::transform::{Scale, Position, Rotation},
-    model::spritesheet::{SpriteSheet, SpriteSchema, AnimationSchema},
-    renderer::sprite::RenderSprite,
+    model::{
+        spritesheet::{SpriteSheet, SpriteSchema, AnimationSchema},
+        palette::Palettes,
+    },
+    renderer::{
+        sprite::{RenderSprite, FLAG_INDEXED},
+        nine_slice::RenderPanel,
+        text::VectorSpace,
+    },
 };
 
 #[repr(C)]
@@ -56,10 +63,71 @@ impl From<(&Sprite, &Position, &Scale, &Color)> for RenderSprite {
 
             color:       c.clone().into(),
 
+            sprite_id:      (spr.schema.root as i32 + spr.id_offset) as u32,
+            sprite_dims:    dim_x | (dim_y << 4),
+            sprite_flags:   if spr.schema.indexed { FLAG_INDEXED } else { 0 },
+            reserved:       0,
+        }
+    }
+}
+impl From<(&Sprite, &Position, &Scale, &Color, Option<&Palette>)> for RenderSprite {
+    /// Builds the struct used to render a sprite, colored by an optional palette
+    fn from(data: (&Sprite, &Position, &Scale, &Color, Option<&Palette>)) -> Self {
+        let (spr, p, s, c, pal) = data;
+        let mut sprite = RenderSprite::from((spr, p, s, c));
+        sprite.reserved = pal.map(|pal| pal.row).unwrap_or(0);
+        sprite
+    }
+}
+
+/// Selects the palette used to color an indexed Sprite, as a row of the
+/// `Palettes` resource. Indexed sprites without a Palette use the first row.
+///
+/// Palettes can be swapped at runtime by changing the row.
+#[derive(Debug, Component, Clone, Copy, PartialEq, Eq, Default)]
+#[storage(DenseVecStorage)]
+pub struct Palette {
+    pub row: u16,
+}
+impl Palette {
+    /// Finds a palette by name, if it exists.
+    pub fn named(palettes: &Palettes, name: &str) -> Option<Self> {
+        palettes.row(name).map(|row| Self { row })
+    }
+}
+
+/// Draws an entity's Sprite stretched over a rectangle, rather than at its art size.
+///
+/// Sprites of the `NineSlice` kind keep their borders intact, while others are
+/// simply stretched. The entity's Position is the bottom-left corner of the panel,
+/// in world units or in render target pixels depending on `space`.
+#[derive(Debug, Component, Clone, Copy)]
+#[storage(DenseVecStorage)]
+pub struct Panel {
+    pub size:   (f32, f32),
+    pub space:  VectorSpace,
+}
+impl Panel {
+    pub fn new(size: (f32, f32), space: VectorSpace) -> Self {
+        Self { size, space }
+    }
+}
+impl From<(&Sprite, &Position, &Panel, &Color)> for RenderPanel {
+    /// Builds the struct used to render a panel from it's components
+    fn from(data: (&Sprite, &Position, &Panel, &Color)) -> Self {
+        let (spr, p, panel, c) = data;
+        let (dim_x, dim_y) = spr.schema.dimensions;
+        let (left, right, top, bottom) = spr.schema.kind.insets();
+        Self {
+            translation:    (*p).into(),
+            size:           panel.size,
+            color:          (*c).into(),
+
             sprite_id:      (spr.schema.root as i32 + spr.id_offset) as u32,
             sprite_dims:    dim_x | (dim_y << 4),
             sprite_flags:   0,
             reserved:       0,
+            insets:         [left, right, top, bottom],
         }
     }
 }
@@ -1,16 +1,26 @@
 use std::sync::Arc;
-use specs::{Component, DenseVecStorage};
+use specs::{Component, DenseVecStorage, FlaggedStorage};
 
 use crate::{
     model::spritesheet::SpriteSchema, 
-    renderer::sprite::RenderSprite
+    renderer::sprite::{RenderSprite, FLAG_INDEXED, TILE_WIDTH},
 };
 
-#[derive(Debug, Component, Clone)]
-#[storage(DenseVecStorage)]
+#[derive(Debug, Clone)]
 pub struct Tile {
     pub pos: (i32, i32),
 }
+impl Component for Tile {
+    // Changes are tracked so that the occluders of walls are only rebuilt when moved
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+impl Tile {
+    /// The world position the tile's sprite is drawn at, for tiles of `scale`.
+    pub fn translation(&self, scale: (f32, f32)) -> (f32, f32) {
+        let tile = TILE_WIDTH as f32;
+        (self.pos.0 as f32 * scale.0 * tile, self.pos.1 as f32 * scale.1 * tile)
+    }
+}
 
 #[derive(Debug, Component, Clone)]
 #[storage(DenseVecStorage)]
@@ -18,11 +28,41 @@ pub struct Floor {
     pub schema: Arc<SpriteSchema>,
 }
 
-#[derive(Debug, Component, Clone)]
-#[storage(DenseVecStorage)]
+#[derive(Debug, Clone)]
 pub struct Wall {
     pub schema: Arc<SpriteSchema>,
 }
+impl Component for Wall {
+    // Changes are tracked so that occluders are only built for new walls
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
+
+/// Marks a Floor or Wall tile to have its sprite chosen by the AutotileSys.
+///
+/// The schema holds the `autotile` rules, and the variant picked from its
+/// neighbors is set as the tile's Floor or Wall schema.
+#[derive(Debug, Clone)]
+pub struct Autotile {
+    pub schema: Arc<SpriteSchema>,
+}
+impl Autotile {
+    pub fn new(schema: Arc<SpriteSchema>) -> Self {
+        Self { schema }
+    }
+
+    /// True if `other` counts as a neighbor of this tile.
+    pub fn connects(&self, other: &Autotile) -> bool {
+        if Arc::ptr_eq(&self.schema, &other.schema) { return true; }
+        match (&self.schema.autotile, &other.schema.autotile) {
+            (Some(rules), Some(other_rules)) => rules.connects(other_rules),
+            _ => false,
+        }
+    }
+}
+impl Component for Autotile {
+    // Changes are tracked so that only the affected tiles are updated
+    type Storage = FlaggedStorage<Self, DenseVecStorage<Self>>;
+}
 
 // Sorry for this...
 impl From<(
@@ -44,7 +84,7 @@ impl From<(
              f32)) -> Self {
 
         let (tile, color, schema, scale, z) = data;
-        let pos = (tile.pos.0 as f32 * scale.0 * 10.0, tile.pos.1 as f32 * scale.1 * 10.0);
+        let pos = tile.translation(scale);
         RenderSprite {
             translation:    (pos.0, pos.1, z),
             scale,
@@ -54,7 +94,7 @@ impl From<(
 
             sprite_id:      schema.root,
             sprite_dims:    0,
-            sprite_flags:   0,
+            sprite_flags:   if schema.indexed { FLAG_INDEXED } else { 0 },
             reserved:       0,
         }
     }
@@ -1,8 +1,327 @@
+use crate::EngineError;
+use crate::math::{Curve, Interpolate, lerp};
+use crate::renderer::{
+    upscale::{ScalingMode, ViewportRect, fit_viewport},
+    post::PostPass,
+    light::LightingMode,
+    shadow::ShadowMode,
+};
+
+use std::path;
+use serde::Deserialize;
+
 #[derive(Default, Clone, Copy, Debug)]
 pub struct DeltaTime(pub f64);
 
+/// The size of the window's framebuffer in physical pixels.
+///
+/// This is kept up to date by the engine when the game provides its world
+/// through `EngineCore::world`.
 #[derive(Default, Clone, Copy, Debug)]
 pub struct WindowSize(pub f32, pub f32);
 
+/// The size of the window in logical (DPI independent) pixels.
+///
+/// This is the physical `WindowSize` divided by the `ScaleFactor`.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct LogicalWindowSize(pub f32, pub f32);
+
+/// The ratio of physical to logical pixels of the window's current display.
+#[derive(Clone, Copy, Debug)]
+pub struct ScaleFactor(pub f64);
+impl Default for ScaleFactor { fn default() -> Self { Self(1.0) } }
+
 #[derive(Default, Clone, Copy, Debug)]
 pub struct View(pub f32, pub f32, pub f32);
+
+/// The resolution the scene is drawn at before being scaled up to the window.
+///
+/// World units map one-to-one with pixels of this resolution, so sprites are
+/// drawn at the size of their art. A width or height of 0 draws the scene
+/// at the window's size.
+#[derive(Clone, Copy, Debug)]
+pub struct VirtualResolution {
+    pub width:      u32,
+    pub height:     u32,
+    pub scaling:    ScalingMode,
+    /// The color of any letterbox or pillarbox bars
+    pub bar_color:  (f32, f32, f32, f32),
+}
+impl Default for VirtualResolution {
+    fn default() -> Self {
+        Self {
+            width: 0,
+            height: 0,
+            scaling: ScalingMode::PixelPerfect,
+            bar_color: (0.0, 0.0, 0.0, 1.0),
+        }
+    }
+}
+impl VirtualResolution {
+    pub fn new(width: u32, height: u32, scaling: ScalingMode) -> Self {
+        Self { width, height, scaling, ..Self::default() }
+    }
+
+    /// The size of the scene render target for a given window size.
+    pub fn target_size(&self, window: &WindowSize) -> (u32, u32) {
+        if self.width == 0 || self.height == 0 {
+            (window.0 as u32, window.1 as u32)
+        } else {
+            (self.width, self.height)
+        }
+    }
+
+    /// The area of the window that the scene is drawn to.
+    pub fn viewport(&self, window: &WindowSize) -> ViewportRect {
+        let (w, h) = self.target_size(window);
+        fit_viewport((w as f32, h as f32), (window.0, window.1), self.scaling)
+    }
+
+    /// Converts a window position (origin top-left, as reported by cursor events) 
+    /// into a position on the render target (origin bottom-left).
+    pub fn window_to_target(&self, window: &WindowSize, point: (f32, f32)) -> (f32, f32) {
+        let (w, h) = self.target_size(window);
+        let rect = self.viewport(window);
+        let x = (point.0 - rect.x) / rect.width * w as f32;
+        let y = (window.1 - point.1 - rect.y) / rect.height * h as f32;
+        (x, y)
+    }
+}
+
+/// The size, in pixels, of the target the scene is currently being drawn to.
+///
+/// This is updated by `RenderSys` at the start of each frame and should be
+/// used by rendering systems in place of `WindowSize`.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct RenderSize(pub f32, pub f32);
+
+/// Multiplier on the size of Tiles, relative to their art size.
+///
+/// Read by the TileRenderSys to place and size tiles, and by the LightRenderSys
+/// so that walls occlude their sprites.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileScale(pub f32, pub f32);
+impl Default for TileScale { fn default() -> Self { Self(1.0, 1.0) } }
+
+/// How the LightRenderSys draws lights over the scene.
+///
+/// Read every frame, so lighting can be changed at runtime. See
+/// `LightRenderer` for what each setting does.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Lighting {
+    pub mode:           LightingMode,
+    /// The size of lightmap pixels and dither cells in screen pixels
+    pub dither_scale:   f32,
+    /// The color of unlit areas
+    pub shadow_color:   (f32, f32, f32),
+    /// How opaque `shadow_color` is where there is no light, from 0-1
+    pub darkness:       f32,
+    /// Only mask the scene with the lightmap, ignoring the lights' colors
+    pub monochrome:     bool,
+    pub shadows:        ShadowMode,
+}
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            mode: LightingMode::default(),
+            dither_scale: 1.0,
+            shadow_color: (0.1, 0.1, 0.15),
+            darkness: 0.975,
+            monochrome: false,
+            shadows: ShadowMode::default(),
+        }
+    }
+}
+
+/// Light reaching the whole scene, on top of which lights are drawn.
+///
+/// With no strength only lit areas are visible, as underground or at night,
+/// while a strength of 1 lights everything, as outdoors by day. The color
+/// tints the scene like a light's does.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct AmbientLight {
+    /// RGB from 0-1
+    pub color:      (f32, f32, f32),
+    /// From 0-1
+    pub strength:   f32,
+}
+impl Default for AmbientLight {
+    fn default() -> Self {
+        Self { color: (1.0, 1.0, 1.0), strength: 0.0 }
+    }
+}
+impl AmbientLight {
+    pub fn new(color: (f32, f32, f32), strength: f32) -> Self {
+        Self { color, strength }
+    }
+
+    /// The color scaled by the strength, as added to the lightmap.
+    pub fn light(&self) -> (f32, f32, f32) {
+        let s = self.strength.clamp(0.0, 1.0);
+        (self.color.0 * s, self.color.1 * s, self.color.2 * s)
+    }
+}
+impl Interpolate for AmbientLight {
+    fn interpolate(a: Self, b: Self, t: f32) -> Self {
+        Self {
+            color: Interpolate::interpolate(a.color, b.color, t),
+            strength: lerp(a.strength, b.strength, t),
+        }
+    }
+}
+
+/// The AmbientLight over a day, as keyframes by hour from 0 up to 24.
+///
+/// The schedule wraps around midnight, so the last keyframe of a day blends
+/// into the first of the next.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(from = "Vec<(f32, AmbientLight)>")]
+pub struct AmbientSchedule {
+    curve: Curve<AmbientLight>,
+}
+impl From<Vec<(f32, AmbientLight)>> for AmbientSchedule {
+    fn from(keys: Vec<(f32, AmbientLight)>) -> Self {
+        Self::new(keys)
+    }
+}
+impl AmbientSchedule {
+    pub fn new(keys: Vec<(f32, AmbientLight)>) -> Self {
+        let mut keys: Vec<(f32, AmbientLight)> = keys.into_iter()
+            .map(|(hour, ambient)| (hour.rem_euclid(24.0), ambient))
+            .collect();
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        // Repeat the ends a day away, so that sampling blends across midnight
+        if let (Some(&first), Some(&last)) = (keys.first(), keys.last()) {
+            keys.insert(0, (last.0 - 24.0, last.1));
+            keys.push((first.0 + 24.0, first.1));
+        }
+        Self { curve: Curve::new(keys) }
+    }
+
+    /// The ambient light at an hour of the day. Empty schedules return `None`.
+    pub fn sample(&self, hour: f32) -> Option<AmbientLight> {
+        self.curve.sample(hour.rem_euclid(24.0))
+    }
+
+    /// Takes a schedule in Rusty Object Notation, a list of `(hour, ambient)`.
+    ///
+    /// # Example
+    /// ```
+    /// # use stoneng::ecs::resource::*;
+    /// let layout = r#"[
+    ///     (6.0,  (color: (1.0, 0.7, 0.5), strength: 0.6)),
+    ///     (12.0, (color: (1.0, 1.0, 1.0), strength: 1.0)),
+    ///     (22.0, (color: (0.3, 0.3, 0.6), strength: 0.0)),
+    /// ]"#;
+    /// let schedule = AmbientSchedule::from_string(layout.into()).unwrap();
+    ///
+    /// assert_eq!(schedule.sample(9.0).unwrap().strength, 0.8);
+    /// // Between 22:00 and 6:00 the schedule wraps around midnight
+    /// assert_eq!(schedule.sample(2.0).unwrap().strength, 0.3);
+    /// ```
+    pub fn from_string(layout: String) -> Result<Self, EngineError> {
+        Ok(ron::from_str::<AmbientSchedule>(&layout)?)
+    }
+
+    /// Takes a path to a schedule file in Rusty Object Notation and deserializes it.
+    /// The format can be found in AmbientSchedule::from_string().
+    pub fn from_layout(path_to_layout: String) -> Result<Self, EngineError> {
+        let layout_string = std::fs::read_to_string(path::PathBuf::from(&path_to_layout))?;
+        Self::from_string(layout_string)
+    }
+}
+
+/// The in-game time of day, advanced by the TimeOfDaySys.
+///
+/// With a schedule the AmbientLight follows it through the day. Gameplay can
+/// read the time, or set it to jump ahead.
+#[derive(Clone, Debug, Default)]
+pub struct TimeOfDay {
+    /// The hour of the day, from 0 up to 24
+    pub hour:       f32,
+    /// Whole days passed
+    pub day:        u32,
+    /// In-game hours passed per real second, 0 stops the clock
+    pub rate:       f32,
+    pub schedule:   Option<AmbientSchedule>,
+}
+impl TimeOfDay {
+    pub fn new(hour: f32, rate: f32) -> Self {
+        Self { hour: hour.rem_euclid(24.0), day: 0, rate, schedule: None }
+    }
+
+    pub fn with_schedule(mut self, schedule: AmbientSchedule) -> Self {
+        self.schedule = Some(schedule);
+        self
+    }
+
+    /// Moves the clock forwards by `hours`, counting the days passed.
+    ///
+    /// # Example
+    /// ```
+    /// # use stoneng::ecs::resource::TimeOfDay;
+    /// let mut time = TimeOfDay::new(22.0, 1.0);
+    /// time.advance(3.5);
+    /// assert_eq!(time.day, 1);
+    /// assert_eq!(time.clock(), (1, 30));
+    /// ```
+    pub fn advance(&mut self, hours: f32) {
+        let total = self.hour + hours.max(0.0);
+        self.day += (total / 24.0).floor() as u32;
+        self.hour = total.rem_euclid(24.0);
+    }
+
+    /// The time as (hours, minutes) on a 24 hour clock.
+    pub fn clock(&self) -> (u32, u32) {
+        let minutes = (self.hour * 60.0) as u32;
+        ((minutes / 60) % 24, minutes % 60)
+    }
+
+    /// The scheduled ambient light for the current hour, if there is a schedule.
+    pub fn ambient(&self) -> Option<AmbientLight> {
+        self.schedule.as_ref().and_then(|schedule| schedule.sample(self.hour))
+    }
+}
+
+/// The ordered chain of full-screen effects applied to the scene.
+///
+/// Passes run in order after the scene has been drawn and before it is scaled
+/// to the window. They can be toggled or tweaked at runtime by name.
+#[derive(Default, Clone, Debug)]
+pub struct PostProcessing {
+    pub passes: Vec<PostPass>,
+}
+impl PostProcessing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a pass to the end of the chain.
+    pub fn push(&mut self, pass: PostPass) {
+        self.passes.push(pass);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PostPass> {
+        self.passes.iter().find(|p| p.name == name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut PostPass> {
+        self.passes.iter_mut().find(|p| p.name == name)
+    }
+
+    /// Enables or disables a pass, returning false if it doesn't exist.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.get_mut(name) {
+            Some(pass) => { pass.enabled = enabled; true },
+            None => false,
+        }
+    }
+
+    /// Flips a pass between enabled and disabled, returning false if it doesn't exist.
+    pub fn toggle(&mut self, name: &str) -> bool {
+        match self.get_mut(name) {
+            Some(pass) => { pass.enabled = !pass.enabled; true },
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use specs::{System, Read, Write};
+use crate::{
+    controller::camera::CameraEffects,
+    ecs::resource::DeltaTime,
+};
+
+/// A system to advance the camera effects resource.
+///
+/// (resource::CameraEffects, resource::DeltaTime)
+///
+/// This should be dispatched before any rendering systems so that the effects
+/// applied to the frame are up to date.
+#[derive(Default)]
+pub struct CameraEffectSys;
+impl<'a> System<'a> for CameraEffectSys {
+    type SystemData = (Write<'a, CameraEffects>,
+                       Read<'a, DeltaTime>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut effects, dt) = data;
+        effects.tick(dt.0 as f32);
+    }
+}
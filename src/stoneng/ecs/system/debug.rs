@@ -0,0 +1,64 @@
+use specs::{System, Read, Write, SystemData};
+use specs::prelude::*;
+use crate::{
+    log,
+    renderer::gl_debug,
+    ecs::resource::{DeltaTime, RenderSize, View},
+    renderer::{
+        debug::{DebugDraw, DebugRenderer, DebugVertex},
+        text::{RenderString, TextRenderer},
+    },
+    controller::camera::CameraEffects,
+};
+
+/// Draws the shapes pushed to the `DebugDraw` resource, then ages them.
+///
+/// This should run after the rest of the scene has been drawn, so that the
+/// shapes end up on top. In release builds it does nothing.
+#[derive(Default)]
+pub struct DebugDrawSys {
+    renderer:       DebugRenderer,
+    text_renderer:  TextRenderer,
+    lines:          Vec<DebugVertex>,
+    labels:         Vec<RenderString>,
+}
+impl<'a> System<'a> for DebugDrawSys {
+    type SystemData = (Write<'a, DebugDraw>,
+                       Read<'a, RenderSize>,
+                       Read<'a, View>,
+                       Read<'a, CameraEffects>,
+                       Read<'a, DeltaTime>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut debug, window, view, effects, dt) = data;
+        if !debug.is_active() {
+            debug.clear();
+            return;
+        }
+        let window = (window.0, window.1);
+        let cam = effects.apply(&view);
+
+        self.lines.clear();
+        self.labels.clear();
+        DebugRenderer::build(&debug, &mut self.lines, &mut self.labels);
+        gl_debug::pass("debug draw", || {
+            self.renderer.render(&self.lines, window, &cam);
+            self.text_renderer.render(&self.labels, window, &cam);
+        });
+
+        debug.tick(dt.0 as f32);
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        if !crate::renderer::debug::DEBUG_DRAW_AVAILABLE { return; }
+
+        self.renderer = DebugRenderer::new();
+        self.renderer.init().unwrap_or_else(|err| log::error("debug draw", err));
+        self.text_renderer = TextRenderer::new();
+        self.text_renderer.init(
+            include_bytes!("../../../../assets/textures/fonts/dogica.png"),
+            8,
+        ).unwrap_or_else(|err| log::error("debug draw", err));
+    }
+}
@@ -1,44 +1,177 @@
 use specs::{ReadStorage, WriteStorage, System, Join, Read, SystemData};
 use specs::prelude::*;
-use std::sync::Arc;
+use specs::storage::ComponentEvent;
+use std::{collections::{HashMap, HashSet}, sync::Arc};
 use crate::{
+    log,
+    renderer::gl_debug,
     model::spritesheet::{SpriteSheet, AnimationSchema},
-    ecs::resource::{DeltaTime, WindowSize, View},
-    ecs::component::{Color, Sprite, Position, Animation, PointLight},
+    ecs::resource::{DeltaTime, RenderSize, View, Lighting, AmbientLight, TimeOfDay, TileScale},
+    ecs::component::{Color, Sprite, Position, Animation, PointLight, SpotLight, LightAnimation, Occluder, Tile, Wall},
+    controller::camera::CameraEffects,
     renderer::{
-        sprite::{RenderSprite, SpriteRenderer}, 
+        sprite::{RenderSprite, SpriteRenderer, TILE_WIDTH}, 
         light::{RenderLight, LightRenderer},
+        shadow::RenderOccluder,
     },
 };
 
 
-/// A System for rendering lights to the screen
+/// A system to advance LightAnimations.
+///
+/// (LightAnimation, resource::DeltaTime)
+#[derive(Default)]
+pub struct LightAnimSys;
+impl<'a> System<'a> for LightAnimSys {
+    type SystemData = (WriteStorage<'a, LightAnimation>,
+                       Read<'a, DeltaTime>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut animations, dt) = data;
+        let dt = dt.0 as f32;
+        for animation in (&mut animations).join() {
+            animation.advance(dt);
+        }
+    }
+}
+
+/// A system to advance the TimeOfDay, setting the AmbientLight from its schedule.
+///
+/// (TimeOfDay, AmbientLight, resource::DeltaTime)
+#[derive(Default)]
+pub struct TimeOfDaySys;
+impl<'a> System<'a> for TimeOfDaySys {
+    type SystemData = (Write<'a, TimeOfDay>,
+                       Write<'a, AmbientLight>,
+                       Read<'a, DeltaTime>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut time, mut ambient, dt) = data;
+        let hours = time.rate * dt.0 as f32;
+        time.advance(hours);
+        if let Some(scheduled) = time.ambient() {
+            *ambient = scheduled;
+        }
+    }
+}
+
+/// A System for rendering point and spot lights to the screen
+///
+/// Wall tiles, sized by the TileScale resource, and entities with an Occluder
+/// cast shadows from the lights. The look of the lighting is set by the Lighting
+/// resource, and the light reaching everywhere by the AmbientLight resource.
+///
+/// The occluders of walls are kept between frames, and only rebuilt for tiles
+/// whose Tile or Wall component was added, changed or removed, or when the
+/// TileScale changes. Only occluders within reach of a light are drawn.
 ///
 /// As this is an OpenGL system it must be called on the main thread with `with_thread_local`
-#[derive(Default)] 
+#[derive(Default)]
 pub struct LightRenderSys {
     renderer: LightRenderer,
+    tile_reader:    Option<ReaderId<ComponentEvent>>,
+    wall_reader:    Option<ReaderId<ComponentEvent>>,
+    /// The occluders of wall tiles by entity id
+    walls:      HashMap<u32, RenderOccluder>,
+    /// The TileScale the wall occluders were built for
+    wall_scale: Option<(f32, f32)>,
+}
+impl LightRenderSys {
+    /// Rebuilds the occluders of the walls changed since the last call.
+    fn update_walls(&mut self, entities: &Entities, tiles: &ReadStorage<Tile>,
+                    walls: &ReadStorage<Wall>, scale: (f32, f32)) {
+        let mut changed: HashSet<u32> = HashSet::new();
+        let id = |event: &ComponentEvent| match event {
+            ComponentEvent::Inserted(id)
+            | ComponentEvent::Modified(id)
+            | ComponentEvent::Removed(id) => *id,
+        };
+        if let Some(reader) = &mut self.tile_reader {
+            changed.extend(tiles.channel().read(reader).map(id));
+        }
+        if let Some(reader) = &mut self.wall_reader {
+            changed.extend(walls.channel().read(reader).map(id));
+        }
+        if self.wall_scale != Some(scale) {
+            self.wall_scale = Some(scale);
+            self.walls.clear();
+            changed.extend((entities, tiles, walls).join().map(|(entity, _, _)| entity.id()));
+        }
+
+        for id in changed {
+            let entity = entities.entity(id);
+            match (tiles.get(entity), walls.get(entity)) {
+                (Some(tile), Some(_)) if entities.is_alive(entity) => {
+                    // Walls occlude the sprites the TileRenderSys draws for them
+                    let pos = tile.translation(scale);
+                    let sprite = RenderSprite {
+                        translation: (pos.0, pos.1, 0.0),
+                        scale,
+                        ..Default::default()
+                    };
+                    self.walls.insert(id, RenderOccluder::sprite(&sprite, TILE_WIDTH));
+                },
+                _ => { self.walls.remove(&id); },
+            }
+        }
+    }
 }
 impl<'a> System<'a> for LightRenderSys {
-    type SystemData = (ReadStorage<'a, Position>,
+    type SystemData = (Entities<'a>,
+                       ReadStorage<'a, Position>,
                        ReadStorage<'a, PointLight>,
-                       Read<'a, WindowSize>,
-                       Read<'a, View>);
+                       ReadStorage<'a, SpotLight>,
+                       ReadStorage<'a, LightAnimation>,
+                       ReadStorage<'a, Occluder>,
+                       ReadStorage<'a, Tile>,
+                       ReadStorage<'a, Wall>,
+                       Read<'a, TileScale>,
+                       Read<'a, Lighting>,
+                       Read<'a, AmbientLight>,
+                       Read<'a, RenderSize>,
+                       Read<'a, View>,
+                       Read<'a, CameraEffects>);
 
     fn run(&mut self, data: Self::SystemData) {
-        let (pos, lights, window, view) = data;
+        let (entities, pos, lights, spots, animations, occluders, tiles, walls, tile_scale,
+             lighting, ambient, window, view, effects) = data;
         let window = (window.0, window.1);
-        let view = (view.0, view.1, view.2);
-        let lights: Vec<RenderLight> = (&pos, &lights).join()
-            .map(|data| data.into())
+        let cam = effects.apply(&view);
+        let animate = |(light, animation): (RenderLight, Option<&LightAnimation>)| {
+            let mut light = light;
+            if let Some(animation) = animation { animation.apply(&mut light); }
+            light
+        };
+        let lights: Vec<RenderLight> = (&pos, &lights, animations.maybe()).join()
+            .map(|(p, l, a)| ((p, l).into(), a))
+            .chain((&pos, &spots, animations.maybe()).join().map(|(p, l, a)| ((p, l).into(), a)))
+            .map(animate)
+            .collect();
+        self.renderer.mode = lighting.mode;
+        self.renderer.dither_scale = lighting.dither_scale;
+        self.renderer.shadow_color = lighting.shadow_color;
+        self.renderer.darkness = lighting.darkness;
+        self.renderer.monochrome = lighting.monochrome;
+        self.renderer.shadows = lighting.shadows;
+        self.renderer.ambient = ambient.light();
+
+        self.update_walls(&entities, &tiles, &walls, (tile_scale.0, tile_scale.1));
+        // Occluders out of every light's reach can't cast a shadow
+        let spread = lighting.shadows.spread();
+        let occluders: Vec<RenderOccluder> = self.walls.values().copied()
+            .chain((&pos, &occluders).join().map(|data| data.into()))
+            .filter(|o| lights.iter().any(|l| o.within(l.pos, l.intensity + spread)))
             .collect();
 
-        self.renderer.render(&lights, window, view);
+        gl_debug::pass("lights", || self.renderer.render(&lights, &occluders, window, &cam));
     }
     fn setup(&mut self, world: &mut World) {
         Self::SystemData::setup(world);
+        self.tile_reader = Some(WriteStorage::<Tile>::fetch(world).register_reader());
+        self.wall_reader = Some(WriteStorage::<Wall>::fetch(world).register_reader());
+        let size = *world.read_resource::<RenderSize>();
         self.renderer = LightRenderer::new();
-        self.renderer.init().unwrap();
-        self.renderer.dither_scale = 2.0;
+        self.renderer.dither_scale = world.read_resource::<Lighting>().dither_scale;
+        self.renderer.init((size.0, size.1)).unwrap_or_else(|err| log::error("lights", err));
     }
 }
@@ -2,18 +2,83 @@ pub mod sprite;
 pub mod light;
 pub mod text;
 pub mod movement;
+pub mod camera;
+pub mod post;
+pub mod particle;
+pub mod debug;
+pub mod tile;
 
 use specs::prelude::*;
+use crate::{
+    log,
+    renderer::gl_debug,
+    ecs::resource::{WindowSize, VirtualResolution, RenderSize},
+    renderer::{target::RenderTarget, upscale::UpscaleRenderer},
+};
 
+/// Begins a frame by binding and clearing the scene's render target.
+///
+/// The target is sized to the `VirtualResolution` and stored as a resource,
+/// so this must be dispatched before any other rendering systems, with
+/// `PresentSys` dispatched after them.
 #[derive(Default)]
 pub struct RenderSys;
 impl<'a> System<'a> for RenderSys {
-    type SystemData = ();
+    type SystemData = (Read<'a, WindowSize>,
+                       Read<'a, VirtualResolution>,
+                       Write<'a, RenderSize>,
+                       Write<'a, RenderTarget>);
 
     fn run(&mut self, data: Self::SystemData) {
-        unsafe {
-            gl::ClearColor(0.2, 0.2, 0.25, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-        }
+        let (window, resolution, mut render_size, mut target) = data;
+        let (w, h) = resolution.target_size(&window);
+        target.resize(w, h);
+        *render_size = RenderSize(w as f32, h as f32);
+
+        gl_debug::pass("clear", || {
+            target.bind();
+            target.clear((0.2, 0.2, 0.25, 1.0));
+        });
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        let (w, h) = {
+            let window = world.read_resource::<WindowSize>();
+            world.read_resource::<VirtualResolution>().target_size(&window)
+        };
+        let mut target = world.write_resource::<RenderTarget>();
+        target.init(w, h).unwrap_or_else(|err| log::error("scene", err));
+        target.label("scene");
+    }
+}
+
+/// Ends a frame by scaling the scene's render target up to the window.
+///
+/// As this is an OpenGL system it must be called on the main thread with `with_thread_local`
+#[derive(Default)]
+pub struct PresentSys {
+    renderer: UpscaleRenderer,
+}
+impl<'a> System<'a> for PresentSys {
+    type SystemData = (Read<'a, WindowSize>,
+                       Read<'a, VirtualResolution>,
+                       Write<'a, RenderTarget>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (window, resolution, mut target) = data;
+        gl_debug::pass("present", || self.renderer.render(
+            &mut target,
+            (window.0, window.1), 
+            resolution.viewport(&window),
+            resolution.bar_color,
+        ));
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        let mut target = world.write_resource::<RenderTarget>();
+        self.renderer = UpscaleRenderer::new();
+        self.renderer.init(&mut target).unwrap_or_else(|err| log::error("present", err));
     }
 }
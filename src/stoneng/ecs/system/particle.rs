@@ -0,0 +1,128 @@
+use specs::{ReadStorage, WriteStorage, System, Join, Read, SystemData};
+use specs::prelude::*;
+use rand::Rng;
+use crate::{
+    log,
+    renderer::gl_debug,
+    ecs::resource::{DeltaTime, RenderSize, View},
+    ecs::component::{Position, ParticleEmitter, particle::Particle},
+    renderer::sprite::{RenderSprite, SpriteRenderer},
+    controller::camera::CameraEffects,
+};
+
+/// A system to spawn, move and expire particles.
+///
+/// (ParticleEmitter, Position, resource::DeltaTime)
+#[derive(Default)]
+pub struct ParticleSys;
+impl<'a> System<'a> for ParticleSys {
+    type SystemData = (WriteStorage<'a, ParticleEmitter>,
+                       ReadStorage<'a, Position>,
+                       Read<'a, DeltaTime>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut emitters, positions, dt) = data;
+        let dt = dt.0 as f32;
+        let mut rng = rand::thread_rng();
+
+        for (emitter, pos) in (&mut emitters, &positions).join() {
+            let preset = emitter.preset.clone();
+
+            // Age and move the living particles
+            let drag = (1.0 - preset.drag * dt).max(0.0);
+            emitter.particles.retain_mut(|p| {
+                p.age += dt;
+                if p.age >= p.lifetime { return false; }
+                p.vel.0 = (p.vel.0 + preset.gravity.0 * dt) * drag;
+                p.vel.1 = (p.vel.1 + preset.gravity.1 * dt) * drag;
+                p.pos.0 += p.vel.0 * dt;
+                p.pos.1 += p.vel.1 * dt;
+                true
+            });
+
+            // Work out how many particles to spawn this frame
+            let mut count = std::mem::take(&mut emitter.pending);
+            if emitter.emitting {
+                emitter.elapsed += dt;
+                emitter.spawn_debt += preset.rate * dt;
+                let whole = emitter.spawn_debt.floor();
+                emitter.spawn_debt -= whole;
+                count += whole as u32;
+
+                if let Some(duration) = preset.duration {
+                    if emitter.elapsed >= duration { emitter.emitting = false; }
+                }
+            }
+            let free = preset.max_particles.saturating_sub(emitter.particles.len());
+            let count = (count as usize).min(free);
+
+            let origin = (pos.x + emitter.offset.0, pos.y + emitter.offset.1);
+            for _ in 0..count {
+                let spread = preset.spread.to_radians() * 0.5;
+                let angle = preset.direction.to_radians() + rng.gen_range(-1.0..=1.0) * spread;
+                let speed = range(&mut rng, preset.speed);
+                let offset_angle = rng.gen_range(0.0..std::f32::consts::TAU);
+                let offset = preset.radius * rng.gen_range(0.0f32..=1.0).sqrt();
+
+                emitter.particles.push(Particle {
+                    pos: (origin.0 + offset_angle.cos() * offset,
+                          origin.1 + offset_angle.sin() * offset),
+                    z: pos.z,
+                    vel: (angle.cos() * speed, angle.sin() * speed),
+                    age: 0.0,
+                    lifetime: range(&mut rng, preset.lifetime),
+                });
+            }
+        }
+    }
+}
+
+/// Picks a random value in a `(min, max)` range, allowing `min == max`.
+fn range(rng: &mut impl Rng, (min, max): (f32, f32)) -> f32 {
+    if max > min { rng.gen_range(min..=max) } else { min }
+}
+
+/// Draws the particles of every emitter in a single batch.
+#[derive(Default)]
+pub struct ParticleRenderSys {
+    renderer: SpriteRenderer,
+    sprites:  Vec<RenderSprite>,
+}
+impl<'a> System<'a> for ParticleRenderSys {
+    type SystemData = (ReadStorage<'a, ParticleEmitter>,
+                       Read<'a, RenderSize>,
+                       Read<'a, View>,
+                       Read<'a, CameraEffects>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (emitters, window, view, effects) = data;
+        let window = (window.0, window.1);
+        let cam = effects.apply(&view);
+
+        // Reuse the allocation between frames, as the particle count can be large
+        self.sprites.clear();
+        for emitter in emitters.join() {
+            let preset = &emitter.preset;
+            self.sprites.extend(emitter.particles.iter().map(|p| {
+                let t = p.progress();
+                let scale = preset.scale.sample(t).unwrap_or(1.0);
+                RenderSprite {
+                    translation: (p.pos.0, p.pos.1, p.z),
+                    scale:       (scale, scale),
+                    color:       preset.color.sample(t).unwrap_or((1.0, 1.0, 1.0, 1.0)),
+                    sprite_id:   emitter.particle_sprite(p),
+                    ..RenderSprite::default()
+                }
+            }));
+        }
+        if self.sprites.is_empty() { return; }
+        gl_debug::pass("particles", || self.renderer.render(&self.sprites, window, &cam));
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.renderer = SpriteRenderer::new();
+        self.renderer.init(include_bytes!("../../../../assets/textures/sprites.png"))
+            .unwrap_or_else(|err| log::error("particles", err));
+    }
+}
@@ -0,0 +1,35 @@
+use specs::prelude::*;
+use crate::{
+    log,
+    renderer::gl_debug,
+    ecs::resource::{DeltaTime, PostProcessing},
+    renderer::{target::RenderTarget, post::PostProcessRenderer},
+};
+
+/// A system that applies the `PostProcessing` chain to the scene.
+///
+/// This must be dispatched after all scene rendering systems and before `PresentSys`.
+/// As this is an OpenGL system it must be called on the main thread with `with_thread_local`
+#[derive(Default)]
+pub struct PostProcessSys {
+    renderer: PostProcessRenderer,
+    time: f64,
+}
+impl<'a> System<'a> for PostProcessSys {
+    type SystemData = (Read<'a, PostProcessing>,
+                       Write<'a, RenderTarget>,
+                       Read<'a, DeltaTime>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (post, mut target, dt) = data;
+        self.time += dt.0;
+        gl_debug::pass("post process", || self.renderer.render(&post.passes, &mut target, self.time as f32));
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        let mut target = world.write_resource::<RenderTarget>();
+        self.renderer = PostProcessRenderer::new();
+        self.renderer.init(&mut target).unwrap_or_else(|err| log::error("post process", err));
+    }
+}
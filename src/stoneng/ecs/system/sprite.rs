@@ -4,13 +4,24 @@ use std::sync::Arc;
 use crate::ecs::component;
 use crate::error::EngineError;
 use crate::{
-    model::spritesheet::{SpriteSheet, AnimationSchema},
-    ecs::resource::{DeltaTime, WindowSize, View},
-    ecs::component::{Color, Sprite, Position, Scale, Animation, tile::*},
+    model::{spritesheet::{SpriteSheet, AnimationSchema}, palette::Palettes},
+    ecs::resource::{DeltaTime, RenderSize, View, TileScale},
+    ecs::component::{Color, Sprite, Position, Scale, Animation, Material, Palette, Panel, ParallaxLayer, tile::*},
     renderer::sprite::{RenderSprite, SpriteRenderer},
+    renderer::nine_slice::{RenderPanel, NineSliceRenderer},
+    renderer::text::VectorSpace,
     renderer::light::{RenderLight, LightRenderer},
+    renderer::gl_debug,
+    controller::camera::CameraEffects,
+    log,
 };
 
+/// The depth floor tiles are drawn at, behind walls and other sprites.
+/// Parallax layers deeper than this are backdrops, drawn before the scene.
+pub const FLOOR_DEPTH: f32 = -10.1;
+/// The depth wall tiles are drawn at.
+pub const WALL_DEPTH: f32 = -10.0;
+
 #[derive(Default)]
 pub struct AnimSpriteSys;
 impl AnimSpriteSys {
@@ -172,77 +183,247 @@ impl<'a> System<'a> for SpriteRenderSys {
                        ReadStorage<'a, Position>,
                        ReadStorage<'a, Scale>,
                        ReadStorage<'a, Color>,
-                       Read<'a, WindowSize>,
-                       Read<'a, View>);
+                       ReadStorage<'a, Material>,
+                       ReadStorage<'a, Palette>,
+                       ReadStorage<'a, Panel>,
+                       Read<'a, Palettes>,
+                       Read<'a, RenderSize>,
+                       Read<'a, View>,
+                       Read<'a, CameraEffects>,
+                       Read<'a, DeltaTime>);
 
     fn run(&mut self, data: Self::SystemData) {
-        let (sprites, positions, scales, colors, window, view) = data;
+        let (sprites, positions, scales, colors, materials, palettes, panels, palette_set,
+             window, view, effects, dt) = data;
         let window = (window.0, window.1); 
-        let view = (view.0, view.1, view.2);
-        // Build the RenderSprite Vec from the components
-        let sprites: Vec<RenderSprite> = 
-            (&sprites, &positions, &scales, &colors).join()
-                .map(|data| data.into())
-                .collect();
-        self.renderer.render(&sprites, window, view);
+        let cam = effects.apply(&view);
+        self.renderer.time += dt.0 as f32;
+        self.renderer.set_palettes(&palette_set);
+
+        // Build the RenderSprite Vecs from the components, batched by Material
+        let mut batches: Vec<(Option<&Material>, Vec<RenderSprite>)> = Vec::new();
+        // Panels are drawn by the PanelRenderSys instead
+        for (s, p, sc, c, m, pal, _) in (&sprites, &positions, &scales, &colors, 
+                                         materials.maybe(), palettes.maybe(), !&panels).join() {
+            let sprite = RenderSprite::from((s, p, sc, c, pal));
+            match batches.iter_mut().find(|(bm, _)| *bm == m) {
+                Some((_, batch)) => batch.push(sprite),
+                None => batches.push((m, vec![sprite])),
+            }
+        }
+
+        gl_debug::pass("sprites", || {
+            for (material, sprites) in batches.iter() {
+                match material {
+                    Some(mat) => self.renderer.render_material(sprites, window, &cam, mat),
+                    None => self.renderer.render(sprites, window, &cam),
+                }
+            }
+        });
     }
 
     fn setup(&mut self, world: &mut World) {
         Self::SystemData::setup(world);
         self.renderer = SpriteRenderer::new();
-        self.renderer.init(include_bytes!("../../../../assets/textures/sprites.png")).unwrap();
+        self.renderer.init(include_bytes!("../../../../assets/textures/sprites.png"))
+            .unwrap_or_else(|err| log::error("sprites", err));
     }
 }
 
-//TODO join renderers into a common resource (potentially using the resource system?)
-pub struct TileRenderSys {
+/// Draws sprites with a Panel component, stretched to the panel's size.
+///
+/// World space panels are drawn first, followed by screen space panels.
+#[derive(Default)]
+pub struct PanelRenderSys {
+    renderer: NineSliceRenderer,
+}
+impl<'a> System<'a> for PanelRenderSys {
+    type SystemData = (ReadStorage<'a, Sprite>,
+                       ReadStorage<'a, Position>,
+                       ReadStorage<'a, Panel>,
+                       ReadStorage<'a, Color>,
+                       Read<'a, RenderSize>,
+                       Read<'a, View>,
+                       Read<'a, CameraEffects>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (sprites, positions, panels, colors, window, view, effects) = data;
+        let window = (window.0, window.1);
+        let cam = effects.apply(&view);
+
+        for space in [VectorSpace::World, VectorSpace::Screen] {
+            let batch: Vec<RenderPanel> =
+                (&sprites, &positions, &panels, &colors).join()
+                    .filter(|(_, _, panel, _)| panel.space == space)
+                    .map(|data| data.into())
+                    .collect();
+            gl_debug::pass("panels", || self.renderer.render(&batch, window, &cam, space));
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.renderer = NineSliceRenderer::new();
+        self.renderer.init(include_bytes!("../../../../assets/textures/sprites.png"))
+            .unwrap_or_else(|err| log::error("panels", err));
+    }
+}
+
+/// A system to auto-scroll parallax layers.
+///
+/// (ParallaxLayer, resource::DeltaTime)
+#[derive(Default)]
+pub struct ParallaxSys;
+impl<'a> System<'a> for ParallaxSys {
+    type SystemData = (WriteStorage<'a, ParallaxLayer>,
+                       Read<'a, DeltaTime>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut layers, dt) = data;
+        let dt = dt.0 as f32;
+        for layer in (&mut layers).join() {
+            let (scroll, repeat, size) = (layer.settings.scroll, layer.settings.repeat, layer.tile_size);
+            layer.scrolled.0 += scroll.0 * dt;
+            layer.scrolled.1 += scroll.1 * dt;
+            // Repeating layers look the same every tile, so keep the offset small
+            if repeat.0 { layer.scrolled.0 %= size.0; }
+            if repeat.1 { layer.scrolled.1 %= size.1; }
+        }
+    }
+}
+
+/// Draws parallax layers, repeating their sprites to cover the screen.
+///
+/// Layers are split by depth into two systems: `background` draws the layers
+/// behind the floor tiles before the scene, and `foreground` the rest after it.
+/// Foreground layers don't record their depth, so translucent ones such as fog
+/// tint the sprites behind them rather than hiding them.
+#[derive(Default)]
+pub struct ParallaxRenderSys {
     renderer: SpriteRenderer,
-    scale:  (f32, f32),
+    foreground: bool,
 }
-impl Default for TileRenderSys {
-    fn default() -> Self {
-        Self {
-            renderer: SpriteRenderer::default(),
-            scale: (5.0, 5.0),
+impl ParallaxRenderSys {
+    /// Draws the layers deeper than `FLOOR_DEPTH`, to be run before the
+    /// scene's renderers.
+    pub fn background() -> Self {
+        Self::default()
+    }
+
+    /// Draws the layers at or in front of `FLOOR_DEPTH`, to be run after the
+    /// scene's renderers.
+    pub fn foreground() -> Self {
+        Self { foreground: true, ..Self::default() }
+    }
+}
+impl<'a> System<'a> for ParallaxRenderSys {
+    type SystemData = (ReadStorage<'a, ParallaxLayer>,
+                       Read<'a, RenderSize>,
+                       Read<'a, View>,
+                       Read<'a, CameraEffects>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (layers, window, view, effects) = data;
+        let window = (window.0, window.1);
+        let cam = effects.apply(&view);
+
+        // The visible area of the world, accounting for zoom around the center
+        let half = (window.0 / 2.0 / cam.zoom, window.1 / 2.0 / cam.zoom);
+        let center = (cam.pos.0 + window.0 / 2.0, cam.pos.1 + window.1 / 2.0);
+        let (min, max) = ((center.0 - half.0, center.1 - half.1), (center.0 + half.0, center.1 + half.1));
+
+        let mut sprites: Vec<RenderSprite> = Vec::new();
+        for layer in layers.join().filter(|l| (l.settings.depth >= FLOOR_DEPTH) == self.foreground) {
+            let s = &layer.settings;
+            let origin = layer.origin((cam.pos.0, cam.pos.1));
+            let size = layer.tile_size;
+
+            // Tile indices covering the screen, with a tile of margin for the sprite anchor
+            let span = |repeat: bool, min: f32, max: f32, origin: f32, size: f32| {
+                if repeat {
+                    (((min - origin) / size).floor() as i32 - 1, ((max - origin) / size).ceil() as i32 + 1)
+                } else {
+                    (0, 0)
+                }
+            };
+            let (x0, x1) = span(s.repeat.0, min.0, max.0, origin.0, size.0);
+            let (y0, y1) = span(s.repeat.1, min.1, max.1, origin.1, size.1);
+
+            let (dim_x, dim_y) = layer.schema.dimensions;
+            for i in x0..=x1 {
+                for j in y0..=y1 {
+                    sprites.push(RenderSprite {
+                        translation:    (origin.0 + i as f32 * size.0, origin.1 + j as f32 * size.1, s.depth),
+                        scale:          s.scale,
+                        color:          s.color,
+                        sprite_id:      layer.schema.root,
+                        sprite_dims:    dim_x | (dim_y << 4),
+                        ..RenderSprite::default()
+                    });
+                }
+            }
         }
+        if sprites.is_empty() { return; }
+        let pass = if self.foreground { "parallax foreground" } else { "parallax background" };
+        gl_debug::pass(pass, || self.renderer.render(&sprites, window, &cam));
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.renderer = SpriteRenderer::new();
+        self.renderer.init(include_bytes!("../../../../assets/textures/sprites.png"))
+            .unwrap_or_else(|err| log::error("parallax", err));
+        self.renderer.depth_write = !self.foreground;
     }
 }
+
+//TODO join renderers into a common resource (potentially using the resource system?)
+/// A System for rendering Floor and Wall tiles, sized by the TileScale resource.
+#[derive(Default)]
+pub struct TileRenderSys {
+    renderer: SpriteRenderer,
+}
 impl<'a> System<'a> for TileRenderSys {
     type SystemData = (ReadStorage<'a, Tile>,
                        ReadStorage<'a, Floor>,
                        ReadStorage<'a, Wall>,
                        ReadStorage<'a, Color>,
-                       Read<'a, WindowSize>,
-                       Read<'a, View>);
+                       Read<'a, Palettes>,
+                       Read<'a, TileScale>,
+                       Read<'a, RenderSize>,
+                       Read<'a, View>,
+                       Read<'a, CameraEffects>);
 
     fn run(&mut self, data: Self::SystemData) {
         // Unpack system data
-        let (tiles, floors, walls, colors, window, view) = data;
+        let (tiles, floors, walls, colors, palettes, tile_scale, window, view, effects) = data;
         let window = (window.0, window.1);
-        let view = (view.0, view.1, view.2);
-        let scale = self.scale.clone();
+        let cam = effects.apply(&view);
+        self.renderer.set_palettes(&palettes);
+        let scale = (tile_scale.0, tile_scale.1);
         let sprites: Vec<RenderSprite> = 
             (&tiles, &floors, &colors).join()
                 .map(|data| {
                     let (tile, floor, color) = data;
-                    RenderSprite::from((tile, color, floor.schema.clone(), scale, -10.1))
+                    RenderSprite::from((tile, color, floor.schema.clone(), scale, FLOOR_DEPTH))
                 })
                 .collect();
-        self.renderer.render(&sprites, window, view);
+        gl_debug::pass("floors", || self.renderer.render(&sprites, window, &cam));
 
         let sprites: Vec<RenderSprite> = 
             (&tiles, &walls, &colors).join()
                 .map(|data| {
                     let (tile, wall, color) = data;
-                    RenderSprite::from((tile, color, wall.schema.clone(), scale, -10.0))
+                    RenderSprite::from((tile, color, wall.schema.clone(), scale, WALL_DEPTH))
                 })
                 .collect();
-        self.renderer.render(&sprites, window, view);
+        gl_debug::pass("walls", || self.renderer.render(&sprites, window, &cam));
     }
 
     fn setup(&mut self, world: &mut World) {
         Self::SystemData::setup(world);
         self.renderer = SpriteRenderer::new();
-        self.renderer.init(include_bytes!("../../../../assets/textures/sprites.png")).unwrap();
+        self.renderer.init(include_bytes!("../../../../assets/textures/sprites.png"))
+            .unwrap_or_else(|err| log::error("tiles", err));
     }
 }
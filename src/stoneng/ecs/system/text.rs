@@ -1,9 +1,12 @@
 use specs::{ReadStorage, WriteStorage, System, Join, Read, SystemData};
 use specs::prelude::*;
 use crate::{
+    log,
+    renderer::gl_debug,
     ecs::component::{Color, Position, Text},
-    ecs::resource::{WindowSize, View},
+    ecs::resource::{RenderSize, View},
     renderer::text::*,
+    controller::camera::CameraEffects,
 };
 
 
@@ -15,18 +18,19 @@ impl<'a> System<'a> for TextRenderSys {
     type SystemData = (ReadStorage<'a, Text>,
                        ReadStorage<'a, Position>,
                        ReadStorage<'a, Color>,
-                       Read<'a, WindowSize>,
-                       Read<'a, View>);
+                       Read<'a, RenderSize>,
+                       Read<'a, View>,
+                       Read<'a, CameraEffects>);
 
     fn run(&mut self, data: Self::SystemData) {
-        let (texts, pos, colors, window, view) = data;
+        let (texts, pos, colors, window, view, effects) = data;
         let window = (window.0, window.1);
-        let view = (view.0, view.1, view.2);
+        let cam = effects.apply(&view);
         let texts: Vec<RenderString> = 
             (&texts, &pos, &colors).join()
                 .map(|data| data.into())
                 .collect();
-        self.renderer.render(&texts, window, view);
+        gl_debug::pass("text", || self.renderer.render(&texts, window, &cam));
     }
 
     fn setup(&mut self, world: &mut World){ 
@@ -36,6 +40,6 @@ impl<'a> System<'a> for TextRenderSys {
         self.renderer.init(
             include_bytes!("../../../../assets/textures/fonts/dogica.png"),
             8,
-        ).unwrap();
+        ).unwrap_or_else(|err| log::error("text", err));
     }
 }
@@ -0,0 +1,140 @@
+use std::collections::{HashMap, HashSet};
+
+use specs::{ReadStorage, WriteStorage, System, Join, SystemData};
+use specs::prelude::*;
+use specs::storage::ComponentEvent;
+use crate::{
+    ecs::component::{Tile, Floor, Wall, Autotile},
+    model::autotile::neighbor,
+};
+
+/// A system to pick the sprites of autotiled Floors and Walls from their neighbors.
+///
+/// (Autotile, Tile, Floor, Wall)
+///
+/// Floors and Walls are matched separately. Only tiles whose Autotile component
+/// was added, changed or removed, along with their neighbors, are updated each
+/// frame. Moving a Tile requires re-inserting its Autotile.
+#[derive(Default)]
+pub struct AutotileSys {
+    reader:     Option<ReaderId<ComponentEvent>>,
+    /// Autotiled floors and walls by tile position
+    floors:     HashMap<(i32, i32), Entity>,
+    walls:      HashMap<(i32, i32), Entity>,
+    /// Where each tracked entity was placed, by entity id
+    placed:     HashMap<u32, ((i32, i32), bool)>,
+}
+impl AutotileSys {
+    fn layer(&mut self, is_wall: bool) -> &mut HashMap<(i32, i32), Entity> {
+        if is_wall { &mut self.walls } else { &mut self.floors }
+    }
+
+    /// Stops tracking an entity, returning where it was placed.
+    fn unplace(&mut self, id: u32) -> Option<((i32, i32), bool)> {
+        let (pos, is_wall) = self.placed.remove(&id)?;
+        let layer = self.layer(is_wall);
+        if layer.get(&pos).map(|e| e.id()) == Some(id) {
+            layer.remove(&pos);
+        }
+        Some((pos, is_wall))
+    }
+}
+
+impl<'a> System<'a> for AutotileSys {
+    type SystemData = (Entities<'a>,
+                       ReadStorage<'a, Autotile>,
+                       ReadStorage<'a, Tile>,
+                       WriteStorage<'a, Floor>,
+                       WriteStorage<'a, Wall>);
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, autotiles, tiles, mut floors, mut walls) = data;
+        let reader = match &mut self.reader {
+            Some(reader) => reader,
+            None => return,
+        };
+
+        // Collect the changed entities first, as the reader borrows self
+        let mut changed: HashSet<u32> = HashSet::new();
+        let mut removed: HashSet<u32> = HashSet::new();
+        for event in autotiles.channel().read(reader) {
+            match event {
+                ComponentEvent::Inserted(id) | ComponentEvent::Modified(id) => {
+                    changed.insert(*id);
+                },
+                ComponentEvent::Removed(id) => {
+                    removed.insert(*id);
+                },
+            }
+        }
+        if changed.is_empty() && removed.is_empty() { return; }
+
+        // Update the placement of changed tiles, marking their surroundings dirty
+        let mut dirty: HashSet<((i32, i32), bool)> = HashSet::new();
+        let mut mark = |pos: (i32, i32), is_wall: bool| {
+            dirty.insert((pos, is_wall));
+            for (dx, dy) in neighbor::OFFSETS {
+                dirty.insert(((pos.0 + dx, pos.1 + dy), is_wall));
+            }
+        };
+        for id in removed.iter().chain(changed.iter()) {
+            if let Some((pos, is_wall)) = self.unplace(*id) {
+                mark(pos, is_wall);
+            }
+        }
+        for id in changed {
+            let entity = entities.entity(id);
+            if !entities.is_alive(entity) { continue; }
+            let tile = match (tiles.get(entity), autotiles.get(entity)) {
+                (Some(tile), Some(_)) => tile,
+                _ => continue,
+            };
+            let is_wall = walls.contains(entity);
+            self.layer(is_wall).insert(tile.pos, entity);
+            self.placed.insert(id, (tile.pos, is_wall));
+            mark(tile.pos, is_wall);
+        }
+
+        // Pick the variant of each dirty tile
+        for (pos, is_wall) in dirty {
+            let layer = if is_wall { &self.walls } else { &self.floors };
+            let entity = match layer.get(&pos) {
+                Some(entity) => *entity,
+                None => continue,
+            };
+            let autotile = match autotiles.get(entity) {
+                Some(autotile) => autotile,
+                None => continue,
+            };
+            let rules = match &autotile.schema.autotile {
+                Some(rules) => rules,
+                None => continue,
+            };
+
+            // Build the full neighbor mask
+            let mut mask = 0u8;
+            for (bit, (dx, dy)) in neighbor::OFFSETS.iter().enumerate() {
+                let connected = layer.get(&(pos.0 + dx, pos.1 + dy))
+                    .and_then(|e| autotiles.get(*e))
+                    .map(|other| autotile.connects(other))
+                    .unwrap_or(false);
+                if connected { mask |= 1 << bit; }
+            }
+
+            let schema = rules.variant(mask)
+                .and_then(|name| autotile.schema.variants.get(name))
+                .unwrap_or(&autotile.schema)
+                .clone();
+            if is_wall {
+                if let Some(wall) = walls.get_mut(entity) { wall.schema = schema; }
+            } else if let Some(floor) = floors.get_mut(entity) {
+                floor.schema = schema;
+            }
+        }
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        Self::SystemData::setup(world);
+        self.reader = Some(WriteStorage::<Autotile>::fetch(world).register_reader());
+    }
+}
@@ -7,8 +7,28 @@ pub enum EngineError {
     SheetParseError(ron::error::Error),
     SheetSizeError(String),
     AnimationError(String),
+    PaletteError(String),
+    TextureError(String),
 }
 
+impl std::fmt::Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ShaderCompile(log) => write!(f, "Shader compilation failed:\n{}", log),
+            Self::ShaderLink(log) => write!(f, "Shader linking failed:\n{}", log),
+            Self::RendererInit(msg) => write!(f, "Renderer initialization failed: {}", msg),
+            Self::IOError(error) => write!(f, "IO error: {}", error),
+            Self::SheetParseError(error) => write!(f, "Sheet parse error: {}", error),
+            Self::SheetSizeError(msg) => write!(f, "Sheet size error: {}", msg),
+            Self::AnimationError(msg) => write!(f, "Animation error: {}", msg),
+            Self::PaletteError(msg) => write!(f, "Palette error: {}", msg),
+            Self::TextureError(msg) => write!(f, "Texture error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {}
+
 impl From<ron::error::Error> for EngineError {
     fn from(error: ron::error::Error) -> Self {
         Self::SheetParseError(error)
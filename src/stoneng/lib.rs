@@ -8,8 +8,10 @@ pub mod event;
 pub mod renderer;
 pub mod ecs;
 pub mod controller;
+pub mod math;
+pub mod log;
 
-mod shader;
+pub mod shader;
 mod error;
 
 use event::*;
@@ -19,9 +21,12 @@ use std::sync::mpsc::{self, Sender, Receiver};
 use glutin::{
     event::{Event, WindowEvent, VirtualKeyCode},
     event_loop::{self, ControlFlow, EventLoop},
-    window::{self, Window, WindowBuilder},
-    dpi::PhysicalSize,
+    window::{self, Window, WindowBuilder, Fullscreen},
+    dpi::{PhysicalSize, LogicalSize},
 };
+use specs::{World, WorldExt};
+use ecs::resource::{WindowSize, LogicalWindowSize, ScaleFactor};
+use renderer::context::GlVersion;
 
 // Aliases
 pub type EngineError = error::EngineError;
@@ -34,6 +39,10 @@ pub type EngineError = error::EngineError;
 pub trait EngineCore {
     // Engine Cycle
     /// Called once, after context creation, before initial draw. 
+    ///
+    /// The window resources are inserted into the `world` beforehand, so a
+    /// world created before `init` lets systems read the window's size in
+    /// their `setup`.
     fn init(&mut self){}
     /// Called once per engine update with the number of seconds since the last draw.
     fn tick(&mut self, dt: f64){}
@@ -52,11 +61,19 @@ pub trait EngineCore {
     /// Called when the cursor moves within the window
     fn cursor_moved(&mut self, x: f64, y: f64) {}
 
+    /// Called when the window's framebuffer changes size, in physical pixels.
     fn resized(&mut self, x: u32, y: u32) {} 
+
+    /// Provides the engine with the game's ECS world, if it has one.
+    ///
+    /// The engine uses this to keep the `WindowSize`, `LogicalWindowSize` and 
+    /// `ScaleFactor` resources up to date.
+    fn world(&mut self) -> Option<&mut World> { None }
 }
 
 
 pub struct Config {
+    /// The window's inner size in logical pixels
     pub dimensions: (u32, u32),
     pub title: String,
     pub fullscreen: bool,
@@ -79,24 +96,27 @@ pub fn start<F, G>(config: Config, game: F) where
     G: 'static + EngineCore,
     F: 'static + FnOnce() -> G {
     let mut game = game();
-    let window_size = PhysicalSize::new(config.dimensions.0, config.dimensions.1);
+    // Use a logical size so the window appears the same size on HiDPI displays
+    let window_size = LogicalSize::new(config.dimensions.0, config.dimensions.1);
+    let fullscreen = if config.fullscreen { Some(Fullscreen::Borderless(None)) } else { None };
     // Spawn the event loop thread and build the context
     let el = EventLoop::new();
     let wb = WindowBuilder::new()
-        .with_title("rustylantern")
+        .with_title(config.title)
         .with_inner_size(window_size)
-        .with_resizable(false);
-    let ctx = glutin::ContextBuilder::new()
-        .with_gl(glutin::GlRequest::Specific(glutin::Api::OpenGl, (4, 2)))
-        .with_gl_profile(glutin::GlProfile::Core)
-        .with_vsync(true)
-        .build_windowed(wb, &el)
-        .unwrap();
+        .with_resizable(config.resizable)
+        .with_fullscreen(fullscreen);
+    let ctx = build_context(wb, &el);
     let ctx = unsafe { ctx.make_current().unwrap() };
     
     gl::load_with(|ptr| ctx.context().get_proc_address(ptr) as *const _);
+    renderer::gpu::set_gl_thread();
+    renderer::gl_debug::init();
     
-    init_gl();
+ 
+    let physical_size = ctx.window().inner_size();
+    init_gl(physical_size);
+    update_window_resources(&mut game, physical_size, ctx.window().scale_factor());
     game.init();
     
     ctx.window().set_cursor_visible(false);
@@ -125,6 +145,14 @@ pub fn start<F, G>(config: Config, game: F) where
                     game.cursor_moved(position.x, position.y);
                 },
                 WindowEvent::Resized(new_size) => {
+                    ctx.resize(new_size);
+                    update_window_resources(&mut game, new_size, ctx.window().scale_factor());
+                    game.resized(new_size.width, new_size.height); 
+                },
+                WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size } => {
+                    let new_size = *new_inner_size;
+                    ctx.resize(new_size);
+                    update_window_resources(&mut game, new_size, scale_factor);
                     game.resized(new_size.width, new_size.height); 
                 },
                 _ => {}
@@ -142,6 +170,8 @@ pub fn start<F, G>(config: Config, game: F) where
 
                 // Call renderers here
                 ctx.swap_buffers().unwrap();
+                // Free GL objects dropped off the main thread
+                renderer::gpu::collect_garbage();
                 
                 game.post_render();
             }
@@ -153,7 +183,50 @@ pub fn start<F, G>(config: Config, game: F) where
 
 }
 
-fn init_gl(){
+/// Contexts to try creating, best first. Shaders are rewritten for whichever
+/// is created, see `shader::Preprocessor`.
+const CONTEXT_REQUESTS: [(glutin::Api, (u8, u8)); 3] = [
+    (glutin::Api::OpenGl, (4, 1)),
+    (glutin::Api::OpenGl, (3, 3)),
+    (glutin::Api::OpenGlEs, (3, 0)),
+];
+
+/// Creates the window with the best context available.
+fn build_context(wb: WindowBuilder, el: &EventLoop<()>)
+        -> glutin::WindowedContext<glutin::NotCurrent> {
+    let mut errors = Vec::new();
+    for (api, version) in CONTEXT_REQUESTS {
+        let builder = glutin::ContextBuilder::new()
+            .with_gl(glutin::GlRequest::Specific(api, version))
+            .with_vsync(true)
+            // Debug contexts report more through KHR_debug
+            .with_gl_debug_flag(cfg!(debug_assertions));
+        // Profiles only apply to desktop OpenGL
+        let builder = match api {
+            glutin::Api::OpenGl => builder.with_gl_profile(glutin::GlProfile::Core),
+            _ => builder,
+        };
+        match builder.build_windowed(wb.clone(), el) {
+            Ok(ctx) => return ctx,
+            Err(err) => errors.push(format!("{:?} {:?}: {}", api, version, err)),
+        }
+    }
+    panic!("Failed to create an OpenGL context:\n{}", errors.join("\n"));
+}
+
+/// Updates the viewport and the game's window resources to match the window.
+fn update_window_resources<G: EngineCore>(game: &mut G, size: PhysicalSize<u32>, scale_factor: f64) {
+    unsafe { gl::Viewport(0, 0, size.width as i32, size.height as i32); }
+
+    if let Some(world) = game.world() {
+        let logical: LogicalSize<f32> = size.to_logical(scale_factor);
+        world.insert(WindowSize(size.width as f32, size.height as f32));
+        world.insert(LogicalWindowSize(logical.width, logical.height));
+        world.insert(ScaleFactor(scale_factor));
+    }
+}
+
+fn init_gl(size: PhysicalSize<u32>){
     unsafe {
         // Enable transparency
         gl::Enable(gl::BLEND);
@@ -167,8 +240,11 @@ fn init_gl(){
         gl::DepthFunc(gl::LEQUAL);
         
         // Set the viewport's dimensions. This should match the window.
-        gl::Viewport( 0, 0, 800, 600);
+        gl::Viewport(0, 0, size.width as i32, size.height as i32);
 
-        gl::PointSize(10.0);
+        // Not part of OpenGL ES
+        if gl::PointSize::is_loaded() && !GlVersion::current().map(|v| v.es).unwrap_or(false) {
+            gl::PointSize(10.0);
+        }
     }
 }
@@ -0,0 +1,104 @@
+//! The engine log.
+//!
+//! Messages are written to stderr along with their level and the part of the
+//! engine they came from, e.g. `[warn gl] ...`. Messages below the log level
+//! are dropped. The level starts at `Info`, or the value of `STONENG_LOG`
+//! (`error`, `warn`, `info` or `debug`) when it is set.
+//!
+//! # Example
+//! ```
+//! # use stoneng::log::{self, LogLevel};
+//! log::set_level(LogLevel::Warn);
+//! assert!(log::enabled(LogLevel::Error));
+//! assert!(!log::enabled(LogLevel::Info));
+//! log::warn("example", "shown");
+//! log::info("example", "dropped");
+//! ```
+use std::{
+    fmt,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+/// How severe a message is, from most to least.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+}
+impl LogLevel {
+    fn from_u8(level: u8) -> Option<Self> {
+        match level {
+            1 => Some(LogLevel::Error),
+            2 => Some(LogLevel::Warn),
+            3 => Some(LogLevel::Info),
+            4 => Some(LogLevel::Debug),
+            _ => None,
+        }
+    }
+
+    /// Parses a level's name, ignoring case.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "error" => Some(LogLevel::Error),
+            "warn" | "warning" => Some(LogLevel::Warn),
+            "info" => Some(LogLevel::Info),
+            "debug" => Some(LogLevel::Debug),
+            _ => None,
+        }
+    }
+}
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn  => "warn",
+            LogLevel::Info  => "info",
+            LogLevel::Debug => "debug",
+        })
+    }
+}
+
+/// The current level, 0 until it is first read or set
+static LEVEL: AtomicU8 = AtomicU8::new(0);
+
+/// Sets the least severe level that is written.
+pub fn set_level(level: LogLevel) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// The least severe level that is written.
+pub fn level() -> LogLevel {
+    if let Some(level) = LogLevel::from_u8(LEVEL.load(Ordering::Relaxed)) {
+        return level;
+    }
+    let level = std::env::var("STONENG_LOG").ok()
+        .and_then(|name| LogLevel::parse(&name))
+        .unwrap_or(LogLevel::Info);
+    set_level(level);
+    level
+}
+
+/// Whether messages of `level` are written.
+pub fn enabled(level: LogLevel) -> bool {
+    level <= self::level()
+}
+
+/// Writes a message from `source`, a short name for the part of the engine
+/// it came from, if `level` is enabled.
+pub fn write(level: LogLevel, source: &str, message: impl fmt::Display) {
+    if !enabled(level) { return; }
+    let message = message.to_string();
+    // Multi-line messages, such as shader logs, start on their own line
+    if message.contains('\n') {
+        eprintln!("[{} {}]\n{}", level, source, message.trim_end());
+    } else {
+        eprintln!("[{} {}] {}", level, source, message);
+    }
+}
+
+pub fn error(source: &str, message: impl fmt::Display) { write(LogLevel::Error, source, message) }
+pub fn warn(source: &str, message: impl fmt::Display) { write(LogLevel::Warn, source, message) }
+pub fn info(source: &str, message: impl fmt::Display) { write(LogLevel::Info, source, message) }
+pub fn debug(source: &str, message: impl fmt::Display) { write(LogLevel::Debug, source, message) }
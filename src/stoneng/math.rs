@@ -0,0 +1,160 @@
+//! Small math helpers shared by the engine's time-based effects.
+
+use serde::Deserialize;
+
+/// Easing curves used to shape an effect's progress over its duration.
+///
+/// Every curve maps `t` in `[0, 1]` to `[0, 1]` with `ease(0) == 0` and
+/// `ease(1) == 1`.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineInOut,
+    /// Overshoots the target slightly before settling.
+    BackOut,
+    /// Decaying bounce, useful for recoil.
+    ElasticOut,
+}
+impl Easing {
+    /// Applies the easing curve to `t`, clamping it to `[0, 1]` first.
+    pub fn ease(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear      => t,
+            Easing::QuadIn      => t * t,
+            Easing::QuadOut     => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut   => {
+                if t < 0.5 { 2.0 * t * t }
+                else { 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0 }
+            },
+            Easing::CubicIn     => t * t * t,
+            Easing::CubicOut    => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut  => {
+                if t < 0.5 { 4.0 * t * t * t }
+                else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 }
+            },
+            Easing::SineInOut   => -((std::f32::consts::PI * t).cos() - 1.0) / 2.0,
+            Easing::BackOut     => {
+                let c1 = 1.70158;
+                let c3 = c1 + 1.0;
+                1.0 + c3 * (t - 1.0).powi(3) + c1 * (t - 1.0).powi(2)
+            },
+            Easing::ElasticOut  => {
+                if t == 0.0 || t == 1.0 { return t; }
+                let c4 = (2.0 * std::f32::consts::PI) / 3.0;
+                2f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * c4).sin() + 1.0
+            },
+        }
+    }
+}
+
+/// Linearly interpolates between `a` and `b`.
+pub fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Hashes an integer lattice point into a pseudo-random value in `[-1, 1]`.
+fn hash(x: i32, seed: u32) -> f32 {
+    let mut h = (x as u32).wrapping_mul(0x27d4_eb2d) ^ seed.wrapping_mul(0x9e37_79b9);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+    (h as f32 / u32::MAX as f32) * 2.0 - 1.0
+}
+
+/// Smooth 1D value noise in `[-1, 1]`.
+///
+/// Different seeds produce uncorrelated signals, which allows a single
+/// time value to drive several independent channels (e.g. x and y shake).
+pub fn noise1d(x: f32, seed: u32) -> f32 {
+    let x0 = x.floor();
+    let t = x - x0;
+    // Smoothstep the interpolant to hide the lattice
+    let t = t * t * (3.0 - 2.0 * t);
+    lerp(hash(x0 as i32, seed), hash(x0 as i32 + 1, seed), t)
+}
+
+/// Values that can be blended by a `Curve`.
+pub trait Interpolate: Copy {
+    fn interpolate(a: Self, b: Self, t: f32) -> Self;
+}
+impl Interpolate for f32 {
+    fn interpolate(a: Self, b: Self, t: f32) -> Self { lerp(a, b, t) }
+}
+impl Interpolate for (f32, f32) {
+    fn interpolate(a: Self, b: Self, t: f32) -> Self {
+        (lerp(a.0, b.0, t), lerp(a.1, b.1, t))
+    }
+}
+impl Interpolate for (f32, f32, f32) {
+    fn interpolate(a: Self, b: Self, t: f32) -> Self {
+        (lerp(a.0, b.0, t), lerp(a.1, b.1, t), lerp(a.2, b.2, t))
+    }
+}
+impl Interpolate for (f32, f32, f32, f32) {
+    fn interpolate(a: Self, b: Self, t: f32) -> Self {
+        (lerp(a.0, b.0, t), lerp(a.1, b.1, t), lerp(a.2, b.2, t), lerp(a.3, b.3, t))
+    }
+}
+
+/// A value changing over time, described by `(time, value)` keyframes.
+///
+/// Values between keyframes are linearly interpolated, and values outside of
+/// the keyframes hold the first or last value. In RON a curve is simply a list
+/// of keyframes, e.g. `[(0.0, 1.0), (0.5, 2.0), (1.0, 0.0)]`.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "Vec<(f32, T)>")]
+#[serde(bound = "T: Deserialize<'de> + Interpolate")]
+pub struct Curve<T: Interpolate> {
+    keys: Vec<(f32, T)>,
+}
+impl<T: Interpolate> Curve<T> {
+    /// Builds a curve from keyframes, which may be given in any order.
+    pub fn new(mut keys: Vec<(f32, T)>) -> Self {
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { keys }
+    }
+
+    /// A curve that always has the same value.
+    pub fn constant(value: T) -> Self {
+        Self { keys: vec![(0.0, value)] }
+    }
+
+    /// Finds the value of the curve at `t`.
+    ///
+    /// Empty curves return `None`.
+    pub fn sample(&self, t: f32) -> Option<T> {
+        let first = self.keys.first()?;
+        if t <= first.0 { return Some(first.1); }
+        for pair in self.keys.windows(2) {
+            let ((t0, a), (t1, b)) = (pair[0], pair[1]);
+            if t <= t1 {
+                let span = t1 - t0;
+                let f = if span > 0.0 { (t - t0) / span } else { 1.0 };
+                return Some(T::interpolate(a, b, f));
+            }
+        }
+        self.keys.last().map(|k| k.1)
+    }
+
+    /// The time of the last keyframe.
+    pub fn duration(&self) -> f32 {
+        self.keys.last().map(|k| k.0).unwrap_or(0.0)
+    }
+
+    pub fn keys(&self) -> &[(f32, T)] { &self.keys[..] }
+}
+impl<T: Interpolate> From<Vec<(f32, T)>> for Curve<T> {
+    fn from(keys: Vec<(f32, T)>) -> Self {
+        Self::new(keys)
+    }
+}
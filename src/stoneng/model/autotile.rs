@@ -0,0 +1,176 @@
+#![allow(dead_code)]
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+/// How a tile's neighbors are turned into a mask.
+///
+/// Neighbors are read with +y pointing up (north).
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AutotileMode {
+    /// 4-bit mask of the edge neighbors: N = 1, E = 2, S = 4, W = 8.
+    /// Needs up to 16 variants.
+    #[default]
+    Cardinal,
+    /// 8-bit mask of all neighbors: N = 1, NE = 2, E = 4, SE = 8, S = 16,
+    /// SW = 32, W = 64, NW = 128.
+    ///
+    /// Corners are only counted when both edges beside them are connected, as
+    /// in "blob" tilesets, which reduces the set to 47 variants.
+    Blob,
+    /// 4-bit mask of filled corners: NE = 1, SE = 2, SW = 4, NW = 8, as in
+    /// 2-corner Wang tiles. A corner is filled when the tile's edge and corner
+    /// neighbors around it are all connected. Needs up to 16 variants.
+    Corners,
+}
+
+/// Bits of a neighbor mask, before reduction by an AutotileMode.
+pub mod neighbor {
+    pub const N:    u8 = 1;
+    pub const NE:   u8 = 2;
+    pub const E:    u8 = 4;
+    pub const SE:   u8 = 8;
+    pub const S:    u8 = 16;
+    pub const SW:   u8 = 32;
+    pub const W:    u8 = 64;
+    pub const NW:   u8 = 128;
+
+    /// Offsets of each neighbor, in bit order.
+    pub const OFFSETS: [(i32, i32); 8] = [
+        (0, 1), (1, 1), (1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (-1, 1),
+    ];
+}
+
+/// A rule set picking a sprite variant from the neighbors of a tile.
+///
+/// Rules are part of a SpriteSchema, and map masks to the names of its variants.
+/// ```ron
+/// "dirt": (
+///     root: 504,
+///     variants: { "center": ( root: 505 ), "edge-n": ( root: 506 ), .. },
+///     autotile: Some((
+///         mode:   Cardinal,
+///         tiles:  { 15: "center", 14: "edge-n", .. },
+///         // Tiles of these groups also count as connected
+///         connects_to: ["stone"],
+///     )),
+/// ),
+/// ```
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct AutotileRules {
+    #[serde(default)]
+    pub mode:           AutotileMode,
+    /// Variant names by mask.
+    pub tiles:          HashMap<u8, String>,
+    /// The variant used for masks without a rule. The sprite itself is used
+    /// if there is no fallback.
+    #[serde(default)]
+    pub fallback:       Option<String>,
+    /// The name tiles are connected by. Tiles without a group only connect to
+    /// tiles of the same sprite.
+    #[serde(default)]
+    pub group:          Option<String>,
+    /// Other groups counted as connected neighbors.
+    #[serde(default)]
+    pub connects_to:    Vec<String>,
+}
+
+impl AutotileRules {
+    /// Reduces a full neighbor mask (see `neighbor`) to this rule set's mode.
+    ///
+    /// # Example
+    /// `Cardinal` keeps the edge neighbors only, as N = 1, E = 2, S = 4, W = 8.
+    /// ```
+    /// # use stoneng::model::autotile::{AutotileRules, neighbor::*};
+    /// let rules: AutotileRules = ron::from_str("(mode: Cardinal, tiles: {})").unwrap();
+    /// assert_eq!(rules.mask(N | NE | E), 1 | 2);
+    /// assert_eq!(rules.mask(NE | SE | SW | NW), 0);
+    /// ```
+    /// `Blob` keeps the full mask, less the corners missing an edge beside them.
+    /// ```
+    /// # use stoneng::model::autotile::{AutotileRules, neighbor::*};
+    /// let rules: AutotileRules = ron::from_str("(mode: Blob, tiles: {})").unwrap();
+    /// assert_eq!(rules.mask(N | NE | E), N | NE | E);
+    /// // Without E, the NE corner doesn't count
+    /// assert_eq!(rules.mask(N | NE | S), N | S);
+    /// ```
+    /// `Corners` sets a bit for each corner surrounded by neighbors, as NE = 1,
+    /// SE = 2, SW = 4, NW = 8.
+    /// ```
+    /// # use stoneng::model::autotile::{AutotileRules, neighbor::*};
+    /// let rules: AutotileRules = ron::from_str("(mode: Corners, tiles: {})").unwrap();
+    /// assert_eq!(rules.mask(N | NE | E | S), 1);
+    /// assert_eq!(rules.mask(0xFF), 1 | 2 | 4 | 8);
+    /// ```
+    pub fn mask(&self, neighbors: u8) -> u8 {
+        use neighbor::*;
+        let has = |bit: u8| neighbors & bit != 0;
+        match self.mode {
+            AutotileMode::Cardinal => {
+                (has(N) as u8) | (has(E) as u8) << 1 | (has(S) as u8) << 2 | (has(W) as u8) << 3
+            },
+            AutotileMode::Blob => {
+                let mut mask = neighbors & (N | E | S | W);
+                if has(NE) && has(N) && has(E) { mask |= NE; }
+                if has(SE) && has(S) && has(E) { mask |= SE; }
+                if has(SW) && has(S) && has(W) { mask |= SW; }
+                if has(NW) && has(N) && has(W) { mask |= NW; }
+                mask
+            },
+            AutotileMode::Corners => {
+                (has(N) && has(NE) && has(E)) as u8
+                    | ((has(E) && has(SE) && has(S)) as u8) << 1
+                    | ((has(S) && has(SW) && has(W)) as u8) << 2
+                    | ((has(W) && has(NW) && has(N)) as u8) << 3
+            },
+        }
+    }
+
+    /// Finds the variant name for a full neighbor mask.
+    ///
+    /// # Example
+    /// ```
+    /// # use stoneng::model::autotile::{AutotileRules, neighbor::*};
+    /// let layout = r#"(
+    ///     mode:       Cardinal,
+    ///     tiles:      { 15: "center", 5: "vertical" },
+    ///     fallback:   Some("island"),
+    /// )"#;
+    /// let rules: AutotileRules = ron::from_str(layout).unwrap();
+    ///
+    /// assert_eq!(rules.variant(0xFF), Some("center"));
+    /// assert_eq!(rules.variant(N | NE | S), Some("vertical"));
+    /// // Masks without a rule use the fallback
+    /// assert_eq!(rules.variant(N), Some("island"));
+    /// ```
+    pub fn variant(&self, neighbors: u8) -> Option<&str> {
+        self.tiles.get(&self.mask(neighbors))
+            .or(self.fallback.as_ref())
+            .map(|s| &s[..])
+    }
+
+    /// True if a tile using `other` rules counts as a neighbor of this one.
+    ///
+    /// Tiles without a group are matched by the caller, by sprite.
+    ///
+    /// # Example
+    /// ```
+    /// # use stoneng::model::autotile::AutotileRules;
+    /// let parse = |layout: &str| ron::from_str::<AutotileRules>(layout).unwrap();
+    /// let grass = parse(r#"(tiles: {}, group: Some("grass"), connects_to: ["dirt"])"#);
+    /// let dirt = parse(r#"(tiles: {}, group: Some("dirt"))"#);
+    ///
+    /// // Grass edges blend into dirt, but dirt stops at grass
+    /// assert!(grass.connects(&dirt));
+    /// assert!(!dirt.connects(&grass));
+    /// assert!(grass.connects(&grass));
+    /// ```
+    pub fn connects(&self, other: &AutotileRules) -> bool {
+        match &other.group {
+            Some(group) => {
+                self.group.as_ref() == Some(group) || self.connects_to.contains(group)
+            },
+            None => false,
+        }
+    }
+}
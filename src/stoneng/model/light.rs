@@ -0,0 +1,162 @@
+#![allow(dead_code)]
+use crate::EngineError;
+use crate::math::{self, Curve};
+
+use std::{
+    path,
+    sync::Arc,
+    collections::HashMap,
+};
+
+use serde::Deserialize;
+
+/// The shape of a repeating `LightSignal::Wave`, each going from 0 to 1.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Waveform {
+    /// A smooth pulse, starting halfway up
+    #[default]
+    Sine,
+    /// Rises and falls linearly, starting at 0
+    Triangle,
+    /// On for the first half of each cycle, off for the second
+    Square,
+    /// Rises linearly, then drops back to 0
+    Sawtooth,
+}
+impl Waveform {
+    /// The value of the wave after `cycles` cycles.
+    pub fn sample(&self, cycles: f32) -> f32 {
+        let t = cycles - cycles.floor();
+        match self {
+            Waveform::Sine      => 0.5 + 0.5 * (t * std::f32::consts::TAU).sin(),
+            Waveform::Triangle  => 1.0 - (2.0 * t - 1.0).abs(),
+            Waveform::Square    => if t < 0.5 { 1.0 } else { 0.0 },
+            Waveform::Sawtooth  => t,
+        }
+    }
+}
+
+/// A value changing over time, used as a multiplier on a light.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub enum LightSignal {
+    /// Always the same value.
+    Constant(f32),
+    /// Wanders randomly between `min` and `max`, changing direction around
+    /// `speed` times a second. Suits flames.
+    Noise { min: f32, max: f32, speed: f32 },
+    /// Repeats a wave between `min` and `max`, `frequency` times a second.
+    /// `phase` shifts the wave by a fraction of a cycle.
+    Wave {
+        shape:      Waveform,
+        min:        f32,
+        max:        f32,
+        frequency:  f32,
+        #[serde(default)]
+        phase:      f32,
+    },
+    /// Follows keyframes over time, see `LightAnimPreset::period`.
+    Curve(Curve<f32>),
+}
+impl Default for LightSignal {
+    fn default() -> Self {
+        LightSignal::Constant(1.0)
+    }
+}
+impl LightSignal {
+    /// The value of the signal at `time` seconds. Lights with different seeds
+    /// get unrelated noise.
+    pub fn sample(&self, time: f32, seed: u32) -> f32 {
+        match self {
+            LightSignal::Constant(value) => *value,
+            LightSignal::Noise { min, max, speed } => {
+                let n = math::noise1d(time * speed, seed) * 0.5 + 0.5;
+                math::lerp(*min, *max, n)
+            },
+            LightSignal::Wave { shape, min, max, frequency, phase } => {
+                math::lerp(*min, *max, shape.sample(time * frequency + phase))
+            },
+            LightSignal::Curve(curve) => curve.sample(time).unwrap_or(1.0),
+        }
+    }
+}
+
+/// Describes how a `LightAnimation` changes a light over time.
+///
+/// Each part multiplies the light's own settings, so one preset can animate
+/// lights of any size and color.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct LightAnimPreset {
+    /// Multiplies the light's color, brightening or dimming it.
+    #[serde(default)]
+    pub brightness:     LightSignal,
+    /// Multiplies the light's reach, its PointLight intensity or SpotLight range.
+    #[serde(default)]
+    pub radius:         LightSignal,
+    /// Multiplies the light's color over time, as (r, g, b).
+    #[serde(default)]
+    pub color:          Option<Curve<(f32, f32, f32)>>,
+    /// Seconds after which keyframe curves start over. Without a period,
+    /// curves hold their last value.
+    #[serde(default)]
+    pub period:         Option<f32>,
+}
+impl LightAnimPreset {
+    /// The time keyframe curves are sampled at, `time` wrapped to the period.
+    pub fn curve_time(&self, time: f32) -> f32 {
+        match self.period {
+            Some(period) if period > 0.0 => time.rem_euclid(period),
+            _ => time,
+        }
+    }
+}
+
+/// A named collection of light animation presets.
+#[derive(Deserialize, Debug, Default)]
+pub struct LightAnimPresets {
+    pub presets:        HashMap<String, Arc<LightAnimPreset>>,
+}
+
+impl LightAnimPresets {
+    /// Takes a set of light animation presets in Rusty Object Notation.
+    ///
+    /// # Example
+    /// ```
+    /// # use stoneng::model::light::*;
+    /// let layout = r#"
+    /// LightAnimPresets (
+    ///     presets: {
+    ///         "torch": (
+    ///             brightness: Noise(min: 0.7, max: 1.0, speed: 8.0),
+    ///             radius:     Noise(min: 0.9, max: 1.0, speed: 3.0),
+    ///         ),
+    ///         "alarm": (
+    ///             brightness: Wave(shape: Square, min: 0.2, max: 1.0, frequency: 2.0),
+    ///             // Curves are lists of (seconds, value)
+    ///             color:      Some([(0.0, (1.0, 0.2, 0.2)), (1.0, (1.0, 0.6, 0.2)), (2.0, (1.0, 0.2, 0.2))]),
+    ///             period:     Some(2.0),
+    ///         ),
+    ///     }
+    /// )
+    /// "#;
+    /// let presets = LightAnimPresets::from_string(layout.into()).unwrap();
+    ///
+    /// let alarm = presets.get("alarm").unwrap();
+    /// assert_eq!(alarm.brightness.sample(0.1, 0), 1.0);
+    /// assert_eq!(alarm.brightness.sample(0.3, 0), 0.2);
+    /// assert_eq!(alarm.curve_time(2.5), 0.5);
+    /// ```
+    pub fn from_string(layout: String) -> Result<Self, EngineError> {
+        Ok(ron::from_str::<LightAnimPresets>(&layout)?)
+    }
+
+    /// Takes a path to a presets file in Rusty Object Notation and deserializes it.
+    /// The format can be found in LightAnimPresets::from_string().
+    pub fn from_layout(path_to_layout: String) -> Result<Self, EngineError> {
+        let layout_string = std::fs::read_to_string(path::PathBuf::from(&path_to_layout))?;
+        Self::from_string(layout_string)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<LightAnimPreset>> {
+        self.presets.get(name).cloned()
+    }
+}
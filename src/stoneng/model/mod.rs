@@ -1,2 +1,7 @@
 pub mod spritesheet;
 pub mod tilemap;
+pub mod palette;
+pub mod particle;
+pub mod autotile;
+pub mod parallax;
+pub mod light;
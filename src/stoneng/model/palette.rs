@@ -0,0 +1,201 @@
+#![allow(dead_code)]
+use crate::EngineError;
+use crate::renderer::texture;
+
+use std::{
+    path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use serde::Deserialize;
+
+/// Every change to a `Palettes` takes a new version, so renderers know to re-upload.
+static NEXT_PALETTE_VERSION: AtomicU64 = AtomicU64::new(1);
+
+/// The maximum number of colors in a palette, as indices are stored in a single byte.
+pub const MAX_PALETTE_COLORS: usize = 256;
+
+/// An RGBA color, 0-255 per channel.
+pub type PaletteColor = (u8, u8, u8, u8);
+
+/// A set of named color palettes used to color indexed sprites.
+///
+/// Indexed sprites store a palette index in the red channel of their art. Each
+/// palette is a row of colors, so an entity's colors can be swapped by pointing
+/// its `Palette` component at a different row, or by changing the row itself.
+///
+/// Stored as a resource, the sprite renderers upload the palettes whenever they change.
+#[derive(Debug, Clone, Default)]
+pub struct Palettes {
+    version:    u64,
+    /// Colors per palette, i.e. the width of each row.
+    width:      usize,
+    names:      Vec<String>,
+    /// Rows of RGBA colors, `width` colors long.
+    data:       Vec<u8>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "Palettes")]
+struct PaletteLayout {
+    palettes:   Vec<PaletteEntry>,
+}
+
+#[derive(Deserialize)]
+struct PaletteEntry {
+    name:       String,
+    colors:     Vec<PaletteColor>,
+}
+
+impl Palettes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads palettes from a PNG, where each row of pixels is one palette.
+    /// Images without alpha are treated as opaque.
+    ///
+    /// The rows are named by their index ("0", "1", ..) and can be renamed with
+    /// `rename`.
+    pub fn from_png(bytes: &[u8]) -> Result<Self, EngineError> {
+        let img = texture::decode(bytes).map_err(|err| {
+            EngineError::PaletteError(format!("Failed to load palette image.\n{}", err))
+        })?;
+        if img.width as usize > MAX_PALETTE_COLORS {
+            return Err(EngineError::PaletteError(
+                format!("Palettes can hold at most {} colors, found {}", MAX_PALETTE_COLORS, img.width)
+            ));
+        }
+
+        Ok(Self {
+            version: NEXT_PALETTE_VERSION.fetch_add(1, Ordering::Relaxed),
+            width: img.width as usize,
+            names: (0..img.height).map(|i| i.to_string()).collect(),
+            data: img.data,
+        })
+    }
+
+    /// Takes a palette set in Rusty Object Notation.
+    ///
+    /// # Example
+    /// ```
+    /// # use stoneng::model::palette::*;
+    /// let layout = r#"
+    /// Palettes (
+    ///     palettes: [
+    ///         // Colors are (r, g, b, a) from 0 to 255, in index order
+    ///         (name: "default", colors: [(0, 0, 0, 0), (40, 60, 120, 255)]),
+    ///         (name: "red",     colors: [(0, 0, 0, 0), (170, 30, 30, 255)]),
+    ///     ]
+    /// )
+    /// "#;
+    /// let palettes = Palettes::from_string(layout.into()).unwrap();
+    ///
+    /// assert_eq!(palettes.row("red"), Some(1));
+    /// assert_eq!(palettes.color(1, 1), Some((170, 30, 30, 255)));
+    /// ```
+    pub fn from_string(layout: String) -> Result<Self, EngineError> {
+        let layout = ron::from_str::<PaletteLayout>(&layout)?;
+        let mut palettes = Self::new();
+        for entry in layout.palettes {
+            palettes.set(&entry.name, &entry.colors)?;
+        }
+        Ok(palettes)
+    }
+
+    /// Takes a path to a palette file in Rusty Object Notation and deserializes it.
+    /// The format can be found in Palettes::from_string().
+    pub fn from_layout(path_to_layout: String) -> Result<Self, EngineError> {
+        let layout_string = std::fs::read_to_string(path::PathBuf::from(&path_to_layout))?;
+        Self::from_string(layout_string)
+    }
+
+    /// Sets the colors of a palette, adding it if there is no palette of that name.
+    ///
+    /// Palettes shorter than the widest palette are padded with transparent colors.
+    pub fn set(&mut self, name: &str, colors: &[PaletteColor]) -> Result<u16, EngineError> {
+        if colors.len() > MAX_PALETTE_COLORS {
+            return Err(EngineError::PaletteError(
+                format!("Palettes can hold at most {} colors, found {}", MAX_PALETTE_COLORS, colors.len())
+            ));
+        }
+        if colors.len() > self.width {
+            self.widen(colors.len());
+        }
+
+        let row = match self.row(name) {
+            Some(row) => row as usize,
+            None => {
+                if self.names.len() > u16::MAX as usize {
+                    return Err(EngineError::PaletteError("Too many palettes".into()));
+                }
+                self.names.push(name.into());
+                self.data.resize(self.data.len() + self.width * 4, 0);
+                self.names.len() - 1
+            },
+        };
+
+        let start = row * self.width * 4;
+        let row_data = &mut self.data[start .. start + self.width * 4];
+        row_data.fill(0);
+        for (i, (r, g, b, a)) in colors.iter().enumerate() {
+            row_data[i*4 .. i*4 + 4].copy_from_slice(&[*r, *g, *b, *a]);
+        }
+
+        self.touch();
+        Ok(row as u16)
+    }
+
+    /// Sets a single color of a palette.
+    pub fn set_color(&mut self, row: u16, index: u8, color: PaletteColor) {
+        let (row, index) = (row as usize, index as usize);
+        if row >= self.names.len() || index >= self.width { return; }
+        let start = (row * self.width + index) * 4;
+        self.data[start .. start + 4].copy_from_slice(&[color.0, color.1, color.2, color.3]);
+        self.touch();
+    }
+
+    /// Renames a palette, e.g. after loading from a PNG.
+    pub fn rename(&mut self, row: u16, name: &str) {
+        if let Some(n) = self.names.get_mut(row as usize) {
+            *n = name.into();
+        }
+    }
+
+    /// Finds the row of a palette by name.
+    pub fn row(&self, name: &str) -> Option<u16> {
+        self.names.iter().position(|n| n == name).map(|i| i as u16)
+    }
+
+    pub fn color(&self, row: u16, index: u8) -> Option<PaletteColor> {
+        let (row, index) = (row as usize, index as usize);
+        if row >= self.names.len() || index >= self.width { return None; }
+        let c = &self.data[(row * self.width + index) * 4 ..];
+        Some((c[0], c[1], c[2], c[3]))
+    }
+
+    /// The number of palettes.
+    pub fn len(&self) -> usize { self.names.len() }
+    pub fn is_empty(&self) -> bool { self.names.is_empty() }
+    /// The number of colors in each palette.
+    pub fn width(&self) -> usize { self.width }
+    /// RGBA data of all palettes, one row per palette.
+    pub fn data(&self) -> &[u8] { &self.data[..] }
+    /// Changes whenever the palettes are modified.
+    pub fn version(&self) -> u64 { self.version }
+
+    /// Repacks every row to a new width, padding with transparent colors.
+    fn widen(&mut self, width: usize) {
+        let mut data = vec![0; self.names.len() * width * 4];
+        for row in 0..self.names.len() {
+            let old = &self.data[row * self.width * 4 .. (row + 1) * self.width * 4];
+            data[row * width * 4 .. row * width * 4 + old.len()].copy_from_slice(old);
+        }
+        self.width = width;
+        self.data = data;
+    }
+
+    fn touch(&mut self) {
+        self.version = NEXT_PALETTE_VERSION.fetch_add(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,82 @@
+#![allow(dead_code)]
+use crate::EngineError;
+
+use std::path;
+
+use serde::Deserialize;
+
+/// The settings of a single parallax layer, as found in a RON scene.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ParallaxSettings {
+    /// The name of the sprite in the SpriteSheet to repeat.
+    pub sprite:     String,
+    #[serde(default)]
+    pub variant:    Option<String>,
+    /// How much the layer moves with the camera, per axis. 1 moves with the
+    /// world, 0 stays fixed to the screen and values above 1 move faster than
+    /// the world, as foreground layers do.
+    #[serde(default = "default_factor")]
+    pub factor:     (f32, f32),
+    /// Whether the sprite repeats endlessly along each axis.
+    #[serde(default = "default_repeat")]
+    pub repeat:     (bool, bool),
+    /// Constant movement of the layer in units per second, e.g. drifting fog.
+    #[serde(default)]
+    pub scroll:     (f32, f32),
+    /// The position of the layer's first sprite.
+    #[serde(default)]
+    pub offset:     (f32, f32),
+    /// Draw order, as the z of a Position. Layers behind the floor tiles are
+    /// drawn before the scene, the rest after it without hiding what is
+    /// behind them.
+    #[serde(default)]
+    pub depth:      f32,
+    #[serde(default = "default_scale")]
+    pub scale:      (f32, f32),
+    #[serde(default = "default_color")]
+    pub color:      (f32, f32, f32, f32),
+}
+
+fn default_factor() -> (f32, f32) { (0.5, 0.5) }
+fn default_repeat() -> (bool, bool) { (true, false) }
+fn default_scale() -> (f32, f32) { (1.0, 1.0) }
+fn default_color() -> (f32, f32, f32, f32) { (1.0, 1.0, 1.0, 1.0) }
+
+/// A set of parallax layers, ordered back to front.
+#[derive(Deserialize, Debug, Default)]
+pub struct ParallaxScene {
+    pub layers: Vec<ParallaxSettings>,
+}
+
+impl ParallaxScene {
+    /// Takes a set of parallax layers in Rusty Object Notation.
+    ///
+    /// # Example
+    /// ```
+    /// # use stoneng::model::parallax::*;
+    /// let layout = r#"
+    /// ParallaxScene (
+    ///     layers: [
+    ///         // Only the sprite is required, see ParallaxSettings for all parameters
+    ///         (sprite: "cave-wall", factor: (0.3, 0.3), depth: -20.0),
+    ///         (sprite: "fog", factor: (0.6, 0.6), scroll: (4.0, 0.0), repeat: (true, true),
+    ///          color: (1.0, 1.0, 1.0, 0.4), depth: -15.0),
+    ///     ]
+    /// )
+    /// "#;
+    /// let scene = ParallaxScene::from_string(layout.into()).unwrap();
+    ///
+    /// assert_eq!(scene.layers.len(), 2);
+    /// assert_eq!(scene.layers[1].repeat, (true, true));
+    /// ```
+    pub fn from_string(layout: String) -> Result<Self, EngineError> {
+        Ok(ron::from_str::<ParallaxScene>(&layout)?)
+    }
+
+    /// Takes a path to a parallax scene in Rusty Object Notation and deserializes it.
+    /// The format can be found in ParallaxScene::from_string().
+    pub fn from_layout(path_to_layout: String) -> Result<Self, EngineError> {
+        let layout_string = std::fs::read_to_string(path::PathBuf::from(&path_to_layout))?;
+        Self::from_string(layout_string)
+    }
+}
@@ -0,0 +1,128 @@
+#![allow(dead_code)]
+use crate::EngineError;
+use crate::math::Curve;
+
+use std::{
+    path,
+    sync::Arc,
+    collections::HashMap,
+};
+
+use serde::Deserialize;
+
+/// Describes how a `ParticleEmitter` spawns and animates its particles.
+///
+/// Ranges are given as `(min, max)` and each particle picks a random value in
+/// between. Curves are sampled over a particle's life, from 0 (spawn) to 1 (death).
+#[derive(Deserialize, Debug, Clone)]
+pub struct EmitterPreset {
+    /// The name of the sprite in the SpriteSheet drawn for each particle.
+    pub sprite:         String,
+    /// A variant of the sprite to use instead.
+    #[serde(default)]
+    pub variant:        Option<String>,
+    /// An animation of the sprite played over each particle's life.
+    ///
+    /// Animations with a `frame_time` of 0 are stretched over the particle's life.
+    #[serde(default)]
+    pub animation:      Option<String>,
+
+    /// Particles spawned per second, while emitting.
+    #[serde(default)]
+    pub rate:           f32,
+    /// Particles spawned at once when the emitter starts.
+    #[serde(default)]
+    pub burst:          u32,
+    /// Seconds to emit for before stopping. Emitters without a duration
+    /// emit until stopped.
+    #[serde(default)]
+    pub duration:       Option<f32>,
+    /// The most particles the emitter may have alive at once.
+    #[serde(default = "default_max_particles")]
+    pub max_particles:  usize,
+
+    /// Seconds each particle lives for.
+    #[serde(default = "default_lifetime")]
+    pub lifetime:       (f32, f32),
+    /// Initial speed in units per second.
+    #[serde(default)]
+    pub speed:          (f32, f32),
+    /// The center of the velocity cone, in degrees counter-clockwise from +x.
+    #[serde(default)]
+    pub direction:      f32,
+    /// The width of the velocity cone in degrees. 360 emits in all directions.
+    #[serde(default)]
+    pub spread:         f32,
+    /// Particles spawn at a random point within this radius of the emitter.
+    #[serde(default)]
+    pub radius:         f32,
+    /// Acceleration applied to every particle, in units per second squared.
+    #[serde(default)]
+    pub gravity:        (f32, f32),
+    /// The fraction of velocity lost per second.
+    #[serde(default)]
+    pub drag:           f32,
+
+    /// Color multiplier over life, as (r, g, b, a).
+    #[serde(default = "default_color")]
+    pub color:          Curve<(f32, f32, f32, f32)>,
+    /// Scale over life.
+    #[serde(default = "default_scale")]
+    pub scale:          Curve<f32>,
+}
+
+fn default_max_particles() -> usize { 1000 }
+fn default_lifetime() -> (f32, f32) { (1.0, 1.0) }
+fn default_color() -> Curve<(f32, f32, f32, f32)> { Curve::constant((1.0, 1.0, 1.0, 1.0)) }
+fn default_scale() -> Curve<f32> { Curve::constant(1.0) }
+
+/// A named collection of emitter presets.
+#[derive(Deserialize, Debug, Default)]
+pub struct ParticlePresets {
+    pub presets:        HashMap<String, Arc<EmitterPreset>>,
+}
+
+impl ParticlePresets {
+    /// Takes a set of emitter presets in Rusty Object Notation.
+    ///
+    /// # Example
+    /// ```
+    /// # use stoneng::model::particle::*;
+    /// let layout = r#"
+    /// ParticlePresets (
+    ///     presets: {
+    ///         // Only the sprite is required, see EmitterPreset for all parameters
+    ///         "sparks": (
+    ///             sprite:     "spark",
+    ///             burst:      20,
+    ///             lifetime:   (0.2, 0.5),
+    ///             speed:      (20.0, 60.0),
+    ///             spread:     360.0,
+    ///             gravity:    (0.0, -90.0),
+    ///             // Curves are lists of (time, value), time going from 0 to 1
+    ///             color:      [(0.0, (1.0, 1.0, 1.0, 1.0)), (1.0, (1.0, 0.3, 0.0, 0.0))],
+    ///             scale:      [(0.0, 1.0), (1.0, 0.5)],
+    ///         ),
+    ///     }
+    /// )
+    /// "#;
+    /// let presets = ParticlePresets::from_string(layout.into()).unwrap();
+    ///
+    /// assert_eq!(presets.presets["sparks"].burst, 20);
+    /// assert_eq!(presets.presets["sparks"].scale.sample(0.5), Some(0.75));
+    /// ```
+    pub fn from_string(layout: String) -> Result<Self, EngineError> {
+        Ok(ron::from_str::<ParticlePresets>(&layout)?)
+    }
+
+    /// Takes a path to a presets file in Rusty Object Notation and deserializes it.
+    /// The format can be found in ParticlePresets::from_string().
+    pub fn from_layout(path_to_layout: String) -> Result<Self, EngineError> {
+        let layout_string = std::fs::read_to_string(path::PathBuf::from(&path_to_layout))?;
+        Self::from_string(layout_string)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<EmitterPreset>> {
+        self.presets.get(name).cloned()
+    }
+}
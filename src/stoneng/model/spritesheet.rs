@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 use crate::EngineError;
+use crate::model::autotile::AutotileRules;
 
 use std::{
     path,
@@ -36,7 +37,7 @@ impl SpriteSheet {
     ///
     /// # Example 
     /// ```
-    /// # use stoneng::sprite::*;
+    /// # use stoneng::model::spritesheet::*;
     /// let layout = r#"
     /// SpriteSheet ( 
     ///     sheet_width:    256,
@@ -54,6 +55,17 @@ impl SpriteSheet {
     ///             root: 9,
     ///             dimensions: (2,2),
     ///         ),
+    ///         // Picks a variant from neighboring tiles, see AutotileRules
+    ///         "dirt": (
+    ///             root: 20,
+    ///             variants: { "center": ( root: 21 ), "island": ( root: 22 ) },
+    ///             autotile: Some(( mode: Cardinal, tiles: { 15: "center", 0: "island" } )),
+    ///         ),
+    ///         // Drawn stretched by a Panel, keeping 3px borders
+    ///         "frame": (
+    ///             root: 12,
+    ///             kind: NineSlice(left: 3, right: 3, top: 3, bottom: 3),
+    ///         ),
     ///         "water": (
     ///             root: 3,
     ///             // Note that animations is a map
@@ -80,6 +92,8 @@ impl SpriteSheet {
     /// assert_eq!(sheet.sheet_width, 256);
     /// assert_eq!(sheet.sprites["arch"].root, 9);
     /// assert!(sheet.sprites["water"].animations.contains_key("idle"));
+    /// assert_eq!(sheet.sprites["frame"].kind.insets(), (3, 3, 3, 3));
+    /// assert_eq!(sheet.sprites["dirt"].autotile.as_ref().unwrap().variant(0xFF), Some("center"));
     /// ```
     pub fn from_string(layout: String, path_to_img: String) -> Result<Self, EngineError> {
         // Deserialize the layout
@@ -175,4 +189,40 @@ pub struct SpriteSchema {
     /// A map of animation schema that the sprite can use.
     #[serde(default)]
     pub animations:      HashMap<String, Arc<AnimationSchema>>,
+
+    /// If the sprite's art stores palette indices in its red channel, to be
+    /// colored by a row of the `Palettes` resource.
+    #[serde(default)]
+    pub indexed:        bool,
+
+    /// How the sprite is drawn, see SpriteKind.
+    #[serde(default)]
+    pub kind:           SpriteKind,
+
+    /// Rules for picking one of the sprite's variants from neighboring tiles.
+    #[serde(default)]
+    pub autotile:       Option<Arc<AutotileRules>>,
+}
+
+/// Describes how a sprite's art is stretched when drawn.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpriteKind {
+    /// The art is drawn as a single quad.
+    #[default]
+    Simple,
+    /// The art is split into a 3x3 grid by border insets, in pixels. When drawn
+    /// as a `Panel`, the corners keep their size, the edges stretch along one
+    /// axis and the center stretches along both.
+    ///
+    /// In RON: `kind: NineSlice(left: 3, right: 3, top: 3, bottom: 3)`
+    NineSlice { left: u8, right: u8, top: u8, bottom: u8 },
+}
+impl SpriteKind {
+    /// The border insets as (left, right, top, bottom), zero for simple sprites.
+    pub fn insets(&self) -> (u8, u8, u8, u8) {
+        match *self {
+            SpriteKind::Simple => (0, 0, 0, 0),
+            SpriteKind::NineSlice { left, right, top, bottom } => (left, right, top, bottom),
+        }
+    }
 }
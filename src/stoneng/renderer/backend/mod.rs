@@ -0,0 +1,451 @@
+//! A small abstraction over the graphics API, covering texture upload, buffer
+//! streaming, draw submission and render targets.
+//!
+//! Everything drawn through a `RenderBackend` is a buffer of vertex data drawn
+//! by one of the engine's `Pipeline`s: plain quads, sprites, panels, text,
+//! debug lines, the passes of the lighting, or full-screen post effects. The `OpenGlBackend` submits these to the GPU, while
+//! the `SoftwareBackend` rasterizes them on the CPU so that frames can be
+//! produced and inspected without a window or GL context, e.g. in headless
+//! tests.
+pub mod opengl;
+pub mod software;
+
+pub use opengl::OpenGlBackend;
+pub use software::SoftwareBackend;
+
+use crate::EngineError;
+use crate::ecs::component::Material;
+use crate::renderer::{
+    sprite::{RenderSprite, SheetLayout},
+    nine_slice::RenderPanel,
+    text::RenderChar,
+    light::{RenderLight, LightingMode},
+    debug::DebugVertex,
+    post::PostEffect,
+    texture::TextureOptions,
+};
+use glm::Mat4;
+
+/// A texture owned by a backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TextureHandle(pub(crate) u32);
+
+/// A vertex buffer owned by a backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BufferHandle(pub(crate) u32);
+
+/// An off-screen render target owned by a backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TargetHandle(pub(crate) u32);
+
+/// A compiled `PostEffect::Custom` shader owned by a backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ShaderHandle(pub(crate) u32);
+
+/// How a texture is sampled between texels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    #[default]
+    Nearest,
+    Linear,
+}
+
+/// How drawn colors are combined with the colors already in the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    /// Replaces the target color.
+    Opaque,
+    /// Standard alpha blending.
+    #[default]
+    Alpha,
+    /// Alpha blending of colors already multiplied by their alpha, e.g. from
+    /// textures loaded with `TextureOptions::premultiplied`.
+    Premultiplied,
+    /// Adds the color, scaled by its alpha, to the target.
+    Additive,
+    /// Multiplies the target color.
+    Multiply,
+}
+
+/// A vertex of a quad.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct QuadVertex {
+    pub pos:    (f32, f32, f32),
+    /// Texture coordinate, with (0, 0) at the first texel of the uploaded data
+    pub uv:     (f32, f32),
+    pub color:  (f32, f32, f32, f32),
+}
+
+/// Four vertices in triangle strip order: bottom-left, top-left, bottom-right,
+/// top-right.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Quad {
+    pub verts: [QuadVertex; 4],
+}
+impl Quad {
+    /// An axis aligned quad from `min` to `max`, sampling `uv_min` to `uv_max`.
+    ///
+    /// The uv's y is flipped, so that the first row of a texture appears at the
+    /// top of the quad.
+    pub fn rect(min: (f32, f32), max: (f32, f32), z: f32,
+                uv_min: (f32, f32), uv_max: (f32, f32),
+                color: (f32, f32, f32, f32)) -> Self {
+        let v = |x, y, u, v| QuadVertex { pos: (x, y, z), uv: (u, v), color };
+        Self { verts: [
+            v(min.0, min.1, uv_min.0, uv_max.1),
+            v(min.0, max.1, uv_min.0, uv_min.1),
+            v(max.0, min.1, uv_max.0, uv_max.1),
+            v(max.0, max.1, uv_max.0, uv_min.1),
+        ]}
+    }
+}
+
+/// A texture's size and sampling options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureDesc {
+    pub width:      u32,
+    pub height:     u32,
+    pub options:    TextureOptions,
+}
+impl TextureDesc {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height, options: TextureOptions::default() }
+    }
+
+    pub fn options(mut self, options: TextureOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+/// Vertex data streamed into a buffer, as read by the pipeline drawing it.
+#[derive(Debug, Clone, Copy)]
+pub enum VertexData<'a> {
+    Quads(&'a [Quad]),
+    Sprites(&'a [RenderSprite]),
+    Panels(&'a [RenderPanel]),
+    Chars(&'a [RenderChar]),
+    Lights(&'a [RenderLight]),
+    /// Positions of untextured triangles, three per triangle
+    Triangles(&'a [(f32, f32)]),
+    /// The ends of lines, two per line
+    Lines(&'a [DebugVertex]),
+}
+impl VertexData<'_> {
+    /// The number of drawable items: quads, sprites, panels, chars, lights or
+    /// vertices.
+    pub fn len(&self) -> usize {
+        match self {
+            VertexData::Quads(data)     => data.len(),
+            VertexData::Sprites(data)   => data.len(),
+            VertexData::Panels(data)    => data.len(),
+            VertexData::Chars(data)     => data.len(),
+            VertexData::Lights(data)    => data.len(),
+            VertexData::Triangles(data) => data.len(),
+            VertexData::Lines(data)     => data.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    pub(crate) fn kind(&self) -> VertexKind {
+        match self {
+            VertexData::Quads(_)     => VertexKind::Quads,
+            VertexData::Sprites(_)   => VertexKind::Sprites,
+            VertexData::Panels(_)    => VertexKind::Panels,
+            VertexData::Chars(_)     => VertexKind::Chars,
+            VertexData::Lights(_)    => VertexKind::Lights,
+            VertexData::Triangles(_) => VertexKind::Triangles,
+            VertexData::Lines(_)     => VertexKind::Lines,
+        }
+    }
+}
+
+/// The kind of data held by a buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub(crate) enum VertexKind {
+    #[default]
+    Quads,
+    Sprites,
+    Panels,
+    Chars,
+    Lights,
+    Triangles,
+    Lines,
+}
+
+/// The engine's ways of drawing a buffer, each reading one kind of `VertexData`.
+///
+/// Every pipeline transforms its vertices by the `DrawCall`'s transform, and
+/// samples the `DrawCall`'s texture, if any, from texture unit 0.
+#[derive(Debug, Clone, Copy)]
+pub enum Pipeline<'a> {
+    /// `Quad`s, multiplying the vertex color by the texture.
+    Quads,
+    /// `RenderSprite`s from a spritesheet, see `SpriteRenderer`.
+    ///
+    /// Materials are only supported by the `OpenGlBackend`, others log an
+    /// error and skip the draw.
+    Sprites {
+        sheet:      SheetLayout,
+        /// The palette rows looked up by indexed sprites
+        palettes:   Option<TextureHandle>,
+        material:   Option<&'a Material>,
+        /// Seconds passed to materials
+        time:       f32,
+    },
+    /// `RenderPanel`s from a spritesheet, see `NineSliceRenderer`.
+    Panels { sheet: SheetLayout },
+    /// `RenderChar`s from a font atlas of `glyph_size` square glyphs.
+    Text { glyph_size: f32, atlas_width: f32 },
+    /// Colored lines between pairs of `DebugVertex`es, untextured.
+    Lines,
+    /// `RenderLight`s, added to a lightmap by `gain`.
+    Lights { gain: f32 },
+    /// Triangles marking the stencil, with color writes masked.
+    ShadowVolumes,
+    /// Quads covering the unlit parts of the scene with `shadow_color`, by
+    /// the lightmap in the texture.
+    ShadowMask {
+        mode:           LightingMode,
+        /// The size of lightmap pixels in target pixels
+        lightmap_scale: f32,
+        shadow_color:   (f32, f32, f32),
+        darkness:       f32,
+    },
+    /// Quads tinting the lit parts of the scene by the hue of the lightmap in
+    /// the texture, drawn with `BlendMode::Multiply`.
+    LightTint { mode: LightingMode, lightmap_scale: f32 },
+    /// Quads applying a `PostEffect` to the previous pass in the texture,
+    /// see `PostProcessRenderer`.
+    Post {
+        effect:     &'a PostEffect,
+        /// The lookup table of a `PostEffect::ColorGrade`
+        lut:        Option<TextureHandle>,
+        /// The shader of a `PostEffect::Custom`, from `RenderBackend::create_shader`
+        shader:     Option<ShaderHandle>,
+        /// Seconds passed to the effect
+        time:       f32,
+    },
+}
+impl Pipeline<'_> {
+    pub fn kind(&self) -> PipelineKind {
+        match self {
+            Pipeline::Quads                 => PipelineKind::Quads,
+            Pipeline::Sprites { .. }        => PipelineKind::Sprites,
+            Pipeline::Panels { .. }         => PipelineKind::Panels,
+            Pipeline::Text { .. }           => PipelineKind::Text,
+            Pipeline::Lines                 => PipelineKind::Lines,
+            Pipeline::Lights { .. }         => PipelineKind::Lights,
+            Pipeline::ShadowVolumes         => PipelineKind::ShadowVolumes,
+            Pipeline::ShadowMask { .. }     => PipelineKind::ShadowMask,
+            Pipeline::LightTint { .. }      => PipelineKind::LightTint,
+            Pipeline::Post { .. }           => PipelineKind::Post,
+        }
+    }
+}
+
+/// A `Pipeline` without its parameters, used to prepare pipelines with
+/// `RenderBackend::init`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PipelineKind {
+    Quads,
+    Sprites,
+    Panels,
+    Text,
+    Lines,
+    Lights,
+    ShadowVolumes,
+    ShadowMask,
+    LightTint,
+    Post,
+}
+impl PipelineKind {
+    /// The kind of vertex data the pipeline draws.
+    pub(crate) fn vertices(&self) -> VertexKind {
+        match self {
+            PipelineKind::Quads | PipelineKind::ShadowMask | PipelineKind::LightTint
+                | PipelineKind::Post    => VertexKind::Quads,
+            PipelineKind::Sprites       => VertexKind::Sprites,
+            PipelineKind::Panels        => VertexKind::Panels,
+            PipelineKind::Text          => VertexKind::Chars,
+            PipelineKind::Lines         => VertexKind::Lines,
+            PipelineKind::Lights        => VertexKind::Lights,
+            PipelineKind::ShadowVolumes => VertexKind::Triangles,
+        }
+    }
+}
+
+/// How a draw uses the stencil of the bound target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StencilTest {
+    #[default]
+    Off,
+    /// Marks every drawn fragment.
+    Mark,
+    /// Only draws where the stencil is unmarked.
+    Unmarked,
+}
+
+/// A request to draw the first `count` items of a buffer with a pipeline.
+///
+/// # Example
+/// ```
+/// # use stoneng::renderer::backend::*;
+/// # let buffer = SoftwareBackend::new(1, 1).create_buffer();
+/// let call = DrawCall::new(Pipeline::Quads, buffer, 1, nalgebra_glm::Mat4::identity())
+///     .blend(BlendMode::Additive)
+///     .depth_test(false);
+/// assert!(call.texture.is_none() && !call.depth_test);
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct DrawCall<'a> {
+    pub pipeline:   Pipeline<'a>,
+    pub buffer:     BufferHandle,
+    /// The number of quads, sprites, panels, chars, lights or vertices to draw
+    pub count:      usize,
+    /// Sampled and multiplied by the vertex color. Untextured quads use the
+    /// vertex color alone.
+    pub texture:    Option<TextureHandle>,
+    /// Transforms vertex positions to clip space, e.g. `Camera::view_projection`
+    pub transform:  Mat4,
+    pub blend:      BlendMode,
+    /// Only draws fragments no farther than what is already drawn, and records
+    /// their depth.
+    pub depth_test: bool,
+    /// Records the depth of drawn fragments when depth testing. Off for
+    /// translucent draws, so that what is drawn behind them later still shows.
+    pub depth_write: bool,
+    pub stencil:    StencilTest,
+}
+impl<'a> DrawCall<'a> {
+    /// A call with alpha blending and depth testing, untextured and ignoring
+    /// the stencil.
+    pub fn new(pipeline: Pipeline<'a>, buffer: BufferHandle, count: usize, transform: Mat4) -> Self {
+        Self {
+            pipeline, buffer, count, transform,
+            texture: None,
+            blend: BlendMode::Alpha,
+            depth_test: true,
+            depth_write: true,
+            stencil: StencilTest::Off,
+        }
+    }
+
+    pub fn texture(mut self, texture: TextureHandle) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    pub fn blend(mut self, blend: BlendMode) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    pub fn depth_test(mut self, depth_test: bool) -> Self {
+        self.depth_test = depth_test;
+        self
+    }
+
+    pub fn depth_write(mut self, depth_write: bool) -> Self {
+        self.depth_write = depth_write;
+        self
+    }
+
+    pub fn stencil(mut self, stencil: StencilTest) -> Self {
+        self.stencil = stencil;
+        self
+    }
+}
+
+/// An RGBA image, stored from the top row down. Read back from backends and
+/// decoded by `texture::decode`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width:  u32,
+    pub height: u32,
+    pub data:   Vec<u8>,
+}
+impl Image {
+    /// The color at a pixel, from the top-left corner.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<(u8, u8, u8, u8)> {
+        if x >= self.width || y >= self.height { return None; }
+        let i = ((y * self.width + x) * 4) as usize;
+        Some((self.data[i], self.data[i + 1], self.data[i + 2], self.data[i + 3]))
+    }
+}
+
+/// Operations a renderer needs from the graphics API.
+///
+/// Targets are bound with `bind_target`, after which `clear` and `draw` act on
+/// them. Binding `None` targets the screen: for the OpenGlBackend whatever
+/// framebuffer was bound before the backend bound a target, and for the
+/// SoftwareBackend an image of the size it was created with. Every target,
+/// including the screen, has a depth and a stencil buffer.
+pub trait RenderBackend {
+    /// A short name for logs, e.g. "opengl".
+    fn name(&self) -> &'static str;
+
+    /// Prepares the backend to draw with `pipelines`, e.g. by compiling their
+    /// shaders. May be called again to prepare more pipelines, and draws with
+    /// pipelines that were never prepared are skipped.
+    fn init(&mut self, pipelines: &[PipelineKind]) -> Result<(), EngineError>;
+
+    /// Sets the size of the screen in pixels, e.g. after the window is resized.
+    fn resize_screen(&mut self, width: u32, height: u32);
+
+    /// Uploads an RGBA texture, given from its first row.
+    fn create_texture(&mut self, desc: TextureDesc, rgba: &[u8])
+        -> Result<TextureHandle, EngineError>;
+    /// Replaces the contents of a whole texture.
+    fn update_texture(&mut self, texture: TextureHandle, rgba: &[u8]);
+    fn destroy_texture(&mut self, texture: TextureHandle);
+    /// Changes how a texture, including a target's, is filtered.
+    fn set_texture_filter(&mut self, texture: TextureHandle, filter: Filter);
+    /// Names a texture in driver messages and debuggers, where supported.
+    fn label_texture(&mut self, _texture: TextureHandle, _label: &str) {}
+
+    fn create_buffer(&mut self) -> BufferHandle;
+    /// Replaces the data in a buffer, growing it as needed.
+    fn stream(&mut self, buffer: BufferHandle, data: VertexData);
+    /// Replaces the data in a buffer with quads.
+    fn stream_quads(&mut self, buffer: BufferHandle, quads: &[Quad]) {
+        self.stream(buffer, VertexData::Quads(quads));
+    }
+    fn destroy_buffer(&mut self, buffer: BufferHandle);
+    /// Names a buffer in driver messages and debuggers, where supported.
+    fn label_buffer(&mut self, _buffer: BufferHandle, _label: &str) {}
+
+    fn create_target(&mut self, width: u32, height: u32) -> Result<TargetHandle, EngineError>;
+    fn resize_target(&mut self, target: TargetHandle, width: u32, height: u32);
+    /// The color texture of a target, to be drawn in later passes.
+    fn target_texture(&self, target: TargetHandle) -> TextureHandle;
+    fn destroy_target(&mut self, target: TargetHandle);
+    /// Names a target and its attachments in driver messages and debuggers,
+    /// where supported.
+    fn label_target(&mut self, _target: TargetHandle, _label: &str) {}
+
+    /// Compiles the fragment shader of a `PostEffect::Custom`, drawn with
+    /// `Pipeline::Post`. Fails if the source doesn't compile, or if the
+    /// backend can't run shaders.
+    fn create_shader(&mut self, frag_source: &str) -> Result<ShaderHandle, EngineError>;
+    fn destroy_shader(&mut self, shader: ShaderHandle);
+
+    /// Directs `clear` and `draw` to a target, or the screen for `None`.
+    fn bind_target(&mut self, target: Option<TargetHandle>);
+    /// The size of the bound target in pixels.
+    fn target_size(&self) -> (u32, u32);
+    /// Clears the color, depth and stencil of the bound target.
+    fn clear(&mut self, color: (f32, f32, f32, f32));
+    /// Resets the whole depth buffer of the bound target, so that later draws
+    /// end up in front of everything drawn so far.
+    fn clear_depth(&mut self);
+    /// Unmarks the whole stencil of the bound target.
+    fn clear_stencil(&mut self);
+    fn draw(&mut self, call: &DrawCall);
+
+    /// Reads back the contents of the bound target.
+    fn read_pixels(&mut self) -> Image;
+}
@@ -0,0 +1,866 @@
+use std::collections::HashMap;
+use std::mem::{size_of, offset_of};
+
+use crate::{
+    EngineError,
+    log,
+    shader::{self, Preprocessor, ShaderProgram, UniformValue, VertexAttribute},
+    renderer::QuadPath,
+    renderer::gpu::{self, GlBuffer, GlFramebuffer, GlRenderbuffer, GlTexture, GlVertexArray},
+    renderer::sprite::{RenderSprite, SPRITE_LAYOUT},
+    renderer::nine_slice::{RenderPanel, PANEL_LAYOUT, PANEL_SLICES},
+    renderer::text::{RenderChar, CHAR_LAYOUT},
+    renderer::light::{RenderLight, LightingMode, DitherMatrix, LIGHT_LAYOUT},
+    renderer::debug::{DebugVertex, DEBUG_VERTEX_LAYOUT},
+    renderer::post::{PostEffect, BUILTIN_EFFECTS},
+};
+use super::*;
+
+use glm::{Vec2, Vec3};
+use gl::types::*;
+
+/// A vertex buffer, with the vertex arrays reading it created as they are
+/// first drawn.
+#[derive(Debug, Default)]
+struct StreamBuffer {
+    vbo:        GlBuffer,
+    /// Bytes allocated for `vbo`
+    capacity:   usize,
+    kind:       VertexKind,
+    /// Items streamed by the last `stream`
+    len:        usize,
+    /// Vertex arrays by whether their attributes advance per instance
+    vaos:       HashMap<bool, GlVertexArray>,
+    label:      String,
+}
+
+/// The attributes of a QuadVertex, as read by `quad/vert.glsl`.
+const QUAD_VERTEX_LAYOUT: [VertexAttribute; 3] = [
+    VertexAttribute::new(0, 3, gl::FLOAT, offset_of!(QuadVertex, pos)),
+    VertexAttribute::new(1, 2, gl::FLOAT, offset_of!(QuadVertex, uv)),
+    VertexAttribute::new(2, 4, gl::FLOAT, offset_of!(QuadVertex, color)),
+];
+
+/// The position of a shadow volume vertex, as read by `shadow/vert.glsl`.
+const TRIANGLE_LAYOUT: [VertexAttribute; 1] = [
+    VertexAttribute::new(0, 2, gl::FLOAT, 0),
+];
+
+impl VertexKind {
+    fn layout(&self) -> (&'static [VertexAttribute], usize) {
+        match self {
+            VertexKind::Quads     => (&QUAD_VERTEX_LAYOUT, size_of::<QuadVertex>()),
+            VertexKind::Sprites   => (&SPRITE_LAYOUT, size_of::<RenderSprite>()),
+            VertexKind::Panels    => (&PANEL_LAYOUT, size_of::<RenderPanel>()),
+            VertexKind::Chars     => (&CHAR_LAYOUT, size_of::<RenderChar>()),
+            VertexKind::Lights    => (&LIGHT_LAYOUT, size_of::<RenderLight>()),
+            VertexKind::Triangles => (&TRIANGLE_LAYOUT, size_of::<(f32, f32)>()),
+            VertexKind::Lines     => (&DEBUG_VERTEX_LAYOUT, size_of::<DebugVertex>()),
+        }
+    }
+
+    /// The quads each item is grown into on the instanced path, one per instance.
+    fn instances(&self) -> GLuint {
+        match self {
+            VertexKind::Panels => PANEL_SLICES,
+            _ => 1,
+        }
+    }
+}
+
+impl PipelineKind {
+    /// Whether the pipeline grows each item into quads, by `QuadPath`.
+    fn grows_quads(&self) -> bool {
+        matches!(self, PipelineKind::Sprites | PipelineKind::Panels
+                     | PipelineKind::Text | PipelineKind::Lights)
+    }
+
+    fn build(&self, path: QuadPath) -> Result<ShaderProgram, EngineError> {
+        let geom = |geom| match path {
+            QuadPath::Geometry => Some(geom),
+            QuadPath::Instanced => None,
+        };
+        let plain = Preprocessor::new();
+        match self {
+            PipelineKind::Quads         => plain.program("quad/vert.glsl", "quad/frag.glsl", None),
+            PipelineKind::Sprites       => path.preprocessor()
+                .program("sprite/vert.glsl", "sprite/frag.glsl", geom("sprite/geom.glsl")),
+            PipelineKind::Panels        => path.preprocessor()
+                .program("nine_slice/vert.glsl", "nine_slice/frag.glsl", geom("nine_slice/geom.glsl")),
+            PipelineKind::Text          => path.preprocessor()
+                .program("text/vert.glsl", "text/frag.glsl", geom("text/geom.glsl")),
+            PipelineKind::Lines         => plain.program("debug/vert.glsl", "debug/frag.glsl", None),
+            PipelineKind::Lights        => path.preprocessor()
+                .program("lightmap/vert.glsl", "lightmap/frag.glsl", geom("lightmap/geom.glsl")),
+            PipelineKind::ShadowVolumes => plain.program("shadow/vert.glsl", "shadow/frag.glsl", None),
+            PipelineKind::ShadowMask    => plain.program("shadowmask/vert.glsl", "shadowmask/frag.glsl", None),
+            PipelineKind::LightTint     => plain.program("shadowmask/vert.glsl", "shadowmask/tint.glsl", None),
+            // Each effect has its own program, see `post_program`
+            PipelineKind::Post          => Ok(ShaderProgram::default()),
+        }
+    }
+}
+
+/// Compiles a post effect's fragment shader with the full-screen vertex shader.
+fn post_program(name: &str, frag_source: &str) -> Result<ShaderProgram, EngineError> {
+    let vert_source = shader::builtin_source("post/vert.glsl").unwrap_or_default();
+    Preprocessor::new().program_from_sources(
+        ("post/vert.glsl", vert_source),
+        (name, frag_source),
+        None,
+    )
+}
+
+/// Returns the framebuffer that is currently bound for drawing.
+fn current_framebuffer() -> GLuint {
+    let mut fbo: GLint = 0;
+    unsafe { gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut fbo); }
+    fbo as GLuint
+}
+
+/// An off-screen framebuffer with a color texture and a depth and stencil
+/// buffer, freed when dropped.
+///
+/// The color attachment uses `NEAREST` filtering so that it can be scaled
+/// without blurring the pixel art.
+#[derive(Debug, Default)]
+struct Framebuffer {
+    fbo:        GlFramebuffer,
+    tex:        GlTexture,
+    depth:      GlRenderbuffer,
+    width:      u32,
+    height:     u32,
+}
+
+impl Framebuffer {
+    fn new(width: u32, height: u32) -> Result<Self, EngineError> {
+        let mut target = Self {
+            fbo: GlFramebuffer::new(),
+            tex: GlTexture::new(),
+            depth: GlRenderbuffer::new(),
+            width: 0,
+            height: 0,
+        };
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, target.tex.id());
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        target.resize(width, height);
+
+        let status = unsafe {
+            let previous = current_framebuffer();
+            gl::BindFramebuffer(gl::FRAMEBUFFER, target.fbo.id());
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous);
+            status
+        };
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            return Err(EngineError::RendererInit(
+                format!("Framebuffer incomplete (status {:#x})", status)
+            ));
+        }
+        Ok(target)
+    }
+
+    /// Reallocates the attachments if the size has changed. The bound
+    /// framebuffer is left as it was.
+    fn resize(&mut self, width: u32, height: u32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if (width, height) == (self.width, self.height) { return; }
+        self.width = width;
+        self.height = height;
+
+        unsafe {
+            let previous = current_framebuffer();
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo.id());
+
+            // Color
+            gl::BindTexture(gl::TEXTURE_2D, self.tex.id());
+            gl::TexImage2D(
+                gl::TEXTURE_2D, 0, gl::RGBA as i32,
+                width as i32, height as i32, 0,
+                gl::RGBA, gl::UNSIGNED_BYTE,
+                std::ptr::null()
+            );
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0,
+                                     gl::TEXTURE_2D, self.tex.id(), 0);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            self.tex.set_bytes(gpu::image_bytes(width as i32, height as i32, 4));
+
+            // Depth and stencil
+            gl::BindRenderbuffer(gl::RENDERBUFFER, self.depth.id());
+            gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH24_STENCIL8,
+                                    width as i32, height as i32);
+            gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_STENCIL_ATTACHMENT,
+                                        gl::RENDERBUFFER, self.depth.id());
+            gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+            self.depth.set_bytes(gpu::image_bytes(width as i32, height as i32, 4));
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, previous);
+        }
+    }
+
+    fn label(&self, name: &str) {
+        self.fbo.label(name);
+        self.tex.label(&format!("{} color", name));
+        self.depth.label(&format!("{} depth", name));
+    }
+
+    /// Binds the framebuffer for drawing and sets the viewport to cover it.
+    fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo.id());
+            gl::Viewport(0, 0, self.width as i32, self.height as i32);
+        }
+    }
+
+    fn texture(&self) -> GLuint { self.tex.id() }
+    fn size(&self) -> (u32, u32) { (self.width, self.height) }
+}
+
+/// A RenderBackend drawing with OpenGL.
+///
+/// Handles are the names of the underlying OpenGL objects, which are owned by
+/// the backend and freed when it is dropped. As the backend
+/// relies on OpenGL to operate, it must only be initialized _after_ the OpenGL
+/// bindings have been loaded and only used on the main thread.
+#[derive(Debug, Default)]
+pub struct OpenGlBackend {
+    path:       QuadPath,
+    programs:   HashMap<PipelineKind, ShaderProgram>,
+    /// The programs of the built-in post effects, by their fragment shader
+    effects:    HashMap<&'static str, ShaderProgram>,
+    /// Programs created by `create_shader`
+    shaders:    HashMap<u32, ShaderProgram>,
+    /// Indices shared by all quad buffers, six per quad
+    ebo:        GlBuffer,
+    index_capacity: usize,
+
+    /// Sizes of every texture that can be drawn with, including those of targets
+    textures:   HashMap<u32, (u32, u32)>,
+    /// Textures created by `create_texture`
+    owned:      HashMap<u32, GlTexture>,
+    buffers:    HashMap<u32, StreamBuffer>,
+    targets:    HashMap<u32, Framebuffer>,
+    bound:      Option<TargetHandle>,
+    /// The framebuffer bound when the backend first bound a target
+    screen_fbo: GLuint,
+    screen_size:    (u32, u32),
+}
+
+impl OpenGlBackend {
+    /// Creates a backend able to draw quads. The screen is the framebuffer
+    /// bound when drawing starts, of `screen_size` pixels.
+    ///
+    /// This can _only_ be called after the OpenGL bindings have been loaded.
+    pub fn new(screen_size: (u32, u32)) -> Result<Self, EngineError> {
+        let mut backend = Self { screen_size, ..Self::default() };
+        backend.init(&[PipelineKind::Quads])?;
+        Ok(backend)
+    }
+
+    /// How sprites, glyphs and lights are grown into quads, picked by `init`
+    /// for the context.
+    pub fn path(&self) -> QuadPath { self.path }
+
+    /// Grows the shared index buffer to hold at least `quads` quads.
+    fn reserve_indices(&mut self, quads: usize) {
+        if quads <= self.index_capacity { return; }
+        let capacity = quads.next_power_of_two();
+        let indices: Vec<GLuint> = (0..capacity as GLuint)
+            .flat_map(|q| [0, 1, 2, 2, 1, 3].map(|i| q * 4 + i))
+            .collect();
+        unsafe {
+            // Bound without a vertex array, so no buffer's binding is replaced
+            gl::BindVertexArray(0);
+            self.ebo.upload(gl::ELEMENT_ARRAY_BUFFER, &indices, gl::STATIC_DRAW);
+            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
+        }
+        self.index_capacity = capacity;
+    }
+
+    /// The vertex array reading a buffer, creating it on first use.
+    fn vertex_array(&mut self, buffer: BufferHandle, instanced: bool) -> Option<GLuint> {
+        let ebo = self.ebo.id();
+        let buf = self.buffers.get_mut(&buffer.0)?;
+        if instanced && !gl::VertexAttribDivisor::is_loaded() { return None; }
+        let (kind, vbo) = (buf.kind, buf.vbo.id());
+        let vao = buf.vaos.entry(instanced).or_insert_with(|| {
+            let vao = GlVertexArray::new();
+            let (layout, stride) = kind.layout();
+            unsafe {
+                gl::BindVertexArray(vao.id());
+                gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+                if kind == VertexKind::Quads {
+                    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ebo);
+                }
+                let divisor = if instanced { kind.instances() } else { 0 };
+                shader::set_vertex_layout(layout, stride, divisor);
+
+                gl::BindVertexArray(0);
+                gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
+            }
+            if !buf.label.is_empty() { vao.label(&buf.label); }
+            vao
+        });
+        Some(vao.id())
+    }
+
+    fn set_blend(blend: BlendMode) {
+        unsafe {
+            gl::Enable(gl::BLEND);
+            match blend {
+                BlendMode::Opaque => gl::Disable(gl::BLEND),
+                BlendMode::Alpha => gl::BlendFuncSeparate(
+                    gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+                BlendMode::Premultiplied => gl::BlendFuncSeparate(
+                    gl::ONE, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE_MINUS_SRC_ALPHA),
+                BlendMode::Additive => gl::BlendFuncSeparate(
+                    gl::SRC_ALPHA, gl::ONE, gl::ZERO, gl::ONE),
+                BlendMode::Multiply => gl::BlendFuncSeparate(
+                    gl::DST_COLOR, gl::ZERO, gl::ZERO, gl::ONE),
+            }
+        }
+    }
+
+    fn set_stencil(stencil: StencilTest) {
+        unsafe {
+            match stencil {
+                StencilTest::Off => gl::Disable(gl::STENCIL_TEST),
+                StencilTest::Mark => {
+                    gl::Enable(gl::STENCIL_TEST);
+                    gl::StencilFunc(gl::ALWAYS, 1, 0xFF);
+                    gl::StencilOp(gl::KEEP, gl::KEEP, gl::REPLACE);
+                },
+                StencilTest::Unmarked => {
+                    gl::Enable(gl::STENCIL_TEST);
+                    gl::StencilFunc(gl::EQUAL, 0, 0xFF);
+                    gl::StencilOp(gl::KEEP, gl::KEEP, gl::KEEP);
+                },
+            }
+        }
+    }
+
+    /// Sets the uniforms of `include/lighting.glsl` on a bound program.
+    fn set_lighting_uniforms(program: &ShaderProgram, mode: LightingMode, lightmap_scale: f32) {
+        let (dither_size, bands) = match mode {
+            LightingMode::Dithered(matrix) => (matrix.size(), 1),
+            LightingMode::Smooth => (DitherMatrix::default().size(), 1),
+            LightingMode::Posterized { bands } => (DitherMatrix::default().size(), bands.max(1) as i32),
+        };
+        program.set_sampler("lightmap", 0);
+        program.set_float("lightmap_scale", lightmap_scale);
+        program.set_int("lighting_mode", mode.shader_id());
+        program.set_int("dither_size", dither_size);
+        program.set_int("bands", bands);
+    }
+
+    /// Sets the uniforms of a pipeline on its bound program, returning the
+    /// texture units bound for a material to be unbound after drawing.
+    unsafe fn set_uniforms(&self, program: &ShaderProgram, call: &DrawCall) -> u32 {
+        let (width, height) = self.target_size();
+        match call.pipeline {
+            Pipeline::Quads => {
+                program.set_mat4("transform", &call.transform);
+                program.set_sampler("tex", 0);
+                program.set_int("use_texture", call.texture.is_some() as i32);
+            },
+            Pipeline::Sprites { sheet, palettes, material, time } => {
+                program.set_mat4("view_projection", &call.transform);
+                program.set_int("sheet_width", sheet.width as i32);
+                program.set_int("sheet_tile_w", sheet.tile_width as i32);
+                program.set_int("sheet_border", sheet.border as i32);
+                if program.has_uniform("premultiplied") {
+                    program.set_int("premultiplied", (call.blend == BlendMode::Premultiplied) as i32);
+                }
+                if let Some(palettes) = palettes {
+                    gl::ActiveTexture(gl::TEXTURE1);
+                    gl::BindTexture(gl::TEXTURE_2D, palettes.0);
+                    gl::ActiveTexture(gl::TEXTURE0);
+                }
+                // Offered to materials, which may not use them
+                for (name, unit) in [("spritesheet_tex", 0), ("palette_tex", 1)] {
+                    if program.has_uniform(name) { program.set_sampler(name, unit); }
+                }
+                if program.has_uniform("time") { program.set_float("time", time); }
+
+                // Material values
+                if let Some(mat) = material {
+                    for (name, value) in mat.uniforms.iter() {
+                        program.set_uniform(name, value);
+                    }
+                    for (unit, (name, tex)) in mat.textures.iter().enumerate() {
+                        let unit = unit as u32 + 2;
+                        // Destroyed or foreign handles would bind whatever
+                        // texture now has their name, so the unit is left empty
+                        let id = if self.textures.contains_key(&tex.0) {
+                            tex.0
+                        } else {
+                            log::warn("opengl", format!(
+                                "Material texture '{}' is not a texture of this backend", name));
+                            0
+                        };
+                        gl::ActiveTexture(gl::TEXTURE0 + unit);
+                        gl::BindTexture(gl::TEXTURE_2D, id);
+                        program.set_sampler(name, unit);
+                    }
+                    gl::ActiveTexture(gl::TEXTURE0);
+                    return mat.textures.len() as u32;
+                }
+            },
+            Pipeline::Panels { sheet } => {
+                program.set_mat4("view_projection", &call.transform);
+                program.set_sampler("spritesheet_tex", 0);
+                program.set_int("sheet_width", sheet.width as i32);
+                program.set_int("sheet_tile_w", sheet.tile_width as i32);
+                program.set_int("sheet_border", sheet.border as i32);
+                program.set_int("premultiplied", (call.blend == BlendMode::Premultiplied) as i32);
+            },
+            Pipeline::Lines => {
+                program.set_mat4("view_projection", &call.transform);
+            },
+            Pipeline::Text { glyph_size, atlas_width } => {
+                program.set_mat4("view_projection", &call.transform);
+                program.set_sampler("tex_bitmap_font", 0);
+                program.set_float("glyph_size", glyph_size);
+                program.set_float("atlas_width", atlas_width);
+            },
+            Pipeline::Lights { gain } => {
+                program.set_mat4("view_projection", &call.transform);
+                program.set_vec2("viewport_size", Vec2::new(width as f32, height as f32));
+                program.set_float("gain", gain);
+            },
+            Pipeline::ShadowVolumes => {
+                program.set_mat4("view_projection", &call.transform);
+            },
+            Pipeline::ShadowMask { mode, lightmap_scale, shadow_color, darkness } => {
+                program.set_mat4("transform", &call.transform);
+                Self::set_lighting_uniforms(program, mode, lightmap_scale);
+                let (r, g, b) = shadow_color;
+                program.set_vec3("shadow_color", Vec3::new(r, g, b));
+                program.set_float("darkness", darkness.clamp(0.0, 1.0));
+            },
+            Pipeline::LightTint { mode, lightmap_scale } => {
+                program.set_mat4("transform", &call.transform);
+                Self::set_lighting_uniforms(program, mode, lightmap_scale);
+            },
+            Pipeline::Post { effect, lut, time, .. } => {
+                program.set_mat4("transform", &call.transform);
+                // Offered to every effect, which may not use them all
+                if program.has_uniform("source") { program.set_sampler("source", 0); }
+                if program.has_uniform("resolution") {
+                    program.set_vec2("resolution", Vec2::new(width as f32, height as f32));
+                }
+                if program.has_uniform("time") { program.set_float("time", time); }
+                Self::set_effect_uniforms(program, effect, lut);
+            },
+        }
+        0
+    }
+
+    /// Sets the parameters of a post effect on its bound program.
+    unsafe fn set_effect_uniforms(program: &ShaderProgram, effect: &PostEffect,
+                                  lut: Option<TextureHandle>) {
+        match effect {
+            PostEffect::Vignette { strength, radius, softness, color } => {
+                program.set_float("strength", *strength);
+                program.set_float("radius", *radius);
+                program.set_float("softness", *softness);
+                program.set_uniform("vignette_color", &UniformValue::Vec3(color.0, color.1, color.2));
+            },
+            PostEffect::Bloom { threshold, intensity, radius, emissive } => {
+                if let Some(emissive) = emissive {
+                    gl::ActiveTexture(gl::TEXTURE1);
+                    gl::BindTexture(gl::TEXTURE_2D, emissive.0);
+                    gl::ActiveTexture(gl::TEXTURE0);
+                }
+                program.set_sampler("emissive", 1);
+                program.set_int("use_emissive", emissive.is_some() as i32);
+                program.set_float("threshold", *threshold);
+                program.set_float("intensity", *intensity);
+                program.set_float("radius", *radius);
+            },
+            PostEffect::ColorGrade { lut: table, strength } => {
+                if let Some(lut) = lut {
+                    gl::ActiveTexture(gl::TEXTURE1);
+                    gl::BindTexture(gl::TEXTURE_2D, lut.0);
+                    gl::ActiveTexture(gl::TEXTURE0);
+                }
+                program.set_sampler("lut", 1);
+                program.set_float("lut_size", table.size as f32);
+                program.set_float("strength", *strength);
+            },
+            PostEffect::ChromaticAberration { offset } => {
+                program.set_float("offset", *offset);
+            },
+            PostEffect::Scanlines { intensity, curvature, flicker } => {
+                program.set_float("intensity", *intensity);
+                program.set_float("curvature", *curvature);
+                program.set_float("flicker", *flicker);
+            },
+            PostEffect::Custom { params, .. } => {
+                for (name, value) in params {
+                    program.set_uniform(name, value);
+                }
+            },
+        }
+    }
+}
+
+impl RenderBackend for OpenGlBackend {
+    fn name(&self) -> &'static str { "opengl" }
+
+    fn init(&mut self, pipelines: &[PipelineKind]) -> Result<(), EngineError> {
+        // Prevent running this function too early.
+        if !gl::Viewport::is_loaded() {
+            let msg = format!("{}\n{}",
+                "OpenGlBackend::init called before gl bindings were loaded.",
+                "It should only be initialized once the engine has started."
+            );
+            return Err(EngineError::RendererInit(msg));
+        }
+        if self.programs.is_empty() {
+            self.path = QuadPath::detect();
+            self.ebo = GlBuffer::new();
+            self.ebo.label("quad indices");
+        }
+
+        for kind in pipelines {
+            if self.programs.contains_key(kind) { continue; }
+            if *kind == PipelineKind::Post {
+                for path in BUILTIN_EFFECTS {
+                    let source = shader::builtin_source(path).unwrap_or_default();
+                    self.effects.insert(path, post_program(path, source)?);
+                }
+            }
+            let program = kind.build(self.path)?;
+            if cfg!(debug_assertions) {
+                let (layout, stride) = kind.vertices().layout();
+                if let Err(err) = program.check_layout(layout, stride) {
+                    log::warn("opengl", err);
+                }
+            }
+            self.programs.insert(*kind, program);
+        }
+        Ok(())
+    }
+
+    fn resize_screen(&mut self, width: u32, height: u32) {
+        self.screen_size = (width, height);
+    }
+
+    fn create_texture(&mut self, desc: TextureDesc, rgba: &[u8])
+            -> Result<TextureHandle, EngineError> {
+        let expected = (desc.width * desc.height * 4) as usize;
+        if rgba.len() != expected {
+            return Err(EngineError::RendererInit(
+                format!("Texture data is {} bytes, expected {}", rgba.len(), expected)
+            ));
+        }
+        let options = desc.options;
+        let internal_format = if options.srgb { gl::SRGB8_ALPHA8 } else { gl::RGBA8 };
+
+        let tex = GlTexture::new();
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, tex.id());
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, options.min_filter_gl());
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, options.mag_filter_gl());
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, options.wrap_s.to_gl());
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, options.wrap_t.to_gl());
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D, 0, internal_format as GLint,
+                desc.width as i32, desc.height as i32, 0,
+                gl::RGBA, gl::UNSIGNED_BYTE,
+                rgba.as_ptr() as *const GLvoid
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            if options.mipmaps {
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+            }
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        // A full mip chain adds a third to the base level
+        let bytes = gpu::image_bytes(desc.width as i32, desc.height as i32, 4);
+        tex.set_bytes(if options.mipmaps { bytes + bytes / 3 } else { bytes });
+        let handle = TextureHandle(tex.id());
+        self.textures.insert(handle.0, (desc.width, desc.height));
+        self.owned.insert(handle.0, tex);
+        Ok(handle)
+    }
+
+    fn update_texture(&mut self, texture: TextureHandle, rgba: &[u8]) {
+        let (width, height) = match self.textures.get(&texture.0) {
+            Some(size) => *size,
+            None => return,
+        };
+        if rgba.len() != (width * height * 4) as usize { return; }
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture.0);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D, 0, 0, 0,
+                width as i32, height as i32,
+                gl::RGBA, gl::UNSIGNED_BYTE,
+                rgba.as_ptr() as *const GLvoid
+            );
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    fn destroy_texture(&mut self, texture: TextureHandle) {
+        // Dropping the texture deletes it. Targets' textures go with their target.
+        if self.owned.remove(&texture.0).is_some() {
+            self.textures.remove(&texture.0);
+        }
+    }
+
+    fn set_texture_filter(&mut self, texture: TextureHandle, filter: Filter) {
+        if !self.textures.contains_key(&texture.0) { return; }
+        let filter = match filter {
+            Filter::Nearest => gl::NEAREST,
+            Filter::Linear => gl::LINEAR,
+        } as GLint;
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture.0);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+    }
+
+    fn label_texture(&mut self, texture: TextureHandle, label: &str) {
+        if let Some(tex) = self.owned.get(&texture.0) { tex.label(label); }
+    }
+
+    fn create_buffer(&mut self) -> BufferHandle {
+        let buffer = StreamBuffer { vbo: GlBuffer::new(), ..StreamBuffer::default() };
+        let handle = BufferHandle(buffer.vbo.id());
+        self.buffers.insert(handle.0, buffer);
+        handle
+    }
+
+    fn stream(&mut self, buffer: BufferHandle, data: VertexData) {
+        if let VertexData::Quads(quads) = data { self.reserve_indices(quads.len()); }
+        let buf = match self.buffers.get_mut(&buffer.0) {
+            Some(buf) => buf,
+            None => return,
+        };
+        // Vertex arrays read the layout of one kind of data
+        if buf.kind != data.kind() {
+            buf.vaos.clear();
+            buf.kind = data.kind();
+        }
+        buf.len = data.len();
+
+        let (ptr, size) = match data {
+            VertexData::Quads(d)     => (d.as_ptr() as *const GLvoid, std::mem::size_of_val(d)),
+            VertexData::Sprites(d)   => (d.as_ptr() as *const GLvoid, std::mem::size_of_val(d)),
+            VertexData::Panels(d)    => (d.as_ptr() as *const GLvoid, std::mem::size_of_val(d)),
+            VertexData::Chars(d)     => (d.as_ptr() as *const GLvoid, std::mem::size_of_val(d)),
+            VertexData::Lights(d)    => (d.as_ptr() as *const GLvoid, std::mem::size_of_val(d)),
+            VertexData::Triangles(d) => (d.as_ptr() as *const GLvoid, std::mem::size_of_val(d)),
+            VertexData::Lines(d)     => (d.as_ptr() as *const GLvoid, std::mem::size_of_val(d)),
+        };
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, buf.vbo.id());
+            if size > buf.capacity {
+                gl::BufferData(gl::ARRAY_BUFFER, size as GLsizeiptr, ptr, gl::DYNAMIC_DRAW);
+                buf.vbo.set_bytes(size);
+                buf.capacity = size;
+            } else if size > 0 {
+                gl::BufferSubData(gl::ARRAY_BUFFER, 0, size as GLsizeiptr, ptr);
+            }
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+    }
+
+    fn destroy_buffer(&mut self, buffer: BufferHandle) {
+        self.buffers.remove(&buffer.0);
+    }
+
+    fn label_buffer(&mut self, buffer: BufferHandle, label: &str) {
+        if let Some(buf) = self.buffers.get_mut(&buffer.0) {
+            buf.vbo.label(label);
+            for vao in buf.vaos.values() { vao.label(label); }
+            buf.label = label.into();
+        }
+    }
+
+    fn create_target(&mut self, width: u32, height: u32) -> Result<TargetHandle, EngineError> {
+        let target = Framebuffer::new(width, height)?;
+        let handle = TargetHandle(target.fbo.id());
+        self.textures.insert(target.texture(), target.size());
+        self.targets.insert(handle.0, target);
+        Ok(handle)
+    }
+
+    fn resize_target(&mut self, target: TargetHandle, width: u32, height: u32) {
+        if let Some(t) = self.targets.get_mut(&target.0) {
+            t.resize(width, height);
+            self.textures.insert(t.texture(), t.size());
+        }
+    }
+
+    fn target_texture(&self, target: TargetHandle) -> TextureHandle {
+        TextureHandle(self.targets.get(&target.0).map(|t| t.texture()).unwrap_or(0))
+    }
+
+    fn destroy_target(&mut self, target: TargetHandle) {
+        if self.bound == Some(target) { self.bind_target(None); }
+        // Dropping the framebuffer deletes it and its attachments
+        if let Some(t) = self.targets.remove(&target.0) {
+            self.textures.remove(&t.texture());
+        }
+    }
+
+    fn label_target(&mut self, target: TargetHandle, label: &str) {
+        if let Some(t) = self.targets.get(&target.0) { t.label(label); }
+    }
+
+    fn create_shader(&mut self, frag_source: &str) -> Result<ShaderHandle, EngineError> {
+        let program = post_program("custom post effect", frag_source)?;
+        let handle = ShaderHandle(program.id());
+        self.shaders.insert(handle.0, program);
+        Ok(handle)
+    }
+
+    fn destroy_shader(&mut self, shader: ShaderHandle) {
+        self.shaders.remove(&shader.0);
+    }
+
+    fn bind_target(&mut self, target: Option<TargetHandle>) {
+        let target = target.filter(|t| self.targets.contains_key(&t.0));
+        if self.bound.is_none() && target.is_some() {
+            self.screen_fbo = current_framebuffer();
+        }
+        self.bound = target;
+        match self.bound.and_then(|t| self.targets.get(&t.0)) {
+            Some(t) => t.bind(),
+            None => unsafe {
+                gl::BindFramebuffer(gl::FRAMEBUFFER, self.screen_fbo);
+                gl::Viewport(0, 0, self.screen_size.0 as i32, self.screen_size.1 as i32);
+            },
+        }
+    }
+
+    fn target_size(&self) -> (u32, u32) {
+        self.bound.and_then(|t| self.targets.get(&t.0))
+            .map(|t| t.size())
+            .unwrap_or(self.screen_size)
+    }
+
+    fn clear(&mut self, color: (f32, f32, f32, f32)) {
+        unsafe {
+            gl::ClearColor(color.0, color.1, color.2, color.3);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT | gl::STENCIL_BUFFER_BIT);
+            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+        }
+    }
+
+    fn clear_depth(&mut self) {
+        unsafe { gl::Clear(gl::DEPTH_BUFFER_BIT); }
+    }
+
+    fn clear_stencil(&mut self) {
+        unsafe { gl::Clear(gl::STENCIL_BUFFER_BIT); }
+    }
+
+    fn draw(&mut self, call: &DrawCall) {
+        let kind = call.pipeline.kind();
+        let material = match call.pipeline {
+            Pipeline::Sprites { material, .. } => material,
+            _ => None,
+        };
+        let path = material.map(|mat| mat.shader.path()).unwrap_or(self.path);
+        let instanced = kind.grows_quads() && path == QuadPath::Instanced;
+
+        let count = match self.buffers.get(&call.buffer.0) {
+            Some(buf) if buf.kind == kind.vertices() => call.count.min(buf.len),
+            _ => return,
+        };
+        if count == 0 { return; }
+        let vao = match self.vertex_array(call.buffer, instanced) {
+            Some(vao) => vao,
+            None => return,
+        };
+        let program = match (material, call.pipeline) {
+            (Some(mat), _) => Some(mat.shader.shader_program()),
+            (None, Pipeline::Post { effect, shader, .. }) => match effect.builtin_shader() {
+                Some(path) => self.effects.get(path),
+                None => shader.and_then(|s| self.shaders.get(&s.0)),
+            },
+            (None, _) => self.programs.get(&kind),
+        };
+        let program = match program {
+            Some(program) => program,
+            None => return,
+        };
+
+        unsafe {
+            let (width, height) = self.target_size();
+            gl::Viewport(0, 0, width as i32, height as i32);
+            if call.depth_test { gl::Enable(gl::DEPTH_TEST); } else { gl::Disable(gl::DEPTH_TEST); }
+            gl::DepthMask(if call.depth_write { gl::TRUE } else { gl::FALSE });
+            Self::set_blend(call.blend);
+            Self::set_stencil(call.stencil);
+            if kind == PipelineKind::ShadowVolumes {
+                gl::ColorMask(gl::FALSE, gl::FALSE, gl::FALSE, gl::FALSE);
+            }
+
+            program.bind();
+            gl::BindVertexArray(vao);
+            if let Some(texture) = call.texture {
+                gl::ActiveTexture(gl::TEXTURE0);
+                gl::BindTexture(gl::TEXTURE_2D, texture.0);
+            }
+            let material_units = self.set_uniforms(program, call);
+
+            match kind {
+                PipelineKind::ShadowVolumes => gl::DrawArrays(gl::TRIANGLES, 0, count as i32),
+                PipelineKind::Lines => gl::DrawArrays(gl::LINES, 0, count as i32),
+                _ if !kind.grows_quads() => gl::DrawElements(
+                    gl::TRIANGLES, (count * 6) as i32, gl::UNSIGNED_INT, std::ptr::null()),
+                _ if instanced => gl::DrawArraysInstanced(gl::TRIANGLE_STRIP, 0, 4,
+                    (count as GLuint * kind.vertices().instances()) as i32),
+                _ => gl::DrawArrays(gl::POINTS, 0, count as i32),
+            }
+
+            // Unbind
+            for unit in (0..material_units + 2).rev() {
+                gl::ActiveTexture(gl::TEXTURE0 + unit);
+                gl::BindTexture(gl::TEXTURE_2D, 0);
+            }
+            gl::BindVertexArray(0);
+            gl::UseProgram(0);
+
+            // Restore the engine's default state
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::STENCIL_TEST);
+            gl::ColorMask(gl::TRUE, gl::TRUE, gl::TRUE, gl::TRUE);
+        }
+    }
+
+    fn read_pixels(&mut self) -> Image {
+        let (width, height) = self.target_size();
+        let mut data = vec![0u8; (width * height * 4) as usize];
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(0, 0, width as i32, height as i32, gl::RGBA, gl::UNSIGNED_BYTE,
+                           data.as_mut_ptr() as *mut GLvoid);
+        }
+        // Flip, as OpenGL reads from the bottom row up
+        let row = (width * 4) as usize;
+        let data = data.chunks_exact(row.max(1)).rev().flatten().copied().collect();
+        Image { width, height, data }
+    }
+}
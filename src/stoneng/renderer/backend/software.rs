@@ -0,0 +1,1040 @@
+use std::collections::HashMap;
+
+use crate::EngineError;
+use crate::log;
+use crate::renderer::{
+    sprite::{self, FLAG_INDEXED},
+    nine_slice,
+    texture::WrapMode,
+};
+use super::*;
+
+use glm::{Vec3, Vec4};
+
+/// A texture's texels, stored from the first uploaded row. For render targets
+/// the first row is the bottom of the image, as in OpenGL.
+#[derive(Debug, Clone)]
+struct SoftTexture {
+    width:      u32,
+    height:     u32,
+    options:    TextureOptions,
+    data:       Vec<u8>,
+}
+impl SoftTexture {
+    fn new(width: u32, height: u32, options: TextureOptions) -> Self {
+        Self { width, height, options, data: vec![0; (width * height * 4) as usize] }
+    }
+
+    /// The texel at a coordinate, clamped into the texture as by `texelFetch`.
+    fn fetch(&self, x: i32, y: i32) -> Vec4 {
+        if self.width == 0 || self.height == 0 { return Vec4::zeros(); }
+        let x = x.clamp(0, self.width as i32 - 1) as u32;
+        let y = y.clamp(0, self.height as i32 - 1) as u32;
+        let i = ((y * self.width + x) * 4) as usize;
+        Vec4::new(self.data[i] as f32, self.data[i + 1] as f32,
+                  self.data[i + 2] as f32, self.data[i + 3] as f32) / 255.0
+    }
+
+    /// The texel at a coordinate, resolved by the wrap modes.
+    fn texel(&self, x: i32, y: i32) -> Vec4 {
+        let wrap = |coord: i32, size: u32, mode: WrapMode| {
+            let size = size as i32;
+            match mode {
+                WrapMode::ClampToEdge => coord,
+                WrapMode::Repeat => coord.rem_euclid(size),
+                WrapMode::MirroredRepeat => {
+                    let coord = coord.rem_euclid(2 * size);
+                    if coord < size { coord } else { 2 * size - 1 - coord }
+                },
+            }
+        };
+        self.fetch(wrap(x, self.width, self.options.wrap_s),
+                   wrap(y, self.height, self.options.wrap_t))
+    }
+
+    /// Samples the texture with its mag filter and wrap modes.
+    fn sample(&self, u: f32, v: f32) -> Vec4 {
+        if self.width == 0 || self.height == 0 { return Vec4::zeros(); }
+        let (x, y) = (u * self.width as f32, v * self.height as f32);
+        match self.options.mag_filter {
+            Filter::Nearest => self.texel(x.floor() as i32, y.floor() as i32),
+            Filter::Linear => {
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (x0, y0) = (x.floor(), y.floor());
+                let (fx, fy) = (x - x0, y - y0);
+                let (x0, y0) = (x0 as i32, y0 as i32);
+                let top = self.texel(x0, y0).lerp(&self.texel(x0 + 1, y0), fx);
+                let bottom = self.texel(x0, y0 + 1).lerp(&self.texel(x0 + 1, y0 + 1), fx);
+                top.lerp(&bottom, fy)
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SoftTarget {
+    color:      u32,
+    depth:      Vec<f32>,
+    /// 1 where marked
+    stencil:    Vec<u8>,
+}
+
+/// The contents of a buffer.
+#[derive(Debug, Clone)]
+enum SoftBuffer {
+    Quads(Vec<Quad>),
+    Sprites(Vec<RenderSprite>),
+    Panels(Vec<RenderPanel>),
+    Chars(Vec<RenderChar>),
+    Lights(Vec<RenderLight>),
+    Triangles(Vec<(f32, f32)>),
+    Lines(Vec<DebugVertex>),
+}
+impl SoftBuffer {
+    fn kind(&self) -> VertexKind {
+        match self {
+            SoftBuffer::Quads(_)     => VertexKind::Quads,
+            SoftBuffer::Sprites(_)   => VertexKind::Sprites,
+            SoftBuffer::Panels(_)    => VertexKind::Panels,
+            SoftBuffer::Chars(_)     => VertexKind::Chars,
+            SoftBuffer::Lights(_)    => VertexKind::Lights,
+            SoftBuffer::Triangles(_) => VertexKind::Triangles,
+            SoftBuffer::Lines(_)     => VertexKind::Lines,
+        }
+    }
+}
+
+/// A vertex after transformation, in pixels of the bound target.
+#[derive(Debug, Clone, Copy)]
+struct ScreenVertex {
+    x:      f32,
+    y:      f32,
+    depth:  f32,
+    uv:     (f32, f32),
+    color:  Vec4,
+}
+
+/// The inputs of a fragment, interpolated from its triangle's vertices.
+#[derive(Debug, Clone, Copy)]
+struct Fragment {
+    /// The center of the pixel, as `gl_FragCoord`
+    coord:  (f32, f32),
+    uv:     (f32, f32),
+    color:  Vec4,
+}
+
+/// Shades a fragment as a pipeline's fragment shader would, `None` discarding it.
+type Shader<'a> = dyn Fn(&Fragment) -> Option<Vec4> + 'a;
+
+/// A RenderBackend that rasterizes on the CPU.
+///
+/// It matches the OpenGL backend closely enough for checking layouts, colors
+/// and draw order in tests, but is far too slow for real-time use. As it
+/// can't run shaders, sprites drawn with a material are skipped with an
+/// error, and `create_shader` always fails.
+///
+/// # Example
+/// ```
+/// # use stoneng::renderer::backend::*;
+/// let mut backend = SoftwareBackend::new(4, 4);
+/// let red = backend.create_texture(TextureDesc::new(1, 1), &[255, 0, 0, 255]).unwrap();
+///
+/// // A quad covering the whole screen in clip space
+/// let buffer = backend.create_buffer();
+/// let quad = Quad::rect((-1.0, -1.0), (1.0, 1.0), 0.0, (0.0, 0.0), (1.0, 1.0), (1.0, 1.0, 1.0, 1.0));
+/// backend.stream_quads(buffer, &[quad]);
+///
+/// backend.clear((0.0, 0.0, 0.0, 1.0));
+/// backend.draw(&DrawCall::new(Pipeline::Quads, buffer, 1, nalgebra_glm::Mat4::identity())
+///     .texture(red));
+/// assert_eq!(backend.read_pixels().pixel(2, 2), Some((255, 0, 0, 255)));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SoftwareBackend {
+    next_id:    u32,
+    textures:   HashMap<u32, SoftTexture>,
+    buffers:    HashMap<u32, SoftBuffer>,
+    targets:    HashMap<u32, SoftTarget>,
+    screen:     SoftTarget,
+    bound:      Option<TargetHandle>,
+}
+
+impl SoftwareBackend {
+    /// Creates a backend whose screen is an image of `width` by `height` pixels.
+    pub fn new(width: u32, height: u32) -> Self {
+        let mut backend = Self {
+            next_id: 1,
+            textures: HashMap::new(),
+            buffers: HashMap::new(),
+            targets: HashMap::new(),
+            screen: SoftTarget { color: 0, depth: Vec::new(), stencil: Vec::new() },
+            bound: None,
+        };
+        backend.screen = backend.new_target(width, height);
+        backend
+    }
+
+    fn next_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    fn new_target(&mut self, width: u32, height: u32) -> SoftTarget {
+        let color = self.next_id();
+        self.textures.insert(color, SoftTexture::new(width, height, TextureOptions::default()));
+        let pixels = (width * height) as usize;
+        SoftTarget { color, depth: vec![1.0; pixels], stencil: vec![0; pixels] }
+    }
+
+    fn bound_target(&mut self) -> &mut SoftTarget {
+        match self.bound.and_then(|t| self.targets.get_mut(&t.0)) {
+            Some(target) => target,
+            None => &mut self.screen,
+        }
+    }
+
+    fn bound_color(&self) -> u32 {
+        self.bound.and_then(|t| self.targets.get(&t.0))
+            .unwrap_or(&self.screen)
+            .color
+    }
+
+    /// Transforms a clip space position to pixels of a target `size` pixels.
+    fn to_screen(clip: Vec4, size: (u32, u32), uv: (f32, f32), color: Vec4) -> ScreenVertex {
+        let ndc = clip.xyz() / clip.w;
+        ScreenVertex {
+            x: (ndc.x * 0.5 + 0.5) * size.0 as f32,
+            y: (ndc.y * 0.5 + 0.5) * size.1 as f32,
+            depth: ndc.z * 0.5 + 0.5,
+            uv,
+            color,
+        }
+    }
+
+    /// Rasterizes the two triangles of a quad, in triangle strip order.
+    fn quad(&mut self, v: [ScreenVertex; 4], call: &DrawCall, shade: &Shader) {
+        self.triangle([v[0], v[1], v[2]], call, shade);
+        self.triangle([v[2], v[1], v[3]], call, shade);
+    }
+
+    /// Rasterizes one triangle into the bound target.
+    fn triangle(&mut self, tri: [ScreenVertex; 3], call: &DrawCall, shade: &Shader) {
+        let color_id = self.bound_color();
+        // Take the color buffer out, so the target can be borrowed while drawing
+        let mut color = match self.textures.remove(&color_id) {
+            Some(color) => color,
+            None => return,
+        };
+        let target = self.bound_target();
+        let (w, h) = (color.width as i32, color.height as i32);
+
+        // Counter-clockwise order, so that inside points have positive edge values
+        let [mut a, mut b, c] = tri;
+        // Evaluated from the same end of an edge for either direction, so that
+        // triangles sharing it agree exactly on which side a pixel is
+        let edge = |a: &ScreenVertex, b: &ScreenVertex, x: f32, y: f32| {
+            let directed = |a: &ScreenVertex, b: &ScreenVertex| {
+                (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x)
+            };
+            if (a.x, a.y) <= (b.x, b.y) { directed(a, b) } else { -directed(b, a) }
+        };
+        let mut area = edge(&a, &b, c.x, c.y);
+        if area < 0.0 {
+            std::mem::swap(&mut a, &mut b);
+            area = -area;
+        }
+        if area <= f32::EPSILON {
+            self.textures.insert(color_id, color);
+            return;
+        }
+        // Top-left rule, so that shared edges are only drawn once
+        let owns = |a: &ScreenVertex, b: &ScreenVertex| (b.y < a.y) || (b.y == a.y && b.x < a.x);
+        let (own_bc, own_ca, own_ab) = (owns(&b, &c), owns(&c, &a), owns(&a, &b));
+
+        let min_x = a.x.min(b.x).min(c.x).floor().max(0.0) as i32;
+        let max_x = (a.x.max(b.x).max(c.x).ceil() as i32).min(w - 1);
+        let min_y = a.y.min(b.y).min(c.y).floor().max(0.0) as i32;
+        let max_y = (a.y.max(b.y).max(c.y).ceil() as i32).min(h - 1);
+
+        for py in min_y..=max_y {
+            for px in min_x..=max_x {
+                let (x, y) = (px as f32 + 0.5, py as f32 + 0.5);
+                let (w0, w1, w2) = (edge(&b, &c, x, y), edge(&c, &a, x, y), edge(&a, &b, x, y));
+                let inside = |w: f32, own: bool| w > 0.0 || (w == 0.0 && own);
+                if !(inside(w0, own_bc) && inside(w1, own_ca) && inside(w2, own_ab)) { continue; }
+                let (l0, l1, l2) = (w0 / area, w1 / area, w2 / area);
+
+                let fragment = Fragment {
+                    coord: (x, y),
+                    uv: (a.uv.0 * l0 + b.uv.0 * l1 + c.uv.0 * l2,
+                         a.uv.1 * l0 + b.uv.1 * l1 + c.uv.1 * l2),
+                    color: a.color * l0 + b.color * l1 + c.color * l2,
+                };
+                let depth = a.depth * l0 + b.depth * l1 + c.depth * l2;
+                write_fragment(target, &mut color, (py * w + px) as usize, depth,
+                               &fragment, call, shade);
+            }
+        }
+
+        self.textures.insert(color_id, color);
+    }
+
+    /// Rasterizes one line into the bound target, a fragment for each pixel
+    /// stepped through along its longer axis, leaving out the last as OpenGL
+    /// does.
+    fn line(&mut self, a: ScreenVertex, b: ScreenVertex, call: &DrawCall, shade: &Shader) {
+        let color_id = self.bound_color();
+        let mut color = match self.textures.remove(&color_id) {
+            Some(color) => color,
+            None => return,
+        };
+        let target = self.bound_target();
+        let (w, h) = (color.width as i32, color.height as i32);
+
+        let (dx, dy) = (b.x - a.x, b.y - a.y);
+        let steps = dx.abs().max(dy.abs()).round() as i32;
+        for step in 0..steps {
+            let t = (step as f32 + 0.5) / steps as f32;
+            let (x, y) = (a.x + dx * t, a.y + dy * t);
+            let (px, py) = (x.floor() as i32, y.floor() as i32);
+            if px < 0 || py < 0 || px >= w || py >= h { continue; }
+
+            let fragment = Fragment {
+                coord: (px as f32 + 0.5, py as f32 + 0.5),
+                uv: (a.uv.0 + (b.uv.0 - a.uv.0) * t, a.uv.1 + (b.uv.1 - a.uv.1) * t),
+                color: a.color.lerp(&b.color, t),
+            };
+            let depth = a.depth + (b.depth - a.depth) * t;
+            write_fragment(target, &mut color, (py * w + px) as usize, depth,
+                           &fragment, call, shade);
+        }
+
+        self.textures.insert(color_id, color);
+    }
+}
+
+/// Tests, shades and blends a fragment into pixel `i` of a target, as `call`
+/// sets.
+fn write_fragment(target: &mut SoftTarget, color: &mut SoftTexture, i: usize, depth: f32,
+                  fragment: &Fragment, call: &DrawCall, shade: &Shader) {
+    if call.stencil == StencilTest::Unmarked && target.stencil[i] != 0 { return; }
+    if call.depth_test && depth > target.depth[i] { return; }
+
+    let src = match shade(fragment) {
+        Some(src) => src,
+        None => return,
+    };
+    if call.depth_test && call.depth_write { target.depth[i] = depth; }
+    if call.stencil == StencilTest::Mark { target.stencil[i] = 1; }
+    // Shadow volumes only mark the stencil
+    if call.pipeline.kind() == PipelineKind::ShadowVolumes { return; }
+
+    let di = i * 4;
+    let dst = Vec4::new(color.data[di] as f32, color.data[di + 1] as f32,
+                        color.data[di + 2] as f32, color.data[di + 3] as f32) / 255.0;
+    let out = blend(src, dst, call.blend);
+    for k in 0..4 {
+        color.data[di + k] = (out[k].clamp(0.0, 1.0) * 255.0).round() as u8;
+    }
+}
+
+fn blend(src: Vec4, dst: Vec4, mode: BlendMode) -> Vec4 {
+    match mode {
+        BlendMode::Opaque => src,
+        BlendMode::Alpha => {
+            let rgb = src.xyz() * src.w + dst.xyz() * (1.0 - src.w);
+            Vec4::new(rgb.x, rgb.y, rgb.z, src.w + dst.w * (1.0 - src.w))
+        },
+        BlendMode::Premultiplied => {
+            let rgb = src.xyz() + dst.xyz() * (1.0 - src.w);
+            Vec4::new(rgb.x, rgb.y, rgb.z, src.w + dst.w * (1.0 - src.w))
+        },
+        BlendMode::Additive => {
+            let rgb = dst.xyz() + src.xyz() * src.w;
+            Vec4::new(rgb.x, rgb.y, rgb.z, dst.w)
+        },
+        BlendMode::Multiply => {
+            let rgb = src.xyz().component_mul(&dst.xyz());
+            Vec4::new(rgb.x, rgb.y, rgb.z, dst.w)
+        },
+    }
+}
+
+/// Scales a color's rgb by an alpha, as the sprite and nine-slice shaders do
+/// for premultiplied sheets.
+fn premultiply_by(color: Vec4, alpha: f32) -> Vec4 {
+    Vec4::new(color.x * alpha, color.y * alpha, color.z * alpha, color.w)
+}
+
+/// The brightest channel of a lightmap color, as `light_level` in `include/lighting.glsl`.
+fn light_level(color: Vec3) -> f32 {
+    color.x.max(color.y).max(color.z)
+}
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Shades a fragment of a built-in post effect as its shader under
+/// `post/` would, sampling the previous pass with `source`. `None` for custom
+/// effects, which can't be run.
+fn post_effect(effect: &PostEffect, f: &Fragment, source: &dyn Fn((f32, f32)) -> Vec4,
+               lut: Option<&SoftTexture>, emissive: Option<&SoftTexture>,
+               resolution: (f32, f32), time: f32) -> Option<Vec4> {
+    let uv = f.uv;
+    let with_rgb = |rgb: Vec3, alpha: f32| Vec4::new(rgb.x, rgb.y, rgb.z, alpha);
+    match effect {
+        PostEffect::Vignette { strength, radius, softness, color } => {
+            let base = source(uv);
+            let delta = ((uv.0 - 0.5) * resolution.0 / resolution.1, uv.1 - 0.5);
+            let dist = (delta.0 * delta.0 + delta.1 * delta.1).sqrt();
+            let shade = smoothstep(*radius, radius + softness, dist) * strength;
+            let tint = Vec3::new(color.0, color.1, color.2);
+            Some(with_rgb(base.xyz().lerp(&tint, shade), base.w))
+        },
+        PostEffect::Bloom { threshold, intensity, radius, .. } => {
+            let luminance = |c: Vec3| c.dot(&Vec3::new(0.2126, 0.7152, 0.0722));
+            let bright = |uv: (f32, f32)| {
+                let c = source(uv).xyz();
+                let glow = smoothstep(*threshold, 1.0, luminance(c));
+                let mask = emissive.map(|e| e.sample(uv.0, uv.1).w).unwrap_or(0.0);
+                c * glow.max(mask)
+            };
+            // Golden angle spiral, as bloom.glsl
+            const SAMPLES: usize = 24;
+            const GOLDEN_ANGLE: f32 = 2.399_963_2;
+            let (mut glow, mut total) = (Vec3::zeros(), 0.0);
+            for i in 0..SAMPLES {
+                let r = ((i as f32 + 0.5) / SAMPLES as f32).sqrt();
+                let theta = i as f32 * GOLDEN_ANGLE;
+                let offset = (theta.cos() * r * radius / resolution.0,
+                              theta.sin() * r * radius / resolution.1);
+                let weight = 1.0 - r;
+                glow += bright((uv.0 + offset.0, uv.1 + offset.1)) * weight;
+                total += weight;
+            }
+            Some(with_rgb(source(uv).xyz() + glow / total * *intensity, 1.0))
+        },
+        PostEffect::ColorGrade { lut: table, strength } => {
+            let base = source(uv);
+            let lut = match lut {
+                Some(lut) => lut,
+                None => return Some(base),
+            };
+            // As sample_lut in color_grade.glsl
+            let color = base.xyz().map(|c| c.clamp(0.0, 1.0));
+            let size = table.size as f32;
+            let max_id = size - 1.0;
+            let blue = color.z * max_id;
+            let slice0 = blue.floor();
+            let slice1 = (slice0 + 1.0).min(max_id);
+            let texel = (1.0 / (size * size), 1.0 / size);
+            let inner = (color.x * max_id * texel.0 + texel.0 * 0.5,
+                         color.y * max_id * texel.1 + texel.1 * 0.5);
+            let graded = lut.sample(slice0 / size + inner.0, inner.1).xyz()
+                .lerp(&lut.sample(slice1 / size + inner.0, inner.1).xyz(), blue - slice0);
+            Some(with_rgb(base.xyz().lerp(&graded, *strength), base.w))
+        },
+        PostEffect::ChromaticAberration { offset } => {
+            let shift = ((uv.0 - 0.5) * 2.0 * offset / resolution.0,
+                         (uv.1 - 0.5) * 2.0 * offset / resolution.1);
+            let r = source((uv.0 + shift.0, uv.1 + shift.1)).x;
+            let g = source(uv);
+            let b = source((uv.0 - shift.0, uv.1 - shift.1)).z;
+            Some(Vec4::new(r, g.y, b, g.w))
+        },
+        PostEffect::Scanlines { intensity, curvature, flicker } => {
+            let (x, y) = (uv.0 * 2.0 - 1.0, uv.1 * 2.0 - 1.0);
+            let bend = 1.0 + curvature * (x * x + y * y);
+            let uv = (x * bend * 0.5 + 0.5, y * bend * 0.5 + 0.5);
+            if !(0.0..=1.0).contains(&uv.0) || !(0.0..=1.0).contains(&uv.1) {
+                return Some(Vec4::new(0.0, 0.0, 0.0, 1.0));
+            }
+            let color = source(uv);
+            let row = (uv.1 * resolution.1).fract();
+            let line = 1.0 - intensity * ((row - 0.5).abs() * 2.0).powi(2);
+            let roll = 1.0 - flicker * (0.5 + 0.5 * (uv.1 * 6.0 - time * 2.0).sin());
+            Some(with_rgb(color.xyz() * line * roll, color.w))
+        },
+        PostEffect::Custom { .. } => None,
+    }
+}
+
+impl RenderBackend for SoftwareBackend {
+    fn name(&self) -> &'static str { "software" }
+
+    fn init(&mut self, _pipelines: &[PipelineKind]) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn resize_screen(&mut self, width: u32, height: u32) {
+        let size = self.textures.get(&self.screen.color).map(|t| (t.width, t.height));
+        if size == Some((width, height)) { return; }
+        self.textures.remove(&self.screen.color);
+        self.screen = self.new_target(width, height);
+    }
+
+    fn create_texture(&mut self, desc: TextureDesc, rgba: &[u8])
+            -> Result<TextureHandle, EngineError> {
+        let expected = (desc.width * desc.height * 4) as usize;
+        if rgba.len() != expected {
+            return Err(EngineError::RendererInit(
+                format!("Texture data is {} bytes, expected {}", rgba.len(), expected)
+            ));
+        }
+        let id = self.next_id();
+        let mut texture = SoftTexture::new(desc.width, desc.height, desc.options);
+        texture.data.copy_from_slice(rgba);
+        self.textures.insert(id, texture);
+        Ok(TextureHandle(id))
+    }
+
+    fn update_texture(&mut self, texture: TextureHandle, rgba: &[u8]) {
+        if let Some(tex) = self.textures.get_mut(&texture.0) {
+            if tex.data.len() == rgba.len() { tex.data.copy_from_slice(rgba); }
+        }
+    }
+
+    fn destroy_texture(&mut self, texture: TextureHandle) {
+        self.textures.remove(&texture.0);
+    }
+
+    fn set_texture_filter(&mut self, texture: TextureHandle, filter: Filter) {
+        if let Some(tex) = self.textures.get_mut(&texture.0) {
+            tex.options = tex.options.filter(filter);
+        }
+    }
+
+    fn create_buffer(&mut self) -> BufferHandle {
+        let id = self.next_id();
+        self.buffers.insert(id, SoftBuffer::Quads(Vec::new()));
+        BufferHandle(id)
+    }
+
+    fn stream(&mut self, buffer: BufferHandle, data: VertexData) {
+        if let Some(buf) = self.buffers.get_mut(&buffer.0) {
+            *buf = match data {
+                VertexData::Quads(d)     => SoftBuffer::Quads(d.to_vec()),
+                VertexData::Sprites(d)   => SoftBuffer::Sprites(d.to_vec()),
+                VertexData::Panels(d)    => SoftBuffer::Panels(d.to_vec()),
+                VertexData::Chars(d)     => SoftBuffer::Chars(d.to_vec()),
+                VertexData::Lights(d)    => SoftBuffer::Lights(d.to_vec()),
+                VertexData::Triangles(d) => SoftBuffer::Triangles(d.to_vec()),
+                VertexData::Lines(d)     => SoftBuffer::Lines(d.to_vec()),
+            };
+        }
+    }
+
+    fn destroy_buffer(&mut self, buffer: BufferHandle) {
+        self.buffers.remove(&buffer.0);
+    }
+
+    fn create_target(&mut self, width: u32, height: u32) -> Result<TargetHandle, EngineError> {
+        let id = self.next_id();
+        let target = self.new_target(width.max(1), height.max(1));
+        self.targets.insert(id, target);
+        Ok(TargetHandle(id))
+    }
+
+    fn resize_target(&mut self, target: TargetHandle, width: u32, height: u32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if let Some(t) = self.targets.get_mut(&target.0) {
+            let pixels = (width * height) as usize;
+            t.depth = vec![1.0; pixels];
+            t.stencil = vec![0; pixels];
+            if let Some(tex) = self.textures.get_mut(&t.color) {
+                *tex = SoftTexture::new(width, height, tex.options);
+            }
+        }
+    }
+
+    fn target_texture(&self, target: TargetHandle) -> TextureHandle {
+        TextureHandle(self.targets.get(&target.0).map(|t| t.color).unwrap_or(0))
+    }
+
+    fn destroy_target(&mut self, target: TargetHandle) {
+        if let Some(t) = self.targets.remove(&target.0) {
+            self.textures.remove(&t.color);
+        }
+        if self.bound == Some(target) { self.bound = None; }
+    }
+
+    fn create_shader(&mut self, _frag_source: &str) -> Result<ShaderHandle, EngineError> {
+        Err(EngineError::RendererInit(
+            "The software backend can't compile custom post effect shaders".into()
+        ))
+    }
+
+    fn destroy_shader(&mut self, _shader: ShaderHandle) {}
+
+    fn bind_target(&mut self, target: Option<TargetHandle>) {
+        self.bound = target;
+    }
+
+    fn target_size(&self) -> (u32, u32) {
+        self.textures.get(&self.bound_color())
+            .map(|t| (t.width, t.height))
+            .unwrap_or((0, 0))
+    }
+
+    fn clear(&mut self, color: (f32, f32, f32, f32)) {
+        let texel = [color.0, color.1, color.2, color.3]
+            .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        let color_id = self.bound_color();
+        if let Some(tex) = self.textures.get_mut(&color_id) {
+            for px in tex.data.chunks_exact_mut(4) { px.copy_from_slice(&texel); }
+        }
+        let target = self.bound_target();
+        target.depth.fill(1.0);
+        target.stencil.fill(0);
+    }
+
+    fn clear_depth(&mut self) {
+        self.bound_target().depth.fill(1.0);
+    }
+
+    fn clear_stencil(&mut self) {
+        self.bound_target().stencil.fill(0);
+    }
+
+    fn draw(&mut self, call: &DrawCall) {
+        let buffer = match self.buffers.get(&call.buffer.0) {
+            Some(buf) if buf.kind() == call.pipeline.kind().vertices() => buf.clone(),
+            _ => return,
+        };
+        // Sampling the bound target while drawing to it is undefined, so copy it
+        let texture = call.texture.and_then(|t| self.textures.get(&t.0)).cloned();
+        let sample = |uv: (f32, f32)| texture.as_ref()
+            .map(|t| t.sample(uv.0, uv.1))
+            .unwrap_or(Vec4::repeat(1.0));
+        let size = self.target_size();
+        let transform = call.transform;
+        let vertex = |pos: (f32, f32, f32), uv, color: (f32, f32, f32, f32)| {
+            let clip = transform * Vec4::new(pos.0, pos.1, pos.2, 1.0);
+            Self::to_screen(clip, size, uv, Vec4::new(color.0, color.1, color.2, color.3))
+        };
+        let quad_vertices = |quad: &Quad| quad.verts.each_ref().map(|v| vertex(v.pos, v.uv, v.color));
+
+        match (call.pipeline, buffer) {
+            (Pipeline::Quads, SoftBuffer::Quads(quads)) => {
+                let shade = |f: &Fragment| {
+                    let color = f.color.component_mul(&sample(f.uv));
+                    (color.w >= 0.01).then_some(color)
+                };
+                for quad in quads.iter().take(call.count) {
+                    self.quad(quad_vertices(quad), call, &shade);
+                }
+            },
+            (Pipeline::Sprites { material: Some(_), .. }, SoftBuffer::Sprites(_)) => {
+                // Drawing them plainly would pass for the material's output
+                log::error("software", "Skipped drawing sprites with a material, which the software backend can't shade");
+            },
+            (Pipeline::Sprites { sheet, palettes, material: None, .. }, SoftBuffer::Sprites(sprites)) => {
+                let sprites = &sprites[..call.count.min(sprites.len())];
+                let palettes = palettes.and_then(|p| self.textures.get(&p.0)).cloned();
+                let quads = sprite::sprite_quads(sprites, sheet);
+                let premultiplied = call.blend == BlendMode::Premultiplied;
+                for (sprite, quad) in sprites.iter().zip(quads.iter()) {
+                    let indexed = sprite.sprite_flags & FLAG_INDEXED != 0;
+                    let row = sprite.reserved as i32;
+                    // As sprite/frag.glsl
+                    let shade = |f: &Fragment| {
+                        let mut tex = sample(f.uv);
+                        if indexed {
+                            let index = (tex.x * 255.0).round() as i32;
+                            let pal = palettes.as_ref().map(|p| p.fetch(index, row))
+                                .unwrap_or(Vec4::zeros());
+                            tex = Vec4::new(pal.x, pal.y, pal.z, pal.w * tex.w);
+                            // Palettes hold straight colors
+                            if premultiplied { tex = premultiply_by(tex, tex.w); }
+                        }
+                        let mut color = tex.component_mul(&f.color);
+                        if premultiplied { color = premultiply_by(color, f.color.w); }
+                        (color.w >= 0.01).then_some(color)
+                    };
+                    self.quad(quad_vertices(quad), call, &shade);
+                }
+            },
+            (Pipeline::Panels { sheet }, SoftBuffer::Panels(panels)) => {
+                let panels = &panels[..call.count.min(panels.len())];
+                // As nine_slice/frag.glsl
+                let premultiplied = call.blend == BlendMode::Premultiplied;
+                let shade = |f: &Fragment| {
+                    let mut color = sample(f.uv).component_mul(&f.color);
+                    if premultiplied { color = premultiply_by(color, f.color.w); }
+                    (color.w >= 0.01).then_some(color)
+                };
+                for quad in nine_slice::panel_quads(panels, sheet) {
+                    self.quad(quad_vertices(&quad), call, &shade);
+                }
+            },
+            (Pipeline::Lines, SoftBuffer::Lines(vertices)) => {
+                let vertices = &vertices[..call.count.min(vertices.len())];
+                let shade = |f: &Fragment| Some(f.color);
+                for line in vertices.chunks_exact(2) {
+                    let [a, b] = [line[0], line[1]].map(|v| vertex(v.pos, (0.0, 0.0), v.color));
+                    self.line(a, b, call, &shade);
+                }
+            },
+            (Pipeline::Text { glyph_size, atlas_width }, SoftBuffer::Chars(chars)) => {
+                // As text/geom.glsl and include/glyph.glsl
+                let scale = glyph_size / atlas_width;
+                let per_row = ((atlas_width / glyph_size) as i32).max(1);
+                let shade = |f: &Fragment| {
+                    let tint = f.color;
+                    let tex = sample(f.uv) + Vec4::new(tint.x, tint.y, tint.z, 0.0);
+                    let color = Vec4::new(tex.x, tex.y, tex.z, tex.w * tint.w);
+                    (color.w >= 0.01).then_some(color)
+                };
+                for c in chars.iter().take(call.count) {
+                    let half = glyph_size / 2.0 * c.size;
+                    let (x, y, z) = c.position;
+                    let glyph = c.glyph as i32 - 32;
+                    let uv_min = ((glyph % per_row) as f32 * scale, (glyph / per_row) as f32 * scale);
+                    let uv_max = (uv_min.0 + scale, uv_min.1 + scale);
+                    let quad = Quad::rect((x - half, y - half), (x + half, y + half), z,
+                                          uv_min, uv_max, c.color);
+                    self.quad(quad_vertices(&quad), call, &shade);
+                }
+            },
+            (Pipeline::Lights { gain }, SoftBuffer::Lights(lights)) => {
+                let viewport = (size.0 as f32, size.1 as f32);
+                for light in lights.iter().take(call.count) {
+                    // As include/light.glsl and lightmap/frag.glsl
+                    let origin = Vec4::new(light.pos.0, light.pos.1, 0.0, 1.0);
+                    let center_clip = transform * origin;
+                    let edge_clip = transform * (origin + Vec4::new(light.intensity, 0.0, 0.0, 0.0));
+                    let center = ((center_clip.x * 0.5 + 0.5) * viewport.0,
+                                  (center_clip.y * 0.5 + 0.5) * viewport.1);
+                    let radius = (edge_clip.x - center_clip.x) * 0.5 * viewport.0;
+                    let radius_sq = radius * radius;
+
+                    let shade = |f: &Fragment| {
+                        let delta = (f.coord.0 - center.0, f.coord.1 - center.1);
+                        let dist_sq = delta.0 * delta.0 + delta.1 * delta.1;
+                        if dist_sq >= radius_sq { return None; }
+                        let mut lum = (1.0 - dist_sq / radius_sq).max(0.0).powf(light.falloff);
+                        if light.cone.1 > -1.0 {
+                            let dist = dist_sq.sqrt();
+                            let cos_angle = if dist > 0.0 {
+                                (delta.0 * light.direction.0 + delta.1 * light.direction.1) / dist
+                            } else { 1.0 };
+                            let t = ((cos_angle - light.cone.1) / (light.cone.0 - light.cone.1))
+                                .clamp(0.0, 1.0);
+                            lum *= t * t * (3.0 - 2.0 * t);
+                        }
+                        let (r, g, b) = light.color;
+                        let rgb = Vec3::new(r, g, b) * (lum * 1.1).clamp(0.0, 1.0) * gain;
+                        Some(Vec4::new(rgb.x, rgb.y, rgb.z, 1.0))
+                    };
+                    let corners = [(-1.0, -1.0), (-1.0, 1.0), (1.0, -1.0), (1.0, 1.0)].map(|(cx, cy)| {
+                        let mut clip = transform * (origin + Vec4::new(cx, cy, 0.0, 0.0) * light.intensity);
+                        clip.z = 0.0;
+                        clip.w = 1.0;
+                        Self::to_screen(clip, size, (0.0, 0.0), Vec4::zeros())
+                    });
+                    self.quad(corners, call, &shade);
+                }
+            },
+            (Pipeline::ShadowVolumes, SoftBuffer::Triangles(vertices)) => {
+                let vertices = &vertices[..call.count.min(vertices.len())];
+                let shade = |_: &Fragment| Some(Vec4::zeros());
+                for tri in vertices.chunks_exact(3) {
+                    let tri = [tri[0], tri[1], tri[2]].map(|(x, y)| {
+                        let mut clip = transform * Vec4::new(x, y, 0.0, 1.0);
+                        clip.z = 0.0;
+                        clip.w = 1.0;
+                        Self::to_screen(clip, size, (0.0, 0.0), Vec4::zeros())
+                    });
+                    self.triangle(tri, call, &shade);
+                }
+            },
+            (Pipeline::ShadowMask { mode, lightmap_scale, shadow_color, darkness },
+             SoftBuffer::Quads(quads)) => {
+                // As shadowmask/frag.glsl
+                let (r, g, b) = shadow_color;
+                let shade = |f: &Fragment| {
+                    let light = light_level(sample(f.uv).xyz());
+                    let coverage = mode.coverage(light, f.coord, lightmap_scale);
+                    if coverage >= 1.0 { return None; }
+                    Some(Vec4::new(r, g, b, darkness.clamp(0.0, 1.0) * (1.0 - coverage)))
+                };
+                for quad in quads.iter().take(call.count) {
+                    self.quad(quad_vertices(quad), call, &shade);
+                }
+            },
+            (Pipeline::LightTint { mode, lightmap_scale }, SoftBuffer::Quads(quads)) => {
+                // As shadowmask/tint.glsl
+                let shade = |f: &Fragment| {
+                    let color = sample(f.uv).xyz();
+                    let light = light_level(color);
+                    let coverage = mode.coverage(light, f.coord, lightmap_scale);
+                    if coverage <= 0.0 { return None; }
+                    let rgb = Vec3::repeat(1.0).lerp(&(color / light), coverage);
+                    Some(Vec4::new(rgb.x, rgb.y, rgb.z, 1.0))
+                };
+                for quad in quads.iter().take(call.count) {
+                    self.quad(quad_vertices(quad), call, &shade);
+                }
+            },
+            (Pipeline::Post { effect, lut, time, .. }, SoftBuffer::Quads(quads)) => {
+                // Custom effects need a shader, which `create_shader` never gives
+                if matches!(effect, PostEffect::Custom { .. }) { return; }
+                let lut = lut.and_then(|l| self.textures.get(&l.0)).cloned();
+                let emissive = match effect {
+                    PostEffect::Bloom { emissive: Some(e), .. } => self.textures.get(&e.0).cloned(),
+                    _ => None,
+                };
+                let resolution = (size.0 as f32, size.1 as f32);
+                let shade = |f: &Fragment| post_effect(effect, f, &sample, lut.as_ref(),
+                                                       emissive.as_ref(), resolution, time);
+                for quad in quads.iter().take(call.count) {
+                    self.quad(quad_vertices(quad), call, &shade);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    fn read_pixels(&mut self) -> Image {
+        let tex = match self.textures.get(&self.bound_color()) {
+            Some(tex) => tex,
+            None => return Image { width: 0, height: 0, data: Vec::new() },
+        };
+        // Flip, as images are stored from the top row down
+        let row = (tex.width * 4) as usize;
+        let data = tex.data.chunks_exact(row.max(1)).rev().flatten().copied().collect();
+        Image { width: tex.width, height: tex.height, data }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::{
+        Camera,
+        light::{LightRenderer, LightingMode},
+        shadow::RenderOccluder,
+    };
+
+    const RED:      (u8, u8, u8, u8) = (255, 0, 0, 255);
+    const GREEN:    (u8, u8, u8, u8) = (0, 255, 0, 255);
+    const BLUE:     (u8, u8, u8, u8) = (0, 0, 255, 255);
+    const WHITE:    (u8, u8, u8, u8) = (255, 255, 255, 255);
+    const CLEAR:    (u8, u8, u8, u8) = (0, 0, 0, 255);
+
+    /// Maps world units to the pixels of a `size` square screen, with the
+    /// origin at the bottom-left.
+    fn pixels(size: f32) -> glm::Mat4 {
+        glm::ortho(0.0, size, 0.0, size, -1.0, 1.0)
+    }
+
+    /// A quad in clip space covering the whole target.
+    fn fullscreen(backend: &mut SoftwareBackend) -> BufferHandle {
+        let buffer = backend.create_buffer();
+        let quad = Quad::rect((-1.0, -1.0), (1.0, 1.0), 0.0, (0.0, 0.0), (1.0, 1.0),
+                              (1.0, 1.0, 1.0, 1.0));
+        backend.stream_quads(buffer, &[quad]);
+        buffer
+    }
+
+    #[test]
+    fn glyphs_are_drawn_from_their_atlas_cell() {
+        let mut backend = SoftwareBackend::new(8, 8);
+        // A 4x4 atlas of 2px glyphs: ' ' red and '!' green, then an empty row
+        let mut atlas = Vec::new();
+        for y in 0..4 {
+            for x in 0..4 {
+                let texel = match (x < 2, y < 2) {
+                    (true, true)  => [255, 0, 0, 255],
+                    (false, true) => [0, 255, 0, 255],
+                    _ => [0, 0, 0, 0],
+                };
+                atlas.extend_from_slice(&texel);
+            }
+        }
+        let atlas = backend.create_texture(TextureDesc::new(4, 4), &atlas).unwrap();
+
+        // Doubled to 4px, centered in opposite corners
+        let chars = [
+            RenderChar::new((2.0, 2.0, 0.0), 2.0, (0.0, 0.0, 0.0, 1.0), ' '),
+            RenderChar::new((6.0, 6.0, 0.0), 2.0, (0.0, 0.0, 0.0, 1.0), '!'),
+        ];
+        let buffer = backend.create_buffer();
+        backend.stream(buffer, VertexData::Chars(&chars));
+        backend.clear((0.0, 0.0, 0.0, 1.0));
+        let text = Pipeline::Text { glyph_size: 2.0, atlas_width: 4.0 };
+        backend.draw(&DrawCall::new(text, buffer, chars.len(), pixels(8.0)).texture(atlas));
+
+        let image = backend.read_pixels();
+        assert_eq!(image.pixel(0, 7), Some(RED));
+        assert_eq!(image.pixel(3, 4), Some(RED));
+        assert_eq!(image.pixel(4, 3), Some(GREEN));
+        assert_eq!(image.pixel(7, 0), Some(GREEN));
+        assert_eq!(image.pixel(1, 1), Some(CLEAR));
+        assert_eq!(image.pixel(6, 6), Some(CLEAR));
+    }
+
+    #[test]
+    fn lights_fall_off_in_their_color() {
+        let mut backend = SoftwareBackend::new(16, 16);
+        let light = RenderLight::point((8.0, 8.0), 8.0, (1.0, 0.5, 0.0));
+        let buffer = backend.create_buffer();
+        backend.stream(buffer, VertexData::Lights(&[light]));
+        backend.clear((0.0, 0.0, 0.0, 1.0));
+        backend.draw(&DrawCall::new(Pipeline::Lights { gain: 1.0 }, buffer, 1, pixels(16.0))
+            .blend(BlendMode::Additive)
+            .depth_test(false));
+
+        let image = backend.read_pixels();
+        let center = image.pixel(8, 7).unwrap();
+        let near = image.pixel(12, 7).unwrap();
+        let far = image.pixel(14, 7).unwrap();
+        assert_eq!(center, (255, 128, 0, 255));
+        assert!(center.0 > near.0 && near.0 > far.0 && far.0 > 0);
+        // Green stays at half of red, blue at none
+        for (r, g, b, _) in [near, far] {
+            assert!((g as i32 - r as i32 / 2).abs() <= 1);
+            assert_eq!(b, 0);
+        }
+        // Beyond the intensity
+        assert_eq!(image.pixel(0, 0), Some(CLEAR));
+    }
+
+    #[test]
+    fn stencil_limits_drawing_to_unmarked_pixels() {
+        let mut backend = SoftwareBackend::new(4, 4);
+        backend.clear((0.0, 0.0, 0.0, 1.0));
+
+        // Mark the left half, drawing it red
+        let left = backend.create_buffer();
+        let quad = Quad::rect((-1.0, -1.0), (0.0, 1.0), 0.0, (0.0, 0.0), (1.0, 1.0),
+                              (1.0, 0.0, 0.0, 1.0));
+        backend.stream_quads(left, &[quad]);
+        backend.draw(&DrawCall::new(Pipeline::Quads, left, 1, glm::Mat4::identity())
+            .depth_test(false)
+            .stencil(StencilTest::Mark));
+
+        let screen = fullscreen(&mut backend);
+        let blue = backend.create_texture(TextureDesc::new(1, 1), &[0, 0, 255, 255]).unwrap();
+        backend.draw(&DrawCall::new(Pipeline::Quads, screen, 1, glm::Mat4::identity())
+            .texture(blue)
+            .depth_test(false)
+            .stencil(StencilTest::Unmarked));
+
+        let image = backend.read_pixels();
+        assert_eq!(image.pixel(1, 2), Some(RED));
+        assert_eq!(image.pixel(2, 2), Some(BLUE));
+
+        // Clearing the stencil lets the whole target be drawn again
+        backend.clear_stencil();
+        backend.draw(&DrawCall::new(Pipeline::Quads, screen, 1, glm::Mat4::identity())
+            .texture(blue)
+            .depth_test(false)
+            .stencil(StencilTest::Unmarked));
+        assert_eq!(backend.read_pixels().pixel(1, 2), Some(BLUE));
+    }
+
+    #[test]
+    fn shadow_volumes_only_mark_the_stencil() {
+        let mut backend = SoftwareBackend::new(4, 4);
+        backend.clear((0.0, 0.0, 0.0, 1.0));
+
+        // The bottom-right half of the screen
+        let volumes = backend.create_buffer();
+        backend.stream(volumes, VertexData::Triangles(&[(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0)]));
+        backend.draw(&DrawCall::new(Pipeline::ShadowVolumes, volumes, 3, glm::Mat4::identity())
+            .depth_test(false)
+            .stencil(StencilTest::Mark));
+        assert_eq!(backend.read_pixels().pixel(3, 3), Some(CLEAR));
+
+        let screen = fullscreen(&mut backend);
+        backend.draw(&DrawCall::new(Pipeline::Quads, screen, 1, glm::Mat4::identity())
+            .depth_test(false)
+            .stencil(StencilTest::Unmarked));
+
+        let image = backend.read_pixels();
+        assert_eq!(image.pixel(3, 3), Some(CLEAR));
+        assert_eq!(image.pixel(0, 0), Some(WHITE));
+    }
+
+    #[test]
+    fn shadow_mask_covers_unlit_pixels_and_tint_colors_lit_ones() {
+        let mut backend = SoftwareBackend::new(4, 2);
+        // A lightmap dark on the left and lit orange on the right
+        let lightmap = backend.create_texture(TextureDesc::new(2, 1),
+                                              &[0, 0, 0, 255, 255, 128, 0, 255]).unwrap();
+        let screen = fullscreen(&mut backend);
+        backend.clear((1.0, 1.0, 1.0, 1.0));
+
+        let mode = LightingMode::Smooth;
+        let mask = Pipeline::ShadowMask {
+            mode, lightmap_scale: 1.0,
+            shadow_color: (0.0, 0.0, 1.0),
+            darkness: 1.0,
+        };
+        backend.draw(&DrawCall::new(mask, screen, 1, glm::Mat4::identity()).texture(lightmap));
+        let image = backend.read_pixels();
+        assert_eq!(image.pixel(0, 0), Some(BLUE));
+        assert_eq!(image.pixel(3, 0), Some(WHITE));
+
+        let tint = Pipeline::LightTint { mode, lightmap_scale: 1.0 };
+        backend.draw(&DrawCall::new(tint, screen, 1, glm::Mat4::identity())
+            .texture(lightmap)
+            .blend(BlendMode::Multiply));
+        let image = backend.read_pixels();
+        assert_eq!(image.pixel(0, 0), Some(BLUE));
+        assert_eq!(image.pixel(3, 0), Some((255, 128, 0, 255)));
+    }
+
+    #[test]
+    fn bloom_spreads_emissive_pixels_regardless_of_luminance() {
+        let mut backend = SoftwareBackend::new(8, 8);
+        // A single dim red pixel, and a mask marking it
+        let mut scene = vec![0; 8 * 8 * 4];
+        let mut mask = vec![0; 8 * 8 * 4];
+        let center = (4 * 8 + 4) * 4;
+        scene[center..center + 4].copy_from_slice(&[255, 0, 0, 255]);
+        mask[center..center + 4].copy_from_slice(&[0, 0, 0, 255]);
+        let scene = backend.create_texture(TextureDesc::new(8, 8), &scene).unwrap();
+        let mask = backend.create_texture(TextureDesc::new(8, 8), &mask).unwrap();
+        let screen = fullscreen(&mut backend);
+
+        let mut glow_beside = |emissive| {
+            let effect = PostEffect::Bloom { threshold: 0.7, intensity: 1.0, radius: 3.0, emissive };
+            let post = Pipeline::Post { effect: &effect, lut: None, shader: None, time: 0.0 };
+            backend.draw(&DrawCall::new(post, screen, 1, glm::Mat4::identity())
+                .texture(scene)
+                .blend(BlendMode::Opaque)
+                .depth_test(false));
+            backend.read_pixels().pixel(5, 3).unwrap()
+        };
+        // Too dark to glow by luminance
+        assert_eq!(glow_beside(None), CLEAR);
+        let (r, g, b, _) = glow_beside(Some(mask));
+        assert!(r > 0 && g == 0 && b == 0);
+    }
+
+    #[test]
+    fn occluders_cast_shadows_away_from_lights() {
+        let mut lights = LightRenderer::with_backend(SoftwareBackend::new(20, 20));
+        lights.mode = LightingMode::Smooth;
+        lights.shadow_color = (0.0, 0.0, 0.0);
+        lights.darkness = 1.0;
+        lights.init((20.0, 20.0)).unwrap();
+        lights.backend_mut().clear((1.0, 1.0, 1.0, 1.0));
+
+        let light = RenderLight::point((2.0, 10.0), 40.0, (1.0, 1.0, 1.0));
+        let wall = RenderOccluder { min: (8.0, 8.0), max: (10.0, 12.0) };
+        lights.render(&[light], &[wall], (20.0, 20.0), &Camera::default());
+
+        let image = lights.backend_mut().read_pixels();
+        // Behind the wall
+        assert_eq!(image.pixel(15, 9), Some(CLEAR));
+        // In front of it, and past its corners
+        assert_eq!(image.pixel(5, 9), Some(WHITE));
+        assert_eq!(image.pixel(15, 2), Some(WHITE));
+        assert_eq!(image.pixel(15, 17), Some(WHITE));
+    }
+}
@@ -0,0 +1,71 @@
+use std::ffi::CStr;
+
+/// The API and version of an OpenGL context.
+///
+/// # Example
+/// ```
+/// # use stoneng::renderer::context::GlVersion;
+/// let version = GlVersion::parse("OpenGL ES 3.0 Mesa 22.0.5").unwrap();
+/// assert!(version.es);
+/// assert_eq!(version.glsl_header(), "#version 300 es");
+/// assert!(!version.supports_geometry_shaders());
+///
+/// let version = GlVersion::parse("3.3 (Core Profile) Mesa 21.2.6").unwrap();
+/// assert_eq!((version.major, version.minor), (3, 3));
+/// assert_eq!(version.glsl_header(), "#version 330 core");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlVersion {
+    /// Whether the context is OpenGL ES
+    pub es:     bool,
+    pub major:  u32,
+    pub minor:  u32,
+}
+
+impl GlVersion {
+    /// Reads the version of the current context.
+    ///
+    /// This can _only_ be called after the OpenGL bindings have been loaded.
+    pub fn current() -> Option<Self> {
+        if !gl::GetString::is_loaded() { return None; }
+        let version = unsafe {
+            let ptr = gl::GetString(gl::VERSION);
+            if ptr.is_null() { return None; }
+            CStr::from_ptr(ptr as *const _).to_string_lossy().into_owned()
+        };
+        Self::parse(&version)
+    }
+
+    /// Parses a `GL_VERSION` string, e.g. "4.1 Metal - 76.3" or
+    /// "OpenGL ES 3.0 Mesa 22.0".
+    pub fn parse(version: &str) -> Option<Self> {
+        let es = version.starts_with("OpenGL ES");
+        let number = version.split_whitespace()
+            .find(|w| w.starts_with(|c: char| c.is_ascii_digit()))?;
+        let mut parts = number.split('.').map(|p| p.parse::<u32>().ok());
+        let major = parts.next()??;
+        let minor = parts.next().flatten().unwrap_or(0);
+        Some(Self { es, major, minor })
+    }
+
+    /// The `#version` line shaders are compiled with on this context.
+    pub fn glsl_header(&self) -> &'static str {
+        if self.es {
+            "#version 300 es"
+        } else if (self.major, self.minor) >= (4, 1) {
+            "#version 410 core"
+        } else {
+            "#version 330 core"
+        }
+    }
+
+    /// Whether geometry shaders can be used. Shaders are always compiled as
+    /// GLSL ES 3.00 on ES contexts, which has none.
+    pub fn supports_geometry_shaders(&self) -> bool {
+        !self.es && (self.major, self.minor) >= (3, 2)
+    }
+
+    pub fn supports_instancing(&self) -> bool {
+        if self.es { self.major >= 3 } else { (self.major, self.minor) >= (3, 3) }
+    }
+}
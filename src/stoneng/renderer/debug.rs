@@ -0,0 +1,268 @@
+#![allow(dead_code)]
+
+use crate::EngineError;
+use crate::shader::VertexAttribute;
+use crate::renderer::{Camera, text::RenderString};
+use crate::renderer::backend::{
+    RenderBackend, OpenGlBackend, PipelineKind, Pipeline, DrawCall, VertexData, BufferHandle,
+};
+
+use std::mem::offset_of;
+
+/// Debug drawing is only available in debug builds. In release builds every
+/// `DebugDraw` call returns immediately and is optimized away.
+pub const DEBUG_DRAW_AVAILABLE: bool = cfg!(debug_assertions);
+
+/// A shape pushed to `DebugDraw`, in world units.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DebugShape {
+    Line    { from: (f32, f32), to: (f32, f32) },
+    Arrow   { from: (f32, f32), to: (f32, f32) },
+    Rect    { min: (f32, f32), max: (f32, f32) },
+    Circle  { center: (f32, f32), radius: f32 },
+    Label   { pos: (f32, f32), text: String },
+}
+
+/// A shape with its color and remaining lifetime.
+#[derive(Debug, Clone)]
+pub struct DebugItem {
+    pub shape:      DebugShape,
+    pub color:      (f32, f32, f32, f32),
+    /// Seconds left to draw the item. Items at 0 are drawn for a single frame.
+    pub remaining:  f32,
+}
+impl DebugItem {
+    /// Keeps drawing the item for `seconds` instead of a single frame.
+    pub fn lasting(&mut self, seconds: f32) -> &mut Self {
+        self.remaining = seconds;
+        self
+    }
+}
+
+/// An immediate-mode list of debug shapes, drawn on top of the scene.
+///
+/// Any system may push shapes, which are drawn for the current frame or, using
+/// `lasting`, for a number of seconds:
+/// ```
+/// # use stoneng::renderer::debug::DebugDraw;
+/// let mut debug = DebugDraw::default();
+/// debug.line((0.0, 0.0), (10.0, 5.0), (1.0, 0.0, 0.0, 1.0));
+/// debug.circle((20.0, 20.0), 8.0, (0.0, 1.0, 0.0, 1.0)).lasting(2.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DebugDraw {
+    /// Turns debug drawing on or off at runtime. Shapes pushed while disabled
+    /// are discarded.
+    pub enabled:    bool,
+    items:          Vec<DebugItem>,
+    /// Handed out when drawing is disabled, so that calls can still be chained
+    discard:        DebugItem,
+}
+impl Default for DebugDraw {
+    fn default() -> Self {
+        Self {
+            enabled: DEBUG_DRAW_AVAILABLE,
+            items: Vec::new(),
+            discard: DebugItem {
+                shape: DebugShape::Line { from: (0.0, 0.0), to: (0.0, 0.0) },
+                color: (0.0, 0.0, 0.0, 0.0),
+                remaining: 0.0,
+            },
+        }
+    }
+}
+impl DebugDraw {
+    /// True if pushed shapes will be drawn.
+    pub fn is_active(&self) -> bool {
+        DEBUG_DRAW_AVAILABLE && self.enabled
+    }
+
+    pub fn push(&mut self, shape: DebugShape, color: (f32, f32, f32, f32)) -> &mut DebugItem {
+        if !self.is_active() { return &mut self.discard; }
+        self.items.push(DebugItem { shape, color, remaining: 0.0 });
+        self.items.last_mut().unwrap()
+    }
+
+    pub fn line(&mut self, from: (f32, f32), to: (f32, f32),
+                color: (f32, f32, f32, f32)) -> &mut DebugItem {
+        self.push(DebugShape::Line { from, to }, color)
+    }
+
+    /// A line with a head at `to`.
+    pub fn arrow(&mut self, from: (f32, f32), to: (f32, f32),
+                 color: (f32, f32, f32, f32)) -> &mut DebugItem {
+        self.push(DebugShape::Arrow { from, to }, color)
+    }
+
+    /// An axis-aligned box spanning two corners.
+    pub fn rect(&mut self, min: (f32, f32), max: (f32, f32),
+                color: (f32, f32, f32, f32)) -> &mut DebugItem {
+        self.push(DebugShape::Rect { min, max }, color)
+    }
+
+    pub fn circle(&mut self, center: (f32, f32), radius: f32,
+                  color: (f32, f32, f32, f32)) -> &mut DebugItem {
+        self.push(DebugShape::Circle { center, radius }, color)
+    }
+
+    /// Text anchored at a world position.
+    pub fn label(&mut self, pos: (f32, f32), text: &str,
+                 color: (f32, f32, f32, f32)) -> &mut DebugItem {
+        self.push(DebugShape::Label { pos, text: text.into() }, color)
+    }
+
+    pub fn items(&self) -> &[DebugItem] { &self.items[..] }
+
+    /// Removes all shapes, including lasting ones.
+    pub fn clear(&mut self) {
+        self.items.clear();
+    }
+
+    /// Ages the shapes after a frame has been drawn, dropping expired ones.
+    pub fn tick(&mut self, dt: f32) {
+        self.items.retain_mut(|item| {
+            if item.remaining <= 0.0 { return false; }
+            item.remaining -= dt;
+            true
+        });
+    }
+}
+
+/// A colored vertex of a debug line.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DebugVertex {
+    pub pos:    (f32, f32, f32),
+    pub color:  (f32, f32, f32, f32),
+}
+
+/// The attributes of a DebugVertex, as read by `debug/vert.glsl`.
+pub const DEBUG_VERTEX_LAYOUT: [VertexAttribute; 2] = [
+    VertexAttribute::new(0, 3, gl::FLOAT, offset_of!(DebugVertex, pos)),
+    VertexAttribute::new(1, 4, gl::FLOAT, offset_of!(DebugVertex, color)),
+];
+
+/// The DebugRenderer draws the shapes of a `DebugDraw` as lines.
+///
+/// Labels are not drawn by the DebugRenderer, but returned from `build` to be
+/// drawn by a TextRenderer.
+///
+/// Drawing goes through a `RenderBackend`, by default OpenGL, in which case the
+/// renderer must only be used _after_ the OpenGL bindings have been loaded and
+/// only on the main thread. The backend's objects are freed when the renderer
+/// is dropped.
+#[derive(Debug)]
+pub struct DebugRenderer<B: RenderBackend = OpenGlBackend> {
+    backend:    B,
+    lines:      Option<BufferHandle>,
+}
+impl<B: RenderBackend + Default> Default for DebugRenderer<B> {
+    fn default() -> Self {
+        Self::with_backend(B::default())
+    }
+}
+
+impl DebugRenderer {
+    /// Creates an empty DebugRenderer. `init` must be called before use.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Converts the shapes into line vertices and label strings.
+    pub fn build(debug: &DebugDraw, lines: &mut Vec<DebugVertex>, labels: &mut Vec<RenderString>) {
+        let mut push_line = |a: (f32, f32), b: (f32, f32), color| {
+            lines.push(DebugVertex { pos: (a.0, a.1, 0.0), color });
+            lines.push(DebugVertex { pos: (b.0, b.1, 0.0), color });
+        };
+
+        for item in debug.items() {
+            let color = item.color;
+            match &item.shape {
+                DebugShape::Line { from, to } => push_line(*from, *to, color),
+                DebugShape::Arrow { from, to } => {
+                    push_line(*from, *to, color);
+                    // Two head strokes, angled back from the tip
+                    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+                    let len = (dx * dx + dy * dy).sqrt();
+                    if len <= f32::EPSILON { continue; }
+                    let head = (len * 0.25).min(4.0);
+                    let (ux, uy) = (dx / len, dy / len);
+                    for side in [-1.0, 1.0] {
+                        let (c, s) = (0.866, 0.5 * side);
+                        let back = (-(ux * c - uy * s), -(ux * s + uy * c));
+                        push_line(*to, (to.0 + back.0 * head, to.1 + back.1 * head), color);
+                    }
+                },
+                DebugShape::Rect { min, max } => {
+                    push_line((min.0, min.1), (max.0, min.1), color);
+                    push_line((max.0, min.1), (max.0, max.1), color);
+                    push_line((max.0, max.1), (min.0, max.1), color);
+                    push_line((min.0, max.1), (min.0, min.1), color);
+                },
+                DebugShape::Circle { center, radius } => {
+                    // More segments for larger circles, so they stay round
+                    let segments = (radius * 0.75).clamp(12.0, 64.0) as usize;
+                    let step = std::f32::consts::TAU / segments as f32;
+                    for i in 0..segments {
+                        let (a, b) = (step * i as f32, step * (i + 1) as f32);
+                        push_line(
+                            (center.0 + a.cos() * radius, center.1 + a.sin() * radius),
+                            (center.0 + b.cos() * radius, center.1 + b.sin() * radius),
+                            color
+                        );
+                    }
+                },
+                DebugShape::Label { pos, text } => labels.push(RenderString {
+                    position: (pos.0, pos.1, 0.0),
+                    size: 1.0,
+                    color,
+                    text: text.clone(),
+                }),
+            }
+        }
+    }
+}
+
+impl<B: RenderBackend> DebugRenderer<B> {
+    /// Creates an empty DebugRenderer drawing through `backend`. `init` must
+    /// be called before use.
+    pub fn with_backend(backend: B) -> Self {
+        Self { backend, lines: None }
+    }
+
+    pub fn backend(&self) -> &B { &self.backend }
+    pub fn backend_mut(&mut self) -> &mut B { &mut self.backend }
+
+    /// Prepares the backend for drawing lines.
+    ///
+    /// With the OpenGlBackend this can _only_ be called after the OpenGL
+    /// bindings have been loaded.
+    pub fn init(&mut self) -> Result<(), EngineError> {
+        if self.lines.is_some() { return Ok(()) }
+
+        self.backend.init(&[PipelineKind::Lines])?;
+        let lines = self.backend.create_buffer();
+        self.backend.label_buffer(lines, "debug lines");
+
+        self.lines = Some(lines);
+        Ok(())
+    }
+
+    /// Draws line vertices over everything that has been drawn so far.
+    ///
+    /// The depth buffer is cleared, so that labels drawn afterwards also end up on top.
+    pub fn render(&mut self, lines: &[DebugVertex], window_size: (f32, f32), cam: &Camera) {
+        let buffer = match self.lines {
+            Some(buffer) => buffer,
+            None => return,
+        };
+        self.backend.clear_depth();
+        if lines.is_empty() { return; }
+        self.backend.resize_screen(window_size.0 as u32, window_size.1 as u32);
+
+        self.backend.stream(buffer, VertexData::Lines(lines));
+        self.backend.draw(&DrawCall::new(Pipeline::Lines, buffer, lines.len(),
+                                         cam.view_projection(window_size))
+            .depth_test(false));
+    }
+}
@@ -0,0 +1,243 @@
+//! OpenGL debug output, object labels and error checks.
+//!
+//! Where the context supports `KHR_debug`, driver messages are sent to the
+//! engine log, objects can be given labels that show up in those messages and
+//! in tools such as RenderDoc, and passes are wrapped in debug groups. Without
+//! it, labels and groups are skipped.
+//!
+//! In debug builds `glGetError` is also checked after every pass, reporting
+//! errors along with the pass's name.
+use std::{
+    ffi::{CStr, CString},
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+};
+use crate::log::{self, LogLevel};
+use crate::renderer::context::GlVersion;
+use gl::types::*;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static MIN_SEVERITY: AtomicU8 = AtomicU8::new(DebugSeverity::Low as u8);
+
+/// The severity the driver gives a debug message, from most to least severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DebugSeverity {
+    /// Errors and undefined behavior, logged as errors
+    High,
+    /// Major performance problems and deprecated usage, logged as warnings
+    Medium,
+    /// Minor performance problems, logged as info
+    Low,
+    /// Anything else, such as buffer placement, logged as debug
+    Notification,
+}
+impl DebugSeverity {
+    const ALL: [DebugSeverity; 4] = [
+        DebugSeverity::High, DebugSeverity::Medium,
+        DebugSeverity::Low, DebugSeverity::Notification,
+    ];
+
+    fn from_gl(severity: GLenum) -> Self {
+        match severity {
+            gl::DEBUG_SEVERITY_HIGH     => DebugSeverity::High,
+            gl::DEBUG_SEVERITY_MEDIUM   => DebugSeverity::Medium,
+            gl::DEBUG_SEVERITY_LOW      => DebugSeverity::Low,
+            _                           => DebugSeverity::Notification,
+        }
+    }
+
+    fn to_gl(self) -> GLenum {
+        match self {
+            DebugSeverity::High         => gl::DEBUG_SEVERITY_HIGH,
+            DebugSeverity::Medium       => gl::DEBUG_SEVERITY_MEDIUM,
+            DebugSeverity::Low          => gl::DEBUG_SEVERITY_LOW,
+            DebugSeverity::Notification => gl::DEBUG_SEVERITY_NOTIFICATION,
+        }
+    }
+
+    fn from_u8(severity: u8) -> Self {
+        Self::ALL.get(severity as usize).copied().unwrap_or(DebugSeverity::Notification)
+    }
+
+    /// The log level messages of this severity are written at.
+    pub fn log_level(&self) -> LogLevel {
+        match self {
+            DebugSeverity::High         => LogLevel::Error,
+            DebugSeverity::Medium       => LogLevel::Warn,
+            DebugSeverity::Low          => LogLevel::Info,
+            DebugSeverity::Notification => LogLevel::Debug,
+        }
+    }
+}
+
+/// Whether the context supports `KHR_debug`, through GL 4.3, GLES 3.2 or the
+/// extension.
+///
+/// This can _only_ be called after the OpenGL bindings have been loaded.
+pub fn supported() -> bool {
+    if !gl::DebugMessageCallback::is_loaded() || !gl::DebugMessageControl::is_loaded() {
+        return false;
+    }
+    let core = GlVersion::current().map(|v| {
+        if v.es { (v.major, v.minor) >= (3, 2) } else { (v.major, v.minor) >= (4, 3) }
+    }).unwrap_or(false);
+    core || has_extension("GL_KHR_debug")
+}
+
+fn has_extension(name: &str) -> bool {
+    if !gl::GetStringi::is_loaded() { return false; }
+    unsafe {
+        let mut count: GLint = 0;
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+        (0..count.max(0) as GLuint).any(|i| {
+            let ptr = gl::GetStringi(gl::EXTENSIONS, i);
+            !ptr.is_null() && CStr::from_ptr(ptr as *const _).to_bytes() == name.as_bytes()
+        })
+    }
+}
+
+/// Sends the driver's debug messages to the engine log where `KHR_debug` is
+/// supported, returning whether it is. Messages are delivered synchronously
+/// in debug builds, so that they are logged from the call that caused them.
+///
+/// Called by the engine once the OpenGL bindings have been loaded.
+pub fn init() -> bool {
+    if !supported() {
+        log::debug("gl", "KHR_debug is unsupported, driver messages will not be logged");
+        return false;
+    }
+    unsafe {
+        gl::Enable(gl::DEBUG_OUTPUT);
+        if cfg!(debug_assertions) {
+            gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        }
+        gl::DebugMessageCallback(Some(message_callback), std::ptr::null());
+    }
+    ENABLED.store(true, Ordering::Relaxed);
+    set_min_severity(min_severity());
+    true
+}
+
+/// Whether debug output was enabled by `init`.
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Sets the least severe driver message that is logged. Defaults to `Low`.
+///
+/// Less severe messages are filtered by the driver, and messages are also
+/// subject to the engine's log level.
+pub fn set_min_severity(min: DebugSeverity) {
+    MIN_SEVERITY.store(min as u8, Ordering::Relaxed);
+    if !enabled() { return; }
+    for severity in DebugSeverity::ALL {
+        let on = if severity <= min { gl::TRUE } else { gl::FALSE };
+        unsafe {
+            gl::DebugMessageControl(gl::DONT_CARE, gl::DONT_CARE, severity.to_gl(),
+                                    0, std::ptr::null(), on);
+        }
+    }
+}
+
+pub fn min_severity() -> DebugSeverity {
+    DebugSeverity::from_u8(MIN_SEVERITY.load(Ordering::Relaxed))
+}
+
+extern "system" fn message_callback(source: GLenum, kind: GLenum, id: GLuint, severity: GLenum,
+                                     length: GLsizei, message: *const GLchar,
+                                     _user: *mut std::ffi::c_void) {
+    let severity = DebugSeverity::from_gl(severity);
+    if severity > min_severity() || message.is_null() { return; }
+    let message = unsafe {
+        let bytes = std::slice::from_raw_parts(message as *const u8, length.max(0) as usize);
+        String::from_utf8_lossy(bytes).into_owned()
+    };
+    log::write(severity.log_level(), "gl", format!("{} {} {}: {}",
+        source_name(source), type_name(kind), id, message.trim_end()));
+}
+
+fn source_name(source: GLenum) -> &'static str {
+    match source {
+        gl::DEBUG_SOURCE_API                => "api",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM      => "window-system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER    => "shader-compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY        => "third-party",
+        gl::DEBUG_SOURCE_APPLICATION        => "application",
+        _                                   => "other",
+    }
+}
+
+fn type_name(kind: GLenum) -> &'static str {
+    match kind {
+        gl::DEBUG_TYPE_ERROR                => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR  => "deprecated",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR   => "undefined",
+        gl::DEBUG_TYPE_PORTABILITY          => "portability",
+        gl::DEBUG_TYPE_PERFORMANCE          => "performance",
+        gl::DEBUG_TYPE_MARKER               => "marker",
+        gl::DEBUG_TYPE_PUSH_GROUP           => "push-group",
+        gl::DEBUG_TYPE_POP_GROUP            => "pop-group",
+        _                                   => "other",
+    }
+}
+
+/// Gives an object a label that is used in driver messages and debuggers.
+/// Does nothing unless debug output is enabled.
+///
+/// `identifier` is the kind of object, e.g. `gl::TEXTURE`. Objects other than
+/// programs must have been bound at least once.
+pub fn label_object(identifier: GLenum, name: GLuint, label: &str) {
+    if !enabled() || name == 0 || !gl::ObjectLabel::is_loaded() { return; }
+    let label = CString::new(label).unwrap_or_default();
+    unsafe { gl::ObjectLabel(identifier, name, -1, label.as_ptr()); }
+}
+
+/// Runs a renderer pass inside a debug group named `name`.
+///
+/// In debug builds `glGetError` is checked afterwards, logging any errors
+/// along with the pass's name.
+pub fn pass<T>(name: &str, draw: impl FnOnce() -> T) -> T {
+    let group = enabled() && gl::PushDebugGroup::is_loaded() && gl::PopDebugGroup::is_loaded();
+    if group {
+        let label = CString::new(name).unwrap_or_default();
+        unsafe { gl::PushDebugGroup(gl::DEBUG_SOURCE_APPLICATION, 0, -1, label.as_ptr()); }
+    }
+    let result = draw();
+    if group {
+        unsafe { gl::PopDebugGroup(); }
+    }
+    if cfg!(debug_assertions) {
+        check_errors(name);
+    }
+    result
+}
+
+/// Logs and clears every error raised by OpenGL since the last check,
+/// attributing them to `pass`. Returns whether there were any.
+///
+/// This can _only_ be called after the OpenGL bindings have been loaded.
+pub fn check_errors(pass: &str) -> bool {
+    if !gl::GetError::is_loaded() { return false; }
+    let mut found = false;
+    // Drivers may record several errors, each returned by its own call.
+    // The count is limited in case a lost context keeps reporting one.
+    for _ in 0..16 {
+        let error = unsafe { gl::GetError() };
+        if error == gl::NO_ERROR { break; }
+        found = true;
+        log::error("gl", format!("{} after pass '{}'", error_name(error), pass));
+    }
+    found
+}
+
+fn error_name(error: GLenum) -> String {
+    match error {
+        gl::INVALID_ENUM                    => "GL_INVALID_ENUM".into(),
+        gl::INVALID_VALUE                   => "GL_INVALID_VALUE".into(),
+        gl::INVALID_OPERATION               => "GL_INVALID_OPERATION".into(),
+        gl::INVALID_FRAMEBUFFER_OPERATION   => "GL_INVALID_FRAMEBUFFER_OPERATION".into(),
+        gl::OUT_OF_MEMORY                   => "GL_OUT_OF_MEMORY".into(),
+        gl::STACK_OVERFLOW                  => "GL_STACK_OVERFLOW".into(),
+        gl::STACK_UNDERFLOW                 => "GL_STACK_UNDERFLOW".into(),
+        other                               => format!("GL error {:#x}", other),
+    }
+}
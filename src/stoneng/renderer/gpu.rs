@@ -0,0 +1,331 @@
+//! Owned OpenGL objects.
+//!
+//! Each wrapper holds one GL object name and deletes the object when it is
+//! dropped, so renderers free their resources when they are dropped or
+//! replaced. Objects dropped on another thread, or before the GL thread is
+//! known, are queued and deleted by `collect_garbage`, which the engine calls
+//! once per frame.
+//!
+//! Live objects and the memory they were given are counted per kind and read
+//! with `memory_report`.
+use std::{
+    fmt,
+    sync::{Mutex, OnceLock, atomic::{AtomicUsize, Ordering}},
+    thread::{self, ThreadId},
+};
+use lazy_static::lazy_static;
+use gl::types::*;
+use crate::renderer::gl_debug;
+
+/// The kinds of OpenGL object the engine owns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GlKind {
+    #[default]
+    Buffer,
+    VertexArray,
+    Texture,
+    Framebuffer,
+    Renderbuffer,
+    Program,
+    Shader,
+}
+impl GlKind {
+    const ALL: [GlKind; 7] = [
+        GlKind::Buffer, GlKind::VertexArray, GlKind::Texture,
+        GlKind::Framebuffer, GlKind::Renderbuffer, GlKind::Program, GlKind::Shader,
+    ];
+
+    /// The identifier of this kind of object for `glObjectLabel`.
+    pub fn identifier(&self) -> GLenum {
+        match self {
+            GlKind::Buffer          => gl::BUFFER,
+            GlKind::VertexArray     => gl::VERTEX_ARRAY,
+            GlKind::Texture         => gl::TEXTURE,
+            GlKind::Framebuffer     => gl::FRAMEBUFFER,
+            GlKind::Renderbuffer    => gl::RENDERBUFFER,
+            GlKind::Program         => gl::PROGRAM,
+            GlKind::Shader          => gl::SHADER,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            GlKind::Buffer          => "buffers",
+            GlKind::VertexArray     => "vertex arrays",
+            GlKind::Texture         => "textures",
+            GlKind::Framebuffer     => "framebuffers",
+            GlKind::Renderbuffer    => "renderbuffers",
+            GlKind::Program         => "programs",
+            GlKind::Shader          => "shaders",
+        }
+    }
+}
+
+static GL_THREAD: OnceLock<ThreadId> = OnceLock::new();
+static COUNTS: [AtomicUsize; 7] = [const { AtomicUsize::new(0) }; 7];
+static BYTES: [AtomicUsize; 7] = [const { AtomicUsize::new(0) }; 7];
+lazy_static! {
+    /// Objects dropped away from the GL thread, waiting to be deleted
+    static ref GARBAGE: Mutex<Vec<(GlKind, GLuint)>> = Mutex::new(Vec::new());
+}
+
+/// Records the calling thread as the one owning the GL context, on which
+/// dropped objects are deleted immediately.
+///
+/// Called by the engine once the OpenGL bindings have been loaded.
+pub fn set_gl_thread() {
+    let _ = GL_THREAD.set(thread::current().id());
+}
+
+fn on_gl_thread() -> bool {
+    GL_THREAD.get() == Some(&thread::current().id())
+}
+
+/// Deletes the objects dropped away from the GL thread since the last call.
+///
+/// This can _only_ be called on the GL thread.
+pub fn collect_garbage() {
+    let garbage = std::mem::take(&mut *GARBAGE.lock().unwrap());
+    for (kind, name) in garbage {
+        unsafe { delete(kind, name); }
+    }
+}
+
+unsafe fn delete(kind: GlKind, name: GLuint) {
+    match kind {
+        GlKind::Buffer          => gl::DeleteBuffers(1, &name),
+        GlKind::VertexArray     => gl::DeleteVertexArrays(1, &name),
+        GlKind::Texture         => gl::DeleteTextures(1, &name),
+        GlKind::Framebuffer     => gl::DeleteFramebuffers(1, &name),
+        GlKind::Renderbuffer    => gl::DeleteRenderbuffers(1, &name),
+        GlKind::Program         => gl::DeleteProgram(name),
+        GlKind::Shader          => gl::DeleteShader(name),
+    }
+}
+
+/// The number of bytes in a `width` by `height` image of `components`
+/// single-byte channels.
+pub fn image_bytes(width: i32, height: i32, components: usize) -> usize {
+    width.max(0) as usize * height.max(0) as usize * components
+}
+
+/// A GL object name, the kind it was created as and the bytes it is counted as holding.
+#[derive(Debug, Default)]
+struct GlObject {
+    kind:   GlKind,
+    name:   GLuint,
+    bytes:  AtomicUsize,
+}
+impl GlObject {
+    fn generate(kind: GlKind) -> Self {
+        let mut name = 0;
+        unsafe {
+            match kind {
+                GlKind::Buffer          => gl::GenBuffers(1, &mut name),
+                GlKind::VertexArray     => gl::GenVertexArrays(1, &mut name),
+                GlKind::Texture         => gl::GenTextures(1, &mut name),
+                GlKind::Framebuffer     => gl::GenFramebuffers(1, &mut name),
+                GlKind::Renderbuffer    => gl::GenRenderbuffers(1, &mut name),
+                GlKind::Program         => name = gl::CreateProgram(),
+                // Shaders are created for a stage, so they are only adopted
+                GlKind::Shader          => unreachable!("GlShader has no `new`"),
+            }
+        }
+        Self::adopt(kind, name)
+    }
+
+    fn adopt(kind: GlKind, name: GLuint) -> Self {
+        if name != 0 {
+            COUNTS[kind as usize].fetch_add(1, Ordering::Relaxed);
+        }
+        Self { kind, name, bytes: AtomicUsize::new(0) }
+    }
+
+    fn set_bytes(&self, bytes: usize) {
+        if self.name == 0 { return; }
+        let old = self.bytes.swap(bytes, Ordering::Relaxed);
+        BYTES[self.kind as usize].fetch_add(bytes, Ordering::Relaxed);
+        BYTES[self.kind as usize].fetch_sub(old, Ordering::Relaxed);
+    }
+}
+impl Drop for GlObject {
+    fn drop(&mut self) {
+        if self.name == 0 { return; }
+        self.set_bytes(0);
+        COUNTS[self.kind as usize].fetch_sub(1, Ordering::Relaxed);
+        if on_gl_thread() {
+            unsafe { delete(self.kind, self.name); }
+        } else {
+            GARBAGE.lock().unwrap().push((self.kind, self.name));
+        }
+    }
+}
+
+macro_rules! gl_object {
+    ($(#[$doc:meta])* $name:ident, $kind:expr) => {
+        gl_object!($(#[$doc])* $name, $kind, adopted);
+        impl $name {
+            /// Creates a new object.
+            ///
+            /// This can _only_ be called after the OpenGL bindings have been loaded.
+            pub fn new() -> Self {
+                Self(GlObject::generate($kind))
+            }
+        }
+    };
+    // Objects that can't be created without parameters, only adopted
+    ($(#[$doc:meta])* $name:ident, $kind:expr, adopted) => {
+        $(#[$doc])*
+        ///
+        /// The default value holds no object.
+        #[derive(Debug, Default)]
+        pub struct $name(GlObject);
+        impl $name {
+            /// Takes ownership of an existing object, which is deleted when
+            /// the wrapper is dropped.
+            pub fn adopt(name: GLuint) -> Self {
+                Self(GlObject::adopt($kind, name))
+            }
+
+            /// Gives up ownership of the object, returning its name without
+            /// deleting it.
+            pub fn into_raw(self) -> GLuint {
+                let name = self.0.name;
+                if name != 0 {
+                    self.set_bytes(0);
+                    COUNTS[$kind as usize].fetch_sub(1, Ordering::Relaxed);
+                }
+                std::mem::forget(self);
+                name
+            }
+
+            /// The object's name, or 0 if it holds none.
+            pub fn id(&self) -> GLuint {
+                self.0.name
+            }
+
+            /// Records the bytes of GPU memory the object holds, replacing
+            /// the previous amount, for `memory_report`.
+            pub fn set_bytes(&self, bytes: usize) {
+                self.0.set_bytes(bytes)
+            }
+
+            /// Names the object in driver messages and debuggers, see
+            /// `gl_debug::label_object`. It must have been bound at least once.
+            pub fn label(&self, label: &str) {
+                gl_debug::label_object($kind.identifier(), self.0.name, label)
+            }
+
+            /// The bytes of GPU memory last recorded with `set_bytes`.
+            pub fn bytes(&self) -> usize {
+                self.0.bytes.load(Ordering::Relaxed)
+            }
+        }
+    };
+}
+
+gl_object!(
+    /// An owned buffer object.
+    GlBuffer, GlKind::Buffer
+);
+gl_object!(
+    /// An owned vertex array object.
+    GlVertexArray, GlKind::VertexArray
+);
+gl_object!(
+    /// An owned texture.
+    GlTexture, GlKind::Texture
+);
+gl_object!(
+    /// An owned framebuffer.
+    GlFramebuffer, GlKind::Framebuffer
+);
+gl_object!(
+    /// An owned renderbuffer.
+    GlRenderbuffer, GlKind::Renderbuffer
+);
+gl_object!(
+    /// An owned shader program.
+    GlProgram, GlKind::Program
+);
+gl_object!(
+    /// An owned shader stage, compiled before being linked into a program.
+    GlShader, GlKind::Shader, adopted
+);
+
+impl GlBuffer {
+    /// Binds the buffer to `target` and fills it with `data`, recording its size.
+    /// The buffer is left bound.
+    ///
+    /// # Safety
+    /// The OpenGL bindings must be loaded, and `T` must be plain data.
+    pub unsafe fn upload<T>(&self, target: GLenum, data: &[T], usage: GLenum) {
+        let size = std::mem::size_of_val(data);
+        gl::BindBuffer(target, self.id());
+        gl::BufferData(target, size as GLsizeiptr, data.as_ptr() as *const GLvoid, usage);
+        self.set_bytes(size);
+    }
+}
+
+/// The number of live objects of one kind and the bytes recorded for them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct GpuUsage {
+    pub count:  usize,
+    pub bytes:  usize,
+}
+
+/// A snapshot of the OpenGL objects owned by the engine.
+///
+/// Byte counts are estimates from the sizes of the uploaded data and allocated
+/// images. Vertex arrays, framebuffers, programs and shaders are counted
+/// without bytes.
+///
+/// # Example
+/// ```
+/// # use stoneng::renderer::gpu;
+/// let report = gpu::memory_report();
+/// assert_eq!(report.total_bytes(), report.kinds().map(|(_, u)| u.bytes).sum::<usize>());
+/// println!("{}", report);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GpuMemoryReport {
+    usage: [GpuUsage; 7],
+}
+impl GpuMemoryReport {
+    /// The usage of objects of one kind.
+    pub fn usage(&self, kind: GlKind) -> GpuUsage {
+        self.usage[kind as usize]
+    }
+
+    /// The usage of each kind of object.
+    pub fn kinds(&self) -> impl Iterator<Item = (GlKind, GpuUsage)> + '_ {
+        GlKind::ALL.iter().map(move |kind| (*kind, self.usage(*kind)))
+    }
+
+    pub fn total_bytes(&self) -> usize {
+        self.usage.iter().map(|u| u.bytes).sum()
+    }
+}
+impl fmt::Display for GpuMemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<14} {:>6} {:>12}", "GPU objects", "count", "KiB")?;
+        for (kind, usage) in self.kinds() {
+            writeln!(f, "{:<14} {:>6} {:>12.1}", kind.label(), usage.count, usage.bytes as f64 / 1024.0)?;
+        }
+        write!(f, "{:<14} {:>6} {:>12.1}", "total",
+               self.usage.iter().map(|u| u.count).sum::<usize>(),
+               self.total_bytes() as f64 / 1024.0)
+    }
+}
+
+/// Reads the current number of live objects and their recorded bytes.
+pub fn memory_report() -> GpuMemoryReport {
+    let mut report = GpuMemoryReport::default();
+    for kind in GlKind::ALL {
+        report.usage[kind as usize] = GpuUsage {
+            count: COUNTS[kind as usize].load(Ordering::Relaxed),
+            bytes: BYTES[kind as usize].load(Ordering::Relaxed),
+        };
+    }
+    report
+}
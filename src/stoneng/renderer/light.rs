@@ -1,258 +1,434 @@
 #![allow(dead_code)]
 
 use crate::EngineError;
-use crate::shader;
-use crate::ecs::component;
-
-use stb::image::LoadResult;
-use std::{
-    path::Path,
-    mem::size_of,
+use crate::shader::VertexAttribute;
+use crate::renderer::Camera;
+use crate::renderer::backend::{
+    RenderBackend, OpenGlBackend, PipelineKind, Pipeline, DrawCall, VertexData,
+    BlendMode, StencilTest, Filter, Quad, BufferHandle, TargetHandle,
 };
-use glm::{Vec2, Vec3, Vec4};
-use gl::types::*;
+use crate::renderer::shadow::{self, RenderOccluder, ShadowMode};
+
+use std::mem::offset_of;
 
+/// A point or spot light. Used directly for rendering.
+///
+/// Brightness falls from the center to `intensity` world units away, following
+/// `(1 - distance² / intensity²)^falloff`. Spot lights also fade from the inner
+/// to the outer edge of their cone.
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct RenderLight {
     pub pos: (f32, f32),
     pub intensity: f32,
+    /// RGB from 0-1, added to the lightmap
+    pub color: (f32, f32, f32),
+    /// The unit direction a spot light shines in
+    pub direction: (f32, f32),
+    /// The cosines of a spot light's inner and outer half angles, or -1 for
+    /// a point light
+    pub cone: (f32, f32),
+    pub falloff: f32,
+}
+impl RenderLight {
+    /// A light shining in all directions, with the quadratic falloff point
+    /// lights have always had.
+    pub fn point(pos: (f32, f32), intensity: f32, color: (f32, f32, f32)) -> Self {
+        Self { pos, intensity, color, direction: (1.0, 0.0), cone: (-1.0, -1.0), falloff: 2.0 }
+    }
+
+    /// A light shining along `angle`, in radians counter-clockwise from +x.
+    /// `cone` holds the inner and outer half angles in radians: it is full
+    /// strength within the inner angle of `angle`, fading to nothing at the
+    /// outer angle.
+    ///
+    /// # Example
+    /// ```
+    /// # use stoneng::renderer::light::RenderLight;
+    /// let light = RenderLight::spot((0.0, 0.0), 100.0, (1.0, 1.0, 1.0), 0.0, (0.2, 0.5), 2.0);
+    /// assert_eq!(light.direction, (1.0, 0.0));
+    /// assert!(light.cone.0 > light.cone.1);
+    /// ```
+    pub fn spot(pos: (f32, f32), intensity: f32, color: (f32, f32, f32),
+                angle: f32, cone: (f32, f32), falloff: f32) -> Self {
+        let outer = cone.1.abs().min(std::f32::consts::PI);
+        let inner = cone.0.abs().min(outer);
+        let (cos_inner, cos_outer) = (inner.cos(), outer.cos());
+        Self {
+            pos, intensity, color,
+            direction: (angle.cos(), angle.sin()),
+            // The shader's smoothstep needs the edges apart
+            cone: (cos_inner.max(cos_outer + 1e-4), cos_outer),
+            falloff,
+        }
+    }
+}
+
+/// The attributes of a RenderLight, as read by `lightmap/vert.glsl`.
+pub const LIGHT_LAYOUT: [VertexAttribute; 6] = [
+    VertexAttribute::new(0, 2, gl::FLOAT, offset_of!(RenderLight, pos)),
+    VertexAttribute::new(1, 1, gl::FLOAT, offset_of!(RenderLight, intensity)),
+    VertexAttribute::new(2, 3, gl::FLOAT, offset_of!(RenderLight, color)),
+    VertexAttribute::new(3, 2, gl::FLOAT, offset_of!(RenderLight, direction)),
+    VertexAttribute::new(4, 2, gl::FLOAT, offset_of!(RenderLight, cone)),
+    VertexAttribute::new(5, 1, gl::FLOAT, offset_of!(RenderLight, falloff)),
+];
+
+/// How strongly each light is drawn to the lightmap. Lights used to be drawn
+/// twice, and the gain keeps that brightness with a single draw.
+const LIGHT_GAIN: f32 = 2.0;
+
+/// The ordered dithering pattern used by `LightingMode::Dithered`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DitherMatrix {
+    /// A 2x2 Bayer matrix, 4 levels of light in a coarse pattern
+    Bayer2,
+    /// A 4x4 Bayer matrix, 16 levels of light
+    Bayer4,
+    /// An 8x8 Bayer matrix, 64 levels of light in a fine pattern
+    #[default]
+    Bayer8,
+}
+impl DitherMatrix {
+    /// The width and height of the matrix in cells.
+    pub fn size(&self) -> i32 {
+        match self {
+            DitherMatrix::Bayer2 => 2,
+            DitherMatrix::Bayer4 => 4,
+            DitherMatrix::Bayer8 => 8,
+        }
+    }
+
+    /// The threshold of a cell, from 0 up to 1, as `bayer` in `include/dither.glsl`.
+    /// Cells repeat every `size` along each axis.
+    ///
+    /// # Example
+    /// ```
+    /// # use stoneng::renderer::light::DitherMatrix;
+    /// assert_eq!(DitherMatrix::Bayer2.threshold(1, 0), 0.5);
+    /// assert_eq!(DitherMatrix::Bayer2.threshold(3, 2), 0.5);
+    /// ```
+    pub fn threshold(&self, x: i32, y: i32) -> f32 {
+        const BAYER2: [u8; 4] = [0, 2, 3, 1];
+        const BAYER4: [u8; 16] = [
+             0,  8,  2, 10,
+            12,  4, 14,  6,
+             3, 11,  1,  9,
+            15,  7, 13,  5,
+        ];
+        const BAYER8: [u8; 64] = [
+             0, 32,  8, 40,  2, 34, 10, 42,
+            48, 16, 56, 24, 50, 18, 58, 26,
+            12, 44,  4, 36, 14, 46,  6, 38,
+            60, 28, 52, 20, 62, 30, 54, 22,
+             3, 35, 11, 43,  1, 33,  9, 41,
+            51, 19, 59, 27, 49, 17, 57, 25,
+            15, 47,  7, 39, 13, 45,  5, 37,
+            63, 31, 55, 23, 61, 29, 53, 21,
+        ];
+        let size = self.size();
+        let matrix: &[u8] = match self {
+            DitherMatrix::Bayer2 => &BAYER2,
+            DitherMatrix::Bayer4 => &BAYER4,
+            DitherMatrix::Bayer8 => &BAYER8,
+        };
+        let id = (x & (size - 1)) + (y & (size - 1)) * size;
+        matrix[id as usize] as f32 / (size * size) as f32
+    }
 }
 
-#[derive(Default, Debug)]
-pub struct LightRenderer {
-    initialized:        bool,
-    pub dithered:       bool, // TODO implement disabling dithering
+/// How the lightmap is turned into shadows over the scene.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightingMode {
+    /// Each pixel is either lit or in shadow, with the falloff of lights drawn
+    /// as an ordered dither. Cells of the matrix are `dither_scale` pixels wide.
+    Dithered(DitherMatrix),
+    /// Shadows fade smoothly with the light.
+    Smooth,
+    /// The light is stepped into `bands` even levels, giving hard edged rings.
+    Posterized { bands: u32 },
+}
+impl Default for LightingMode {
+    fn default() -> Self {
+        LightingMode::Dithered(DitherMatrix::default())
+    }
+}
+impl LightingMode {
+    /// The value of `lighting_mode` in `include/lighting.glsl`.
+    pub(crate) fn shader_id(&self) -> i32 {
+        match self {
+            LightingMode::Dithered(_)       => 0,
+            LightingMode::Smooth            => 1,
+            LightingMode::Posterized { .. } => 2,
+        }
+    }
+
+    /// How lit a pixel is drawn, from 0 (in shadow) to 1 (fully lit), for a
+    /// `light` level at `frag_coord`, as `light_coverage` in `include/lighting.glsl`.
+    pub(crate) fn coverage(&self, light: f32, frag_coord: (f32, f32), lightmap_scale: f32) -> f32 {
+        let light = light.clamp(0.0, 1.0);
+        match self {
+            LightingMode::Smooth => light,
+            LightingMode::Posterized { bands } => {
+                let steps = (*bands).max(1) as f32;
+                (light * steps - 0.5).ceil() / steps
+            },
+            LightingMode::Dithered(matrix) => {
+                let x = (frag_coord.0.floor() / lightmap_scale) as i32;
+                let y = (frag_coord.1.floor() / lightmap_scale) as i32;
+                if light > matrix.threshold(x, y) { 1.0 } else { 0.0 }
+            },
+        }
+    }
+}
+
+/// The lightmap and buffers of an initialized LightRenderer.
+#[derive(Debug, Clone, Copy)]
+struct LightPasses {
+    lightmap:   TargetHandle,
+    lights:     BufferHandle,
+    volumes:    BufferHandle,
+    /// A quad covering the scene, masked and tinted by the lightmap
+    screen:     BufferHandle,
+}
+
+/// Draws RenderLights to a lightmap, then masks the scene with it.
+///
+/// Lights are added together in an RGB lightmap. The scene is covered by
+/// `shadow_color` where it is unlit, as selected by `mode`, and where it is
+/// lit it is tinted by the hue of the light reaching it, unless `monochrome`
+/// is set.
+///
+/// Lights are blocked by RenderOccluders according to `shadows`, see
+/// `renderer::shadow`.
+///
+/// Drawing goes through a `RenderBackend`, by default OpenGL, whose objects
+/// are freed when the renderer is dropped.
+#[derive(Debug)]
+pub struct LightRenderer<B: RenderBackend = OpenGlBackend> {
+    /// How the lightmap is drawn over the scene
+    pub mode:           LightingMode,
+    /// The size of lightmap pixels and dither cells in screen pixels
     pub dither_scale:   f32,
-    
-    fbo:        GLuint,
-    shaders:    [GLuint; 2],
-    vaos:       [GLuint; 2],
-    ebo:        GLuint,
-    abos:       [GLuint; 2],
-    tex:        GLuint,
-    uniform_locations:   [GLint; 4],
+    /// The color of unlit areas
+    pub shadow_color:   (f32, f32, f32),
+    /// How opaque `shadow_color` is where there is no light, from 0-1
+    pub darkness:       f32,
+    /// Only mask the scene with the lightmap, ignoring the lights' colors
+    pub monochrome:     bool,
+    /// Light reaching everywhere, as RGB from 0-1. Black leaves only the lights
+    pub ambient:        (f32, f32, f32),
+    /// How occluders block the lights
+    pub shadows:        ShadowMode,
+
+    backend:    B,
+    passes:     Option<LightPasses>,
+    /// The current size of the lightmap
+    lightmap_size:  (u32, u32),
+    /// Shadow volume triangles, reused between lights
+    volumes:    Vec<(f32, f32)>,
+}
+impl<B: RenderBackend + Default> Default for LightRenderer<B> {
+    fn default() -> Self {
+        Self::with_backend(B::default())
+    }
 }
 impl LightRenderer {
     pub fn new() -> Self {
         Self::default()
     }
+}
+impl<B: RenderBackend> LightRenderer<B> {
+    /// Creates an empty LightRenderer drawing through `backend`. `init` must
+    /// be called before use.
+    pub fn with_backend(backend: B) -> Self {
+        Self {
+            mode: LightingMode::default(),
+            dither_scale: 1.0,
+            shadow_color: (0.1, 0.1, 0.15),
+            darkness: 0.975,
+            monochrome: false,
+            ambient: (0.0, 0.0, 0.0),
+            shadows: ShadowMode::default(),
 
-    pub fn init(&mut self) -> Result<(), EngineError> {
-        if self.initialized { return Ok(()) }
-
-        // Prevent running this function too early.
-        if !gl::Viewport::is_loaded() {
-            let msg = format!("{}\n{}",
-                "LightRenderer::init called before gl bindings were loaded.",
-                "init() should only be called by the engine."
-            );
-            return Err(EngineError::RendererInit(msg));
+            backend,
+            passes: None,
+            lightmap_size: (0, 0),
+            volumes: Vec::new(),
         }
- 
-        // Build shader programs
-        self.shaders[0] = shader::program_from_sources(
-            include_str!("../../../assets/shaders/lightmap/vert.glsl").into(),
-            include_str!("../../../assets/shaders/lightmap/frag.glsl").into(),
-            Some(include_str!("../../../assets/shaders/lightmap/geom.glsl").into())
-        ).unwrap();
-        self.shaders[1] = shader::program_from_sources(
-            include_str!("../../../assets/shaders/shadowmask/vert.glsl").into(),
-            include_str!("../../../assets/shaders/shadowmask/frag.glsl").into(),
-            None,
-        ).unwrap();  
-        
-        unsafe {
-            // Generate OpenGL objects/buffers
-            gl::GenFramebuffers(1, &mut self.fbo as *mut GLuint);
-            gl::GenVertexArrays(2, &mut self.vaos as *mut GLuint);
-            gl::GenBuffers(2, &mut self.abos as *mut GLuint);
-            gl::GenTextures(1, &mut self.tex as *mut GLuint);
-            
-
-            // ===================== Lightmap =======================
-            gl::UseProgram(self.shaders[0]);
-            gl::BindVertexArray(self.vaos[0]);
-            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo); 
-            gl::BindTexture(gl::TEXTURE_2D, self.tex);
-
-            // Framebuffer
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-            gl::TexImage2D(
-                gl::TEXTURE_2D, 0, gl::RGB as i32, 
-                800, 600, 0, 
-                gl::RGB, gl::UNSIGNED_BYTE, 
-                std::ptr::null()
-            );
-            gl::FramebufferTexture(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, self.tex, 0); 
-            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
-            gl::BindTexture(gl::TEXTURE_2D, 0);
-    
-            // Attribute Pointers
-            // 2d Pos
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.abos[0]);
-            let stride = (size_of::<GLfloat>() * 3) as i32;
-            gl::EnableVertexAttribArray(0);
-            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, stride, 0 as *const GLvoid);
-            
-            // Intensity
-            let intensity_offset = (size_of::<GLfloat>() * 2) as i32;
-            gl::EnableVertexAttribArray(1);
-            gl::VertexAttribPointer(1, 1, gl::FLOAT, gl::FALSE, 
-                                    stride, intensity_offset as *const GLvoid); 
-            
-            // Unbinding
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-            gl::BindVertexArray(0);
-            gl::UseProgram(0);
-
-            
-            // ====================== Shadowmask =======================
-            gl::GenBuffers(1, &mut self.ebo as *mut GLuint);
-            
-            gl::UseProgram(self.shaders[1]);
-            
-            gl::BindVertexArray(self.vaos[1]);
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.abos[1]);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, self.ebo);
-          
-            // Pos
-            gl::EnableVertexAttribArray(0);
-            gl::VertexAttribPointer(0, 2, gl::FLOAT, gl::FALSE, 16, 0 as *const GLvoid);
-            // UV
-            gl::EnableVertexAttribArray(1);
-            gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, 16, 8 as *const GLvoid);
-
-            let screen_quad: [f32; 16] = [
-                 1.0,  1.0, 1.0, 1.0,
-                 1.0, -1.0, 1.0, 0.0,
-                -1.0, -1.0, 0.0, 0.0,
-                -1.0,  1.0, 0.0, 1.0,
-            ];
-            let scr_quad_ind: [GLuint; 6] = [
-                0, 1, 3,
-                1, 2, 3,
-            ];
-
-            gl::BufferData(
-                gl::ARRAY_BUFFER,  
-                (size_of::<f32>() * screen_quad.len()) as GLsizeiptr,
-                screen_quad.as_ptr() as *const GLvoid,
-                gl::STATIC_DRAW
-            );
-            gl::BufferData(
-                gl::ELEMENT_ARRAY_BUFFER,
-                (size_of::<GLuint>() * scr_quad_ind.len()) as GLsizeiptr,
-                scr_quad_ind.as_ptr() as *const GLvoid,
-                gl::STATIC_DRAW
-            );
-
-            self.uniform_locations[0] = shader::get_uniform_location(
-                self.shaders[0], "projection");
-            self.uniform_locations[1] = shader::get_uniform_location(
-                self.shaders[0], "view");
-            self.uniform_locations[2] = shader::get_uniform_location(
-                self.shaders[0], "px_scale");
-            self.uniform_locations[3] = shader::get_uniform_location(
-                self.shaders[1], "lightmap_scale");
-
-            gl::BindVertexArray(0);
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-            gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, 0);
-            gl::UseProgram(0);
+    }
 
-        }
+    pub fn backend(&self) -> &B { &self.backend }
+    pub fn backend_mut(&mut self) -> &mut B { &mut self.backend }
+
+    /// Prepares the backend's lighting pipelines and the lightmap, sized for
+    /// `window_size`.
+    ///
+    /// With the OpenGlBackend this can _only_ be called after the OpenGL
+    /// bindings have been loaded.
+    pub fn init(&mut self, window_size: (f32, f32)) -> Result<(), EngineError> {
+        if self.passes.is_some() { return Ok(()) }
+
+        self.backend.init(&[
+            PipelineKind::Lights,
+            PipelineKind::ShadowVolumes,
+            PipelineKind::ShadowMask,
+            PipelineKind::LightTint,
+        ])?;
+
+        self.lightmap_size = self.scaled_size(window_size);
+        let lightmap = self.backend.create_target(self.lightmap_size.0, self.lightmap_size.1)?;
+        let passes = LightPasses {
+            lightmap,
+            lights: self.backend.create_buffer(),
+            volumes: self.backend.create_buffer(),
+            screen: self.backend.create_buffer(),
+        };
 
-        self.initialized = true;
+        // Drawn in clip space, just in front of the far side of the scene, so
+        // that anything drawn in front of the lighting, e.g. a cursor at z=1,
+        // stays unshadowed
+        let screen = Quad::rect((-1.0, -1.0), (1.0, 1.0), -0.001,
+                                (0.0, 1.0), (1.0, 0.0), (1.0, 1.0, 1.0, 1.0));
+        self.backend.stream_quads(passes.screen, &[screen]);
+
+        self.backend.label_target(lightmap, "lightmap");
+        self.backend.label_buffer(passes.lights, "lights");
+        self.backend.label_buffer(passes.volumes, "shadow volumes");
+        self.backend.label_buffer(passes.screen, "shadow mask quad");
+
+        self.passes = Some(passes);
         Ok(())
+    }
+
+    /// `dither_scale`, or 1 if it is not positive.
+    fn lightmap_scale(&self) -> f32 {
+        if self.dither_scale > 0.0 { self.dither_scale } else { 1.0 }
+    }
 
+    /// The size of the lightmap for a window, one pixel per `lightmap_scale`.
+    fn scaled_size(&self, window_size: (f32, f32)) -> (u32, u32) {
+        let scale = self.lightmap_scale();
+        (((window_size.0 / scale) as u32).max(1), ((window_size.1 / scale) as u32).max(1))
     }
 
-    pub fn render(&self, lights: &[RenderLight], window_size: (f32, f32), cam: (f32, f32, f32)) {
-        let (winx, winy) = window_size;
-        let (s_winx, s_winy) = (window_size.0 / self.dither_scale, 
-                                window_size.1 / self.dither_scale);
-
-        let scaled_projection = glm::ortho(0.0, s_winx, 0.0, s_winy, -25.0, 25.0);
-        let mut view_mat: glm::Mat4 = glm::identity();
-        view_mat = glm::translate(&view_mat, &glm::vec3(-cam.0, -cam.1, -cam.2));
-
-        // ============== Render lightmap to framebuffer =============
-        unsafe {
-            gl::Enable(gl::BLEND);
-            gl::Enable(gl::DEPTH_TEST);
-            
-            gl::UseProgram(self.shaders[0]);
-            gl::BindVertexArray(self.vaos[0]);
-            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
-            gl::BindTexture(gl::TEXTURE_2D, self.tex);
-            gl::TexImage2D(
-                gl::TEXTURE_2D, 0, gl::RGB as i32, 
-                s_winx as i32, s_winy as i32, 0, 
-                gl::RGB, gl::UNSIGNED_BYTE, 
-                std::ptr::null()
-            );
-            gl::FramebufferTexture(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, self.tex, 0);  
-            
-            // Black the framebuffer
-            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT);
-
-            // Enable additive blending ( for light blending )
-            gl::BlendFunc(gl::ONE, gl::ONE);
-            
-            gl::Viewport(0, 0, s_winx as i32, s_winy as i32);
-            // projection
-            gl::UniformMatrix4fv(self.uniform_locations[0], 1, gl::FALSE, 
-                                 scaled_projection.as_ptr());
-            // view
-            gl::UniformMatrix4fv(self.uniform_locations[1], 1, gl::FALSE, 
-                                view_mat.as_ptr());
-    
-            // px_scale
-            gl::Uniform1f(self.uniform_locations[2], self.dither_scale);
-
-            // Load point light data
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.abos[0]);
-            gl::BufferData(
-                gl::ARRAY_BUFFER, 
-                (size_of::<RenderLight>() * lights.len()) as GLsizeiptr,
-                lights.as_ptr() as *const GLvoid,
-                gl::DYNAMIC_DRAW
-            );
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-            
-            // Render lightmap to texture
-            gl::DrawArrays(gl::POINTS, 0, lights.len() as i32);
-            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
-            gl::DrawArrays(gl::POINTS, 0, lights.len() as i32);
-            // Reset the blend function to normal
-            gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE);
-            gl::BlendEquation(gl::FUNC_ADD);
-
-            
-            // Revert to using the screen's framebuffer
-            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
-            
-            // ========= Render the shadow mask =============
-            // This renders the lightmap on a quad, run through
-            // a frag shader that discards specific fragments.
-            // The fragments to discard are selected using bayesian
-            // dithering.
-            // ----------------------------------------------
-            gl::UseProgram(self.shaders[1]);
-            gl::BindVertexArray(self.vaos[1]);
-            gl::Viewport(0, 0, winx as i32, winy as i32);
-            
-            // lightmap_scale
-            gl::Uniform1f(self.uniform_locations[3], self.dither_scale);
-            gl::DrawElements(gl::TRIANGLES, 6, gl::UNSIGNED_INT, 0 as *const GLvoid);
-
-            gl::BindTexture(gl::TEXTURE_2D, 0);
-            gl::BindVertexArray(0);
-            gl::UseProgram(0);
+    /// Draws one light, lighting only what its shadow volumes leave unmarked
+    /// in the stencil buffer.
+    fn draw_shadowed_light(&mut self, passes: LightPasses, light: RenderLight,
+                           occluders: &[RenderOccluder], gain: f32, view_projection: glm::Mat4) {
+        self.volumes.clear();
+        shadow::shadow_volumes(light.pos, light.intensity, occluders, &mut self.volumes);
+
+        // Mark the shadowed area in the stencil, without touching the color
+        self.backend.clear_stencil();
+        if !self.volumes.is_empty() {
+            self.backend.stream(passes.volumes, VertexData::Triangles(&self.volumes));
+            self.backend.draw(&DrawCall::new(Pipeline::ShadowVolumes, passes.volumes,
+                                             self.volumes.len(), view_projection)
+                .depth_test(false)
+                .stencil(StencilTest::Mark));
         }
+
+        // Light everything left unmarked
+        self.backend.stream(passes.lights, VertexData::Lights(&[light]));
+        self.backend.draw(&DrawCall::new(Pipeline::Lights { gain }, passes.lights, 1, view_projection)
+            .blend(BlendMode::Additive)
+            .depth_test(false)
+            .stencil(StencilTest::Unmarked));
     }
-}
 
+    /// Draws the lights to the lightmap, blocked by `occluders`, then masks the
+    /// scene with it.
+    pub fn render(&mut self, lights: &[RenderLight], occluders: &[RenderOccluder],
+                  window_size: (f32, f32), cam: &Camera) {
+        let passes = match self.passes {
+            Some(passes) => passes,
+            None => return,
+        };
+        self.backend.resize_screen(window_size.0 as u32, window_size.1 as u32);
+        let size = self.scaled_size(window_size);
+        if size != self.lightmap_size {
+            self.lightmap_size = size;
+            self.backend.resize_target(passes.lightmap, size.0, size.1);
+        }
+
+        // The lightmap covers the same world area as the screen, only at a lower
+        // resolution, so it shares the screen's view-projection.
+        let view_projection = cam.view_projection(window_size);
+
+        // Lights that no occluder can reach are drawn together, the rest
+        // one sample at a time with their shadows.
+        let samples = self.shadows.sample_offsets();
+        let spread = self.shadows.spread();
+        let (shadowed, open): (Vec<RenderLight>, Vec<RenderLight>) = lights.iter().copied()
+            .partition(|light| {
+                self.shadows != ShadowMode::Off && occluders.iter()
+                    .any(|o| o.within(light.pos, light.intensity + spread))
+            });
+
+        // ============== Render lightmap to its target =============
+        // Starting from the ambient light, black without any, lights are
+        // added together. The lightmap's depth is unused, only its stencil.
+        self.backend.bind_target(Some(passes.lightmap));
+        let (r, g, b) = self.ambient;
+        self.backend.clear((r.clamp(0.0, 1.0), g.clamp(0.0, 1.0), b.clamp(0.0, 1.0), 1.0));
+
+        self.backend.stream(passes.lights, VertexData::Lights(&open));
+        self.backend.draw(&DrawCall::new(Pipeline::Lights { gain: LIGHT_GAIN }, passes.lights,
+                                         open.len(), view_projection)
+            .blend(BlendMode::Additive)
+            .depth_test(false));
+
+        if !shadowed.is_empty() {
+            // Samples of a soft light split its brightness
+            let gain = LIGHT_GAIN / samples.len() as f32;
+            for light in &shadowed {
+                for offset in &samples {
+                    let sample = RenderLight {
+                        pos: (light.pos.0 + offset.0, light.pos.1 + offset.1),
+                        ..*light
+                    };
+                    self.draw_shadowed_light(passes, sample, occluders, gain, view_projection);
+                }
+            }
+        }
+
+        // Revert to drawing the scene
+        self.backend.bind_target(None);
+
+        // ========= Render the shadow mask =============
+        // This renders the lightmap on a quad, run through
+        // a frag shader that covers the unlit fragments with
+        // the shadow color. By default the fragments to cover
+        // are selected using bayesian dithering, see LightingMode.
+        // ----------------------------------------------
+        let lightmap = self.backend.target_texture(passes.lightmap);
+
+        // Smooth lighting is filtered so the lightmap's pixels don't show
+        let filter = match self.mode {
+            LightingMode::Smooth => Filter::Linear,
+            _ => Filter::Nearest,
+        };
+        self.backend.set_texture_filter(lightmap, filter);
+
+        let (mode, lightmap_scale) = (self.mode, self.lightmap_scale());
+        let mask = Pipeline::ShadowMask {
+            mode, lightmap_scale,
+            shadow_color: self.shadow_color,
+            darkness: self.darkness,
+        };
+        self.backend.draw(&DrawCall::new(mask, passes.screen, 1, glm::Mat4::identity())
+            .texture(lightmap));
+
+        // ========= Tint the lit fragments =============
+        // Multiplies the fragments the shadow mask left alone by the
+        // hue of the lightmap, keeping the scene's alpha.
+        // ----------------------------------------------
+        if !self.monochrome {
+            let tint = Pipeline::LightTint { mode, lightmap_scale };
+            self.backend.draw(&DrawCall::new(tint, passes.screen, 1, glm::Mat4::identity())
+                .texture(lightmap)
+                .blend(BlendMode::Multiply));
+        }
+    }
+}
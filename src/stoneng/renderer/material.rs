@@ -0,0 +1,119 @@
+#![allow(dead_code)]
+
+use crate::EngineError;
+use crate::log;
+use crate::shader::{self, Preprocessor, ShaderProgram};
+use crate::renderer::sprite::{RenderSprite, SPRITE_LAYOUT};
+use crate::renderer::QuadPath;
+
+use std::{mem::size_of, sync::Arc};
+use gl::types::*;
+
+/// Shader effects shipped with the engine, usable as a starting point for materials.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuiltinMaterial {
+    /// Paints the sprite with `flash_color` by `flash` (0-1).
+    HitFlash,
+    /// Discards pixels by noise as `dissolve` goes from 0 to 1, tinting
+    /// the edge by `edge_color` over `edge_width`.
+    Dissolve,
+    /// Draws `outline_color` around the opaque pixels of the sprite.
+    Outline,
+    /// Ripples the sprite by `amplitude` pixels at `speed` and `frequency`.
+    Water,
+}
+
+/// A shader program used to draw sprites with a `Material`.
+///
+/// Material shaders receive the same vertex data as the default sprite shader.
+/// Usually only the fragment shader is replaced, in which case it must accept:
+/// ```glsl
+/// in GS_OUT {
+///     vec2 tex_coord;
+///     vec4 color_adj;
+///     vec4 uv_bounds;
+/// } gs_out;
+/// ```
+/// The uniforms `spritesheet_tex`, `palette_tex`, `sheet_width`, `sheet_tile_w`,
+/// `view_projection` and `time` (seconds) are provided by the renderer. Palette
+/// lookups are only done if the shader reads `flat in uint sprite_flags` and
+/// `flat in uint palette_row` itself, as the default sprite shader does.
+///
+/// The block keeps its `GS_OUT` name when sprites are drawn instanced, with no
+/// geometry shader, so fragment shaders work with either `QuadPath`.
+#[derive(Debug)]
+pub struct MaterialShader {
+    program:    ShaderProgram,
+    path:       QuadPath,
+}
+
+impl MaterialShader {
+    /// Builds a material from a fragment shader, using the sprite shaders of
+    /// the context's `QuadPath`.
+    ///
+    /// This can _only_ be called after the OpenGL bindings have been loaded.
+    pub fn from_fragment(frag_source: String) -> Result<Arc<Self>, EngineError> {
+        Self::from_named_fragment("material", &frag_source)
+    }
+
+    /// Builds a material from a fragment shader, naming it in compile errors.
+    fn from_named_fragment(name: &str, frag_source: &str) -> Result<Arc<Self>, EngineError> {
+        let path = QuadPath::detect();
+        let builtin = |path: &'static str| (path, shader::builtin_source(path).unwrap_or_default());
+        let geom = match path {
+            QuadPath::Geometry => Some(builtin("sprite/geom.glsl")),
+            QuadPath::Instanced => None,
+        };
+        let program = path.preprocessor()
+            .program_from_sources(builtin("sprite/vert.glsl"), (name, frag_source), geom)?;
+        Ok(Arc::new(Self { program, path }))
+    }
+
+    /// Builds a material from a complete set of shader sources.
+    ///
+    /// The vertex shader must accept the `RenderSprite` attribute layout. With a
+    /// geometry shader, sprites are passed as points. Without one they are
+    /// drawn instanced, as a 4 vertex triangle strip per sprite, as
+    /// `sprite/vert.glsl` does with `INSTANCED` defined.
+    pub fn from_sources(vert_source: String, frag_source: String, geom_source: Option<String>)
+            -> Result<Arc<Self>, EngineError> {
+        let path = match geom_source {
+            Some(_) => QuadPath::Geometry,
+            None => QuadPath::Instanced,
+        };
+        let program = Preprocessor::new().program_from_sources(
+            ("material vertex shader", &vert_source),
+            ("material", &frag_source),
+            geom_source.as_deref().map(|source| ("material geometry shader", source)),
+        )?;
+        if cfg!(debug_assertions) {
+            if let Err(err) = program.check_layout(&SPRITE_LAYOUT, size_of::<RenderSprite>()) {
+                log::warn("material", err);
+            }
+        }
+        Ok(Arc::new(Self { program, path }))
+    }
+
+    /// Builds one of the engine's built-in materials.
+    pub fn builtin(material: BuiltinMaterial) -> Result<Arc<Self>, EngineError> {
+        let path = match material {
+            BuiltinMaterial::HitFlash => "material/flash.glsl",
+            BuiltinMaterial::Dissolve => "material/dissolve.glsl",
+            BuiltinMaterial::Outline  => "material/outline.glsl",
+            BuiltinMaterial::Water    => "material/water.glsl",
+        };
+        Self::from_named_fragment(path, shader::builtin_source(path).unwrap_or_default())
+    }
+
+    /// Takes ownership of an already linked program, drawn with the given path.
+    /// The program is deleted when the MaterialShader is dropped.
+    pub fn from_program(program: GLuint, path: QuadPath) -> Self {
+        Self { program: ShaderProgram::from_id(program), path }
+    }
+
+    pub fn program(&self) -> GLuint { self.program.id() }
+    pub fn path(&self) -> QuadPath { self.path }
+
+    /// The program along with its uniforms.
+    pub fn shader_program(&self) -> &ShaderProgram { &self.program }
+}
@@ -1,3 +1,102 @@
 pub mod sprite;
 pub mod light;
+pub mod shadow;
 pub mod text;
+pub mod target;
+pub mod upscale;
+pub mod post;
+pub mod material;
+pub mod nine_slice;
+pub mod debug;
+pub mod backend;
+pub mod context;
+pub mod gpu;
+pub mod gl_debug;
+pub mod texture;
+
+use glm::{Mat4, Vec3};
+use crate::ecs::resource::View;
+
+use context::GlVersion;
+use crate::shader::Preprocessor;
+
+/// How renderers grow each sprite, glyph or light into a quad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuadPath {
+    /// Points are expanded into quads by a geometry shader.
+    #[default]
+    Geometry,
+    /// A unit quad is drawn once per instance, reading the sprite, glyph or
+    /// light from per-instance attributes. Needs no geometry shader, so it also
+    /// suits GLES and drivers where geometry shaders are slow.
+    Instanced,
+}
+impl QuadPath {
+    /// Picks the path for the current context, preferring instancing where it
+    /// is supported.
+    ///
+    /// Setting `STONENG_QUAD_PATH` to `geometry` forces geometry shaders where
+    /// the context supports them.
+    ///
+    /// This can _only_ be called after the OpenGL bindings have been loaded.
+    pub fn detect() -> Self {
+        let version = GlVersion::current();
+        let geometry = version.map(|v| v.supports_geometry_shaders()).unwrap_or(true);
+        let instancing = version.map(|v| v.supports_instancing()).unwrap_or(false)
+            && gl::VertexAttribDivisor::is_loaded()
+            && gl::DrawArraysInstanced::is_loaded();
+
+        match std::env::var("STONENG_QUAD_PATH").as_deref() {
+            Ok("geometry") if geometry => QuadPath::Geometry,
+            _ if instancing => QuadPath::Instanced,
+            _ => QuadPath::Geometry,
+        }
+    }
+
+    /// A `Preprocessor` for this path's variant of the engine's shaders, which
+    /// draw instanced where `INSTANCED` is defined.
+    pub fn preprocessor(&self) -> Preprocessor {
+        match self {
+            QuadPath::Geometry => Preprocessor::new(),
+            QuadPath::Instanced => Preprocessor::new().define("INSTANCED", "1"),
+        }
+    }
+}
+
+/// The camera transform used to draw a frame.
+///
+/// This is the logical `View` with any camera effects applied to it.
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    /// World position of the bottom-left corner of the screen
+    pub pos:    (f32, f32, f32),
+    /// Magnification applied around the center of the screen
+    pub zoom:   f32,
+}
+impl Default for Camera {
+    fn default() -> Self {
+        Self { pos: (0.0, 0.0, 0.0), zoom: 1.0 }
+    }
+}
+impl From<&View> for Camera {
+    fn from(view: &View) -> Self {
+        Self { pos: (view.0, view.1, view.2), zoom: 1.0 }
+    }
+}
+impl Camera {
+    /// Builds the view matrix, zooming around the center of the screen.
+    pub fn view(&self, window_size: (f32, f32)) -> Mat4 {
+        let center = Vec3::new(window_size.0 / 2.0, window_size.1 / 2.0, 0.0);
+        let translation = glm::translation(&Vec3::new(-self.pos.0, -self.pos.1, -self.pos.2));
+        let zoom = glm::translation(&center)
+            * glm::scaling(&Vec3::new(self.zoom, self.zoom, 1.0))
+            * glm::translation(&-center);
+        zoom * translation
+    }
+
+    /// Builds the orthographic view-projection for a window of `window_size` pixels.
+    pub fn view_projection(&self, window_size: (f32, f32)) -> Mat4 {
+        let projection = glm::ortho(0.0, window_size.0, 0.0, window_size.1, -25.0, 25.0);
+        projection * self.view(window_size)
+    }
+}
@@ -0,0 +1,201 @@
+#![allow(dead_code)]
+
+use crate::EngineError;
+use crate::shader::VertexAttribute;
+use crate::renderer::{Camera, text::VectorSpace, sprite::{SheetLayout, TILE_WIDTH}};
+use crate::renderer::texture::{self, TextureOptions};
+use crate::renderer::backend::{
+    RenderBackend, OpenGlBackend, PipelineKind, Pipeline, DrawCall, VertexData,
+    Quad, QuadVertex, TextureHandle, BufferHandle,
+};
+
+use std::mem::offset_of;
+
+/// A nine-slice sprite stretched over a rectangle. Used directly for rendering.
+#[repr(C)]
+#[derive(Debug, Clone)]
+pub struct RenderPanel {
+    /// The bottom-left corner of the panel
+    pub translation:    (f32, f32, f32),
+    pub size:           (f32, f32),
+    pub color:          (f32, f32, f32, f32),
+    pub sprite_id:      u32,
+    pub sprite_dims:    u8,
+    pub sprite_flags:   u8,
+    pub reserved:       u16,
+    /// Border insets in pixels as left, right, top, bottom
+    pub insets:         [u8; 4],
+}
+/// The attributes of a RenderPanel, as read by `nine_slice/vert.glsl`.
+pub const PANEL_LAYOUT: [VertexAttribute; 6] = [
+    VertexAttribute::new(0, 3, gl::FLOAT, offset_of!(RenderPanel, translation)),
+    VertexAttribute::new(1, 2, gl::FLOAT, offset_of!(RenderPanel, size)),
+    VertexAttribute::new(2, 4, gl::FLOAT, offset_of!(RenderPanel, color)),
+    VertexAttribute::new(3, 1, gl::UNSIGNED_INT, offset_of!(RenderPanel, sprite_id)),
+    VertexAttribute::new(4, 1, gl::UNSIGNED_INT, offset_of!(RenderPanel, sprite_dims)),
+    VertexAttribute::new(5, 1, gl::UNSIGNED_INT, offset_of!(RenderPanel, insets)),
+];
+
+impl Default for RenderPanel {
+    fn default() -> Self {
+        Self {
+            translation: (0.0, 0.0, 0.0),
+            size:        (10.0, 10.0),
+            color:       (1.0, 1.0, 1.0, 1.0),
+
+            sprite_id:    0,
+            sprite_dims:  0,
+            sprite_flags: 0,
+            reserved:     0,
+            insets:       [0; 4],
+        }
+    }
+}
+
+/// The slices of a panel, drawn as one instance each on the instanced path.
+pub(crate) const PANEL_SLICES: u32 = 9;
+
+/// Builds the quads of the slices the nine-slice shaders would emit, for
+/// drawing panels through a RenderBackend. Slices with no area are left out.
+///
+/// As in `include/nine_slice.glsl`, uvs are in units of the sheet's width.
+pub fn panel_quads(panels: &[RenderPanel], sheet: SheetLayout) -> Vec<Quad> {
+    let tile = sheet.tile_width as f32;
+    let (pitch, border) = (sheet.pitch() as f32, sheet.border as f32);
+    let per_row = sheet.per_row();
+    let sheet_width = sheet.width.max(1) as f32;
+
+    let mut quads = Vec::with_capacity(panels.len() * PANEL_SLICES as usize);
+    for p in panels {
+        let dims = ((p.sprite_dims & 0xF) as f32 + 1.0, (p.sprite_dims >> 4) as f32 + 1.0);
+        let (col, row) = ((p.sprite_id % per_row) as f32, (p.sprite_id / per_row) as f32);
+        let left = col * pitch + border;
+        let bottom = row * pitch + border + tile;
+        let (right, top) = (left + dims.0 * tile, bottom - dims.1 * tile);
+
+        // Shrink the borders evenly if the panel is smaller than them
+        let [il, ir, it, ib] = p.insets.map(|i| i as f32);
+        let shrink = ((p.size.0 / (il + ir).max(1.0)).min(1.0),
+                      (p.size.1 / (it + ib).max(1.0)).min(1.0));
+
+        // Grid lines of the slices, from the bottom-left, and in the atlas
+        let xs = [0.0, il * shrink.0, p.size.0 - ir * shrink.0, p.size.0];
+        let ys = [0.0, ib * shrink.1, p.size.1 - it * shrink.1, p.size.1];
+        let us = [left, left + il, right - ir, right];
+        let vs = [bottom, bottom - ib, top + it, top];
+
+        let (x, y, z) = p.translation;
+        for slice in 0..PANEL_SLICES as usize {
+            let (sc, sr) = (slice % 3, slice / 3);
+            if xs[sc + 1] <= xs[sc] || ys[sr + 1] <= ys[sr] { continue; }
+            // Triangle strip order: bottom-left, top-left, bottom-right, top-right
+            let verts = [(0, 0), (0, 1), (1, 0), (1, 1)].map(|(cx, cy)| QuadVertex {
+                pos: (x + xs[sc + cx], y + ys[sr + cy], z),
+                uv: (us[sc + cx] / sheet_width, vs[sr + cy] / sheet_width),
+                color: p.color,
+            });
+            quads.push(Quad { verts });
+        }
+    }
+    quads
+}
+
+/// The NineSliceRenderer draws RenderPanels, sprites that are resized by
+/// stretching their edges and center while keeping their corners intact.
+///
+/// It uses the same atlas layout as the SpriteRenderer. Panels can be drawn in
+/// world space, following the camera, or screen space, in pixels of the render
+/// target from its bottom-left corner. With OpenGL each panel is grown into
+/// its slices on the GPU, by the method picked in `QuadPath::detect`.
+///
+/// Drawing goes through a `RenderBackend`, by default OpenGL, in which case the
+/// renderer must only be used _after_ the OpenGL bindings have been loaded and
+/// only on the main thread. The backend's objects are freed when the renderer
+/// is dropped.
+#[derive(Debug)]
+pub struct NineSliceRenderer<B: RenderBackend = OpenGlBackend> {
+    backend:    B,
+    panels:     Option<BufferHandle>,
+    tex:        Option<TextureHandle>,
+    tex_size:   (u32, u32),
+
+    /// How the atlas is loaded by `init`
+    pub atlas_options: TextureOptions,
+}
+impl<B: RenderBackend + Default> Default for NineSliceRenderer<B> {
+    fn default() -> Self {
+        Self::with_backend(B::default())
+    }
+}
+
+impl NineSliceRenderer {
+    /// Creates an empty NineSliceRenderer. `init` must be called before use.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<B: RenderBackend> NineSliceRenderer<B> {
+    /// Creates an empty NineSliceRenderer drawing through `backend`. `init`
+    /// must be called before use.
+    pub fn with_backend(backend: B) -> Self {
+        Self {
+            backend,
+            panels: None,
+            tex: None,
+            tex_size: (0, 0),
+            atlas_options: TextureOptions::default(),
+        }
+    }
+
+    pub fn backend(&self) -> &B { &self.backend }
+    pub fn backend_mut(&mut self) -> &mut B { &mut self.backend }
+
+    /// Loads the atlas and prepares the backend for drawing panels.
+    ///
+    /// With the OpenGlBackend this can _only_ be called after the OpenGL
+    /// bindings have been loaded.
+    pub fn init(&mut self, atlas: &[u8]) -> Result<(), EngineError> {
+        if self.panels.is_some() { return Ok(()) }
+
+        self.backend.init(&[PipelineKind::Panels])?;
+
+        let (tex, size) = texture::load_into(&mut self.backend, atlas, self.atlas_options)?;
+        let panels = self.backend.create_buffer();
+        self.backend.label_texture(tex, "panel atlas");
+        self.backend.label_buffer(panels, "panels");
+
+        self.tex = Some(tex);
+        self.tex_size = size;
+        self.panels = Some(panels);
+        Ok(())
+    }
+
+    /// Draws a set of RenderPanels in the given space.
+    pub fn render(&mut self, panels: &[RenderPanel], window_size: (f32, f32),
+                  cam: &Camera, space: VectorSpace) {
+        let (buffer, tex) = match (self.panels, self.tex) {
+            (Some(buffer), Some(tex)) => (buffer, tex),
+            _ => return,
+        };
+        if panels.is_empty() { return; }
+        self.backend.resize_screen(window_size.0 as u32, window_size.1 as u32);
+
+        // Screen space panels ignore the camera
+        let view_projection = match space {
+            VectorSpace::World => cam.view_projection(window_size),
+            VectorSpace::Screen => Camera::default().view_projection(window_size),
+        };
+        let pipeline = Pipeline::Panels {
+            sheet: SheetLayout {
+                width: self.tex_size.0,
+                tile_width: TILE_WIDTH,
+                border: self.atlas_options.border(),
+            },
+        };
+        self.backend.stream(buffer, VertexData::Panels(panels));
+        self.backend.draw(&DrawCall::new(pipeline, buffer, panels.len(), view_projection)
+            .texture(tex)
+            .blend(self.atlas_options.blend_mode()));
+    }
+}
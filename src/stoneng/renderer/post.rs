@@ -0,0 +1,317 @@
+#![allow(dead_code)]
+
+use crate::EngineError;
+use crate::log;
+use crate::shader::UniformValue;
+use crate::renderer::target::RenderTarget;
+use crate::renderer::texture::{self, TextureOptions};
+use crate::renderer::backend::{
+    RenderBackend, PipelineKind, Pipeline, DrawCall, BlendMode, Filter, Quad, TextureDesc,
+    TextureHandle, BufferHandle, TargetHandle, ShaderHandle,
+};
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, atomic::{AtomicU64, Ordering}},
+};
+
+static NEXT_LUT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A color lookup table used by `PostEffect::ColorGrade`.
+///
+/// LUTs are stored as a horizontal strip of `size` square slices, each
+/// `size` pixels wide. Blue selects the slice, red increases along x and green
+/// along y. This is the layout most image editors export for 2D LUTs.
+#[derive(Debug)]
+pub struct Lut {
+    id:         u64,
+    pub size:   u32,
+    data:       Vec<u8>,
+}
+impl Lut {
+    /// Loads a strip LUT from a PNG that is `size * size` wide and `size` tall.
+    pub fn from_png(bytes: &[u8]) -> Result<Self, EngineError> {
+        let img = texture::decode(bytes).map_err(|err| {
+            EngineError::RendererInit(format!("Failed to load color grading LUT.\n{}", err))
+        })?;
+        if img.width != img.height * img.height {
+            return Err(EngineError::RendererInit(
+                format!("LUT must be size^2 x size pixels, found {}x{}", img.width, img.height)
+            ));
+        }
+
+        Ok(Self {
+            id: NEXT_LUT_ID.fetch_add(1, Ordering::Relaxed),
+            size: img.height,
+            data: img.data,
+        })
+    }
+
+    /// Builds a LUT that leaves colors unchanged, useful as a starting point.
+    pub fn identity(size: u32) -> Self {
+        let size = size.max(2);
+        let max = (size - 1) as f32;
+        let mut data = Vec::with_capacity((size * size * size * 4) as usize);
+        for g in 0..size {
+            for b in 0..size {
+                for r in 0..size {
+                    data.push((r as f32 / max * 255.0).round() as u8);
+                    data.push((g as f32 / max * 255.0).round() as u8);
+                    data.push((b as f32 / max * 255.0).round() as u8);
+                    data.push(255);
+                }
+            }
+        }
+        Self { id: NEXT_LUT_ID.fetch_add(1, Ordering::Relaxed), size, data }
+    }
+}
+
+/// A full-screen effect and its parameters.
+#[derive(Debug, Clone)]
+pub enum PostEffect {
+    /// Darkens the edges of the screen towards `color`.
+    Vignette { strength: f32, radius: f32, softness: f32, color: (f32, f32, f32) },
+    /// Adds a glow around pixels brighter than `threshold` (0-1 luminance).
+    ///
+    /// Luminance alone misses dim or saturated glowing colors, e.g. a pure red
+    /// is only 0.21. Where `emissive` is set, pixels also glow by the alpha of
+    /// that texture, typically a `RenderTarget` of the same backend that the
+    /// glowing sprites are drawn to as well as the scene.
+    Bloom { threshold: f32, intensity: f32, radius: f32, emissive: Option<TextureHandle> },
+    /// Remaps colors through a lookup table.
+    ColorGrade { lut: Arc<Lut>, strength: f32 },
+    /// Splits the red and blue channels apart towards the screen edges.
+    ChromaticAberration { offset: f32 },
+    /// Darkens the gaps between rows and curves the screen like a CRT.
+    Scanlines { intensity: f32, curvature: f32, flicker: f32 },
+    /// A user defined fragment shader.
+    ///
+    /// The shader receives `in vec2 uv_pos` and the uniforms `source` (sampler2D),
+    /// `resolution` (vec2) and `time` (float), alongside `params`.
+    Custom { frag_source: Arc<String>, params: Vec<(String, UniformValue)> },
+}
+impl PostEffect {
+    pub fn vignette() -> Self {
+        Self::Vignette { strength: 0.6, radius: 0.45, softness: 0.4, color: (0.0, 0.0, 0.0) }
+    }
+    pub fn bloom() -> Self {
+        Self::Bloom { threshold: 0.7, intensity: 0.8, radius: 6.0, emissive: None }
+    }
+    pub fn color_grade(lut: Arc<Lut>) -> Self {
+        Self::ColorGrade { lut, strength: 1.0 }
+    }
+    pub fn chromatic_aberration() -> Self {
+        Self::ChromaticAberration { offset: 1.0 }
+    }
+    pub fn scanlines() -> Self {
+        Self::Scanlines { intensity: 0.35, curvature: 0.03, flicker: 0.05 }
+    }
+
+    /// The engine's fragment shader for the effect, one of `BUILTIN_EFFECTS`,
+    /// or `None` for a custom effect.
+    pub(crate) fn builtin_shader(&self) -> Option<&'static str> {
+        match self {
+            PostEffect::Vignette { .. }             => Some("post/vignette.glsl"),
+            PostEffect::Bloom { .. }                => Some("post/bloom.glsl"),
+            PostEffect::ColorGrade { .. }           => Some("post/color_grade.glsl"),
+            PostEffect::ChromaticAberration { .. }  => Some("post/chromatic.glsl"),
+            PostEffect::Scanlines { .. }            => Some("post/scanlines.glsl"),
+            PostEffect::Custom { .. }               => None,
+        }
+    }
+}
+
+/// The fragment shaders of the built-in effects, under `assets/shaders`.
+pub(crate) const BUILTIN_EFFECTS: [&str; 5] = [
+    "post/vignette.glsl",
+    "post/bloom.glsl",
+    "post/color_grade.glsl",
+    "post/chromatic.glsl",
+    "post/scanlines.glsl",
+];
+
+/// A named, toggleable entry in the post-processing chain.
+#[derive(Debug, Clone)]
+pub struct PostPass {
+    pub name:       String,
+    pub enabled:    bool,
+    pub effect:     PostEffect,
+}
+impl PostPass {
+    pub fn new(name: &str, effect: PostEffect) -> Self {
+        Self { name: name.into(), enabled: true, effect }
+    }
+}
+
+/// The intermediate targets and quad of an initialized PostProcessRenderer.
+#[derive(Debug, Clone, Copy)]
+struct PostPasses {
+    targets:    [TargetHandle; 2],
+    /// A quad covering the whole target in clip space
+    quad:       BufferHandle,
+}
+
+/// Runs an ordered chain of full-screen passes over a render target.
+///
+/// Passes ping-pong between two internal targets at the source's resolution,
+/// with the final result copied back into the source.
+///
+/// Drawing goes through the backend of the target, which owns the renderer's
+/// objects and frees them when it is dropped.
+#[derive(Default, Debug)]
+pub struct PostProcessRenderer {
+    passes:     Option<PostPasses>,
+    /// The current size of the intermediate targets
+    size:       (u32, u32),
+    /// Compiled custom shaders, `None` for those that failed to compile
+    custom:     HashMap<Arc<String>, Option<ShaderHandle>>,
+    /// Uploaded LUTs, by `Lut` id
+    luts:       HashMap<u64, TextureHandle>,
+}
+
+impl PostProcessRenderer {
+    /// Creates an empty PostProcessRenderer. `init` must be called before use.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prepares the target's backend to draw the built-in effects, and creates
+    /// the intermediate targets.
+    ///
+    /// With the OpenGlBackend this can _only_ be called after the OpenGL
+    /// bindings have been loaded.
+    pub fn init<B: RenderBackend>(&mut self, target: &mut RenderTarget<B>) -> Result<(), EngineError> {
+        if self.passes.is_some() { return Ok(()) }
+
+        let (width, height) = target.size();
+        let backend = target.backend_mut();
+        backend.init(&[PipelineKind::Quads, PipelineKind::Post])?;
+
+        let targets = [backend.create_target(width, height)?, backend.create_target(width, height)?];
+        for (i, target) in targets.iter().enumerate() {
+            backend.label_target(*target, &format!("post target {}", i));
+        }
+        let quad = backend.create_buffer();
+        let screen = Quad::rect((-1.0, -1.0), (1.0, 1.0), 0.0,
+                                (0.0, 1.0), (1.0, 0.0), (1.0, 1.0, 1.0, 1.0));
+        backend.stream_quads(quad, &[screen]);
+        backend.label_buffer(quad, "post quad");
+
+        self.passes = Some(PostPasses { targets, quad });
+        self.size = (width, height);
+        Ok(())
+    }
+
+    /// Applies every enabled pass, in order, to `target`, leaving it bound.
+    ///
+    /// Shaders and LUTs of effects no longer in `passes` are freed. Those of
+    /// disabled passes are kept, so that toggling a pass is cheap.
+    pub fn render<B: RenderBackend>(&mut self, passes: &[PostPass], target: &mut RenderTarget<B>,
+                                    time: f32) {
+        self.draw_passes(passes, target, time);
+        self.evict_unused(passes, target.backend_mut());
+    }
+
+    fn draw_passes<B: RenderBackend>(&mut self, passes: &[PostPass], target: &mut RenderTarget<B>,
+                                     time: f32) {
+        let (post, source) = match (self.passes, target.handle()) {
+            (Some(post), Some(source)) => (post, source),
+            _ => return,
+        };
+        if !passes.iter().any(|p| p.enabled) { return; }
+
+        let size = target.size();
+        let backend = target.backend_mut();
+        if size != self.size {
+            self.size = size;
+            for pass_target in post.targets {
+                backend.resize_target(pass_target, size.0, size.1);
+            }
+        }
+
+        let mut input = backend.target_texture(source);
+        let mut output = 0;
+        let mut drawn = false;
+        for pass in passes.iter().filter(|p| p.enabled) {
+            // Compile custom shaders on first use. Broken shaders are stored as
+            // `None` so that compilation isn't retried (and reported) every frame.
+            let shader = match &pass.effect {
+                PostEffect::Custom { frag_source, .. } => {
+                    let shader = self.custom.entry(frag_source.clone()).or_insert_with(|| {
+                        backend.create_shader(frag_source)
+                            .map_err(|err| log::error("post process", err))
+                            .ok()
+                    });
+                    match shader {
+                        Some(shader) => Some(*shader),
+                        None => continue,
+                    }
+                },
+                _ => None,
+            };
+            let lut = match &pass.effect {
+                PostEffect::ColorGrade { lut, .. } => match self.luts.get(&lut.id) {
+                    Some(tex) => Some(*tex),
+                    None => match upload_lut(backend, lut) {
+                        Ok(tex) => {
+                            self.luts.insert(lut.id, tex);
+                            Some(tex)
+                        },
+                        Err(err) => {
+                            log::error("post process", err);
+                            continue;
+                        },
+                    },
+                },
+                _ => None,
+            };
+
+            let pipeline = Pipeline::Post { effect: &pass.effect, lut, shader, time };
+            backend.bind_target(Some(post.targets[output]));
+            backend.draw(&DrawCall::new(pipeline, post.quad, 1, glm::Mat4::identity())
+                .texture(input)
+                .blend(BlendMode::Opaque)
+                .depth_test(false));
+
+            input = backend.target_texture(post.targets[output]);
+            output = 1 - output;
+            drawn = true;
+        }
+
+        // Copy the final pass back into the source. Without a pass drawn,
+        // neither target holds this frame.
+        backend.bind_target(Some(source));
+        if !drawn { return; }
+        backend.draw(&DrawCall::new(Pipeline::Quads, post.quad, 1, glm::Mat4::identity())
+            .texture(input)
+            .blend(BlendMode::Opaque)
+            .depth_test(false));
+    }
+
+    /// Frees the custom shaders and LUTs that no pass in `passes` uses.
+    fn evict_unused<B: RenderBackend>(&mut self, passes: &[PostPass], backend: &mut B) {
+        self.custom.retain(|source, shader| {
+            let used = passes.iter().any(|pass| matches!(&pass.effect,
+                PostEffect::Custom { frag_source, .. } if frag_source == source));
+            if !used {
+                if let Some(shader) = shader { backend.destroy_shader(*shader); }
+            }
+            used
+        });
+        self.luts.retain(|id, tex| {
+            let used = passes.iter().any(|pass| matches!(&pass.effect,
+                PostEffect::ColorGrade { lut, .. } if lut.id == *id));
+            if !used { backend.destroy_texture(*tex); }
+            used
+        });
+    }
+}
+
+/// Uploads a LUT to a linearly filtered texture.
+fn upload_lut<B: RenderBackend>(backend: &mut B, lut: &Lut) -> Result<TextureHandle, EngineError> {
+    let desc = TextureDesc::new(lut.size * lut.size, lut.size)
+        .options(TextureOptions::default().filter(Filter::Linear));
+    let tex = backend.create_texture(desc, &lut.data)?;
+    backend.label_texture(tex, "color grade lut");
+    Ok(tex)
+}
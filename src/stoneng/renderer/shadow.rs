@@ -0,0 +1,153 @@
+//! Line-of-sight shadows for the LightRenderer.
+//!
+//! Occluders are axis-aligned rectangles. For each light, every edge of an
+//! occluder facing away from the light is extended away from it into a shadow
+//! volume, the area that edge hides from the light. The LightRenderer draws
+//! these volumes into the lightmap's stencil buffer before drawing the light,
+//! so the light only reaches what it can see. Occluders themselves stay lit on
+//! the faces turned towards the light.
+//!
+//! Soft shadows are drawn by splitting a light into several dimmer samples
+//! spread around its position, each casting its own hard shadow. Where only
+//! some samples reach, the lightmap is partially lit and the shadow mask
+//! dithers the penumbra like any other falloff.
+
+use crate::renderer::sprite::RenderSprite;
+
+type Point = (f32, f32);
+
+/// An axis-aligned rectangle that blocks light, in world units.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderOccluder {
+    /// The bottom-left corner
+    pub min: (f32, f32),
+    /// The top-right corner
+    pub max: (f32, f32),
+}
+impl RenderOccluder {
+    /// An occluder of `size` centered on `center`.
+    pub fn centered(center: (f32, f32), size: (f32, f32)) -> Self {
+        let half = (size.0.abs() / 2.0, size.1.abs() / 2.0);
+        Self {
+            min: (center.0 - half.0, center.1 - half.1),
+            max: (center.0 + half.0, center.1 + half.1),
+        }
+    }
+
+    /// The occluder covering a sprite's quad, for a sheet of `tile_width`
+    /// pixel tiles, see `RenderSprite::extent`.
+    pub fn sprite(sprite: &RenderSprite, tile_width: u32) -> Self {
+        let (center, half) = sprite.extent(tile_width);
+        Self::centered(center, (half.0 * 2.0, half.1 * 2.0))
+    }
+
+    /// Whether a point lies inside the occluder.
+    pub fn contains(&self, point: (f32, f32)) -> bool {
+        point.0 > self.min.0 && point.0 < self.max.0
+            && point.1 > self.min.1 && point.1 < self.max.1
+    }
+
+    /// Whether any of the occluder lies within `radius` of `point`.
+    pub fn within(&self, point: (f32, f32), radius: f32) -> bool {
+        let dx = point.0 - point.0.clamp(self.min.0, self.max.0);
+        let dy = point.1 - point.1.clamp(self.min.1, self.max.1);
+        dx * dx + dy * dy < radius * radius
+    }
+
+    /// The edges as (start, end, outward normal).
+    fn edges(&self) -> [(Point, Point, Point); 4] {
+        let (x0, y0) = self.min;
+        let (x1, y1) = self.max;
+        [
+            ((x0, y0), (x1, y0), ( 0.0, -1.0)),
+            ((x1, y0), (x1, y1), ( 1.0,  0.0)),
+            ((x1, y1), (x0, y1), ( 0.0,  1.0)),
+            ((x0, y1), (x0, y0), (-1.0,  0.0)),
+        ]
+    }
+}
+
+/// How lights are blocked by occluders.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ShadowMode {
+    /// Light passes through occluders.
+    Off,
+    /// Occluders cast sharp shadows.
+    #[default]
+    Hard,
+    /// Occluders cast shadows with a penumbra, as if each light were a disc of
+    /// `radius` world units. Each light is drawn `samples` times, so this costs
+    /// that many times the hard shadows.
+    Soft { radius: f32, samples: u32 },
+}
+impl ShadowMode {
+    /// How far past its intensity a light can reach occluders, from the
+    /// samples of soft shadows.
+    pub fn spread(&self) -> f32 {
+        match *self {
+            ShadowMode::Soft { radius, .. } => radius.max(0.0),
+            _ => 0.0,
+        }
+    }
+
+    /// The positions a light is drawn from, as offsets from its center.
+    pub fn sample_offsets(&self) -> Vec<(f32, f32)> {
+        match *self {
+            ShadowMode::Soft { radius, samples } if samples > 1 && radius > 0.0 => {
+                (0..samples).map(|i| {
+                    let angle = i as f32 / samples as f32 * std::f32::consts::TAU;
+                    (angle.cos() * radius, angle.sin() * radius)
+                }).collect()
+            },
+            _ => vec![(0.0, 0.0)],
+        }
+    }
+}
+
+/// Appends the shadow volumes `occluders` cast from a light at `light` with
+/// `radius` to `out`, as triangles of world positions. The volumes reach at
+/// least `radius` from the light.
+///
+/// Occluders out of reach, or that contain the light, cast nothing.
+///
+/// # Example
+/// ```
+/// # use stoneng::renderer::shadow::{RenderOccluder, shadow_volumes};
+/// let wall = RenderOccluder { min: (10.0, -5.0), max: (20.0, 5.0) };
+/// let mut triangles = Vec::new();
+///
+/// // Lit from the left, the wall's right, top and bottom edges face away
+/// shadow_volumes((0.0, 0.0), 50.0, &[wall], &mut triangles);
+/// assert_eq!(triangles.len(), 3 * 6);
+///
+/// // Out of reach
+/// triangles.clear();
+/// shadow_volumes((-100.0, 0.0), 50.0, &[wall], &mut triangles);
+/// assert!(triangles.is_empty());
+/// ```
+pub fn shadow_volumes(light: (f32, f32), radius: f32, occluders: &[RenderOccluder],
+                      out: &mut Vec<(f32, f32)>) {
+    for occluder in occluders {
+        if !occluder.within(light, radius) || occluder.contains(light) { continue; }
+        for (a, b, normal) in occluder.edges() {
+            let (ax, ay) = (a.0 - light.0, a.1 - light.1);
+            let (bx, by) = (b.0 - light.0, b.1 - light.1);
+            // Only edges facing away from the light cast a volume
+            if normal.0 * ax + normal.1 * ay <= 0.0 { continue; }
+
+            let (a_len, b_len) = ((ax * ax + ay * ay).sqrt(), (bx * bx + by * by).sqrt());
+            if a_len <= 0.0 || b_len <= 0.0 { continue; }
+            let (da, db) = ((ax / a_len, ay / a_len), (bx / b_len, by / b_len));
+
+            // The far edge of the volume is a chord between the extended
+            // corners, which passes closer to the light the wider the edge
+            // appears from it. Extend far enough for the chord to clear the radius.
+            let cos_half = ((1.0 + da.0 * db.0 + da.1 * db.1) / 2.0).max(0.0).sqrt();
+            let reach = (radius / cos_half.max(1.0 / 64.0)).max(a_len).max(b_len);
+            let far_a = (light.0 + da.0 * reach, light.1 + da.1 * reach);
+            let far_b = (light.0 + db.0 * reach, light.1 + db.1 * reach);
+
+            out.extend_from_slice(&[a, b, far_b, a, far_b, far_a]);
+        }
+    }
+}
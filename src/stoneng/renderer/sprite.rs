@@ -1,20 +1,27 @@
 #![allow(dead_code)]
 
 use crate::EngineError;
-use crate::shader;
-use crate::ecs::component;
-
-use stb::image::LoadResult;
-use std::{
-    path::Path,
-    mem::size_of,
+use crate::shader::VertexAttribute;
+use crate::renderer::Camera;
+use crate::renderer::texture::{self, TextureOptions};
+use crate::renderer::backend::{
+    RenderBackend, OpenGlBackend, PipelineKind, Pipeline, DrawCall, VertexData,
+    Quad, TextureDesc, TextureHandle, BufferHandle,
 };
-use glm::{Vec2, Vec3, Vec4, Mat4};
-use gl::types::*;
+use crate::ecs::component::Material;
+use crate::model::palette::Palettes;
+
+use std::mem::offset_of;
+
+/// Marks a RenderSprite as indexed, colored by the palette row in `reserved`.
+pub const FLAG_INDEXED: u8 = 0x1;
+
+/// Pixel width of a tile of the engine's atlas, `assets/textures/sprites.png`.
+pub const TILE_WIDTH: u32 = 10;
 
 /// An individual sprite model directly used for rendering. 
 #[repr(C)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy)]
 pub struct RenderSprite {
     pub translation:    (f32, f32, f32),
     pub scale:          (f32, f32),
@@ -42,22 +49,130 @@ impl Default for RenderSprite {
     }
 }
 
+impl RenderSprite {
+    /// The center and half size of the sprite's quad in world units, for a
+    /// sheet of `tile_width` pixel tiles. Sprites are anchored along the
+    /// bottom edge of their left-most tile, as in `include/sprite.glsl`.
+    pub fn extent(&self, tile_width: u32) -> ((f32, f32), (f32, f32)) {
+        let tile = tile_width as f32;
+        let dims = ((self.sprite_dims & 0xF) as f32 + 1.0, (self.sprite_dims >> 4) as f32 + 1.0);
+        let transl = (
+            (dims.0 - 1.0) * tile / 2.0,
+            (dims.1 - 1.0 + self.scale.1 / 2.0) * tile / 2.0,
+        );
+        let center = (self.translation.0 + transl.0, self.translation.1 + transl.1);
+        let half = (tile / 2.0 * dims.0 * self.scale.0, tile / 2.0 * dims.1 * self.scale.1);
+        (center, half)
+    }
+}
+
+/// The attributes of a RenderSprite, as read by `sprite/vert.glsl`. The dims,
+/// flags and palette row are read together as `sprite_data`.
+pub const SPRITE_LAYOUT: [VertexAttribute; 6] = [
+    VertexAttribute::new(0, 3, gl::FLOAT, offset_of!(RenderSprite, translation)),
+    VertexAttribute::new(1, 2, gl::FLOAT, offset_of!(RenderSprite, scale)),
+    VertexAttribute::new(2, 1, gl::FLOAT, offset_of!(RenderSprite, rotation)),
+    VertexAttribute::new(3, 4, gl::FLOAT, offset_of!(RenderSprite, color)),
+    VertexAttribute::new(4, 1, gl::UNSIGNED_INT, offset_of!(RenderSprite, sprite_id)),
+    VertexAttribute::new(5, 1, gl::UNSIGNED_INT, offset_of!(RenderSprite, sprite_dims)),
+];
+
+/// How sprites are laid out in a spritesheet: square tiles `tile_width`
+/// pixels wide, counted from the top-left of a sheet `width` pixels wide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SheetLayout {
+    pub width:      u32,
+    pub tile_width: u32,
+    /// Pixels of padding on each side of every tile, as added by
+    /// `TextureOptions::extrude`
+    pub border:     u32,
+}
+
+impl SheetLayout {
+    /// The distance between the corners of neighbouring tiles, in pixels.
+    pub fn pitch(&self) -> u32 {
+        self.tile_width + 2 * self.border
+    }
+
+    /// How many tiles fit along a row of the sheet.
+    pub fn per_row(&self) -> u32 {
+        (self.width / self.pitch().max(1)).max(1)
+    }
+}
+
+/// Builds the quads the sprite geometry shader would emit, for drawing sprites
+/// through a RenderBackend.
+///
+/// Quads only carry the sprites' uvs in the spritesheet and colors, so palette
+/// lookups and materials are left to whatever shades them, as in the
+/// `SoftwareBackend`.
+pub fn sprite_quads(sprites: &[RenderSprite], sheet: SheetLayout) -> Vec<Quad> {
+    let sheet_width = sheet.width as f32;
+    let ratio = sheet.tile_width as f32 / sheet_width;
+    let pitch_ratio = sheet.pitch() as f32 / sheet_width;
+    let border = sheet.border as f32 / sheet_width;
+    let per_row = sheet.per_row();
+
+    // Triangle strip order, with uvs flipped vertically as in geom.glsl
+    let unit_quad = [(-1.0, -1.0), (-1.0, 1.0), (1.0, -1.0), (1.0, 1.0)];
+    let unit_uv = [(0.0, ratio), (0.0, 0.0), (ratio, ratio), (ratio, 0.0)];
+
+    sprites.iter().map(|s| {
+        let dims = ((s.sprite_dims & 0xF) as f32 + 1.0, (s.sprite_dims >> 4) as f32 + 1.0);
+        let uv_offset = (
+            (s.sprite_id % per_row) as f32 * pitch_ratio + border,
+            (s.sprite_id / per_row) as f32 * pitch_ratio + border,
+        );
+        let (center, half) = s.extent(sheet.tile_width);
+
+        let mut quad = Quad::default();
+        for (i, vert) in quad.verts.iter_mut().enumerate() {
+            let (qx, qy) = unit_quad[i];
+            let (u, v) = unit_uv[i];
+            vert.pos = (center.0 + qx * half.0, center.1 + qy * half.1, s.translation.2);
+            vert.uv = (
+                u * dims.0 + uv_offset.0 - uv_offset.0 * (dims.0 - 1.0),
+                v * dims.1 + uv_offset.1 - uv_offset.1 * (dims.1 - 1.0),
+            );
+            vert.color = s.color;
+        }
+        quad
+    }).collect()
+}
+
 /// The SpriteRenderer is used to draw RenderSprites to the screen.
 ///
-/// It operates by loading an atlas image into a texture on the GPU. It later 
-/// references the atlas' sprites using the data in a RenderSprite.
+/// It operates by loading an atlas image into a texture through its
+/// `RenderBackend`, by default OpenGL. It later references the atlas' sprites
+/// using the data in a RenderSprite. With OpenGL each sprite is grown into a
+/// quad on the GPU, by the method picked in `QuadPath::detect`.
 ///
-/// As the renderer naturally relies on OpenGL to operate, it must only be used
-/// _after_ the OpenGL bindings have been loaded and only on the main thread.
-#[derive(Default, Clone, Copy)]
-pub struct SpriteRenderer {
-    initialized: bool,
-
-    shader:     GLuint,
-    vao:        GLuint,
-    abo:        GLuint,
-    tex:        GLuint,
-    uniform_locations:   [GLint; 4],
+/// With the OpenGlBackend the renderer must only be used _after_ the OpenGL
+/// bindings have been loaded and only on the main thread. The backend's
+/// objects are freed when the renderer is dropped.
+#[derive(Debug)]
+pub struct SpriteRenderer<B: RenderBackend = OpenGlBackend> {
+    backend:    B,
+    sprites:    Option<BufferHandle>,
+    tex:        Option<TextureHandle>,
+    tex_size:   (u32, u32),
+    palette_tex:        Option<TextureHandle>,
+    palette_size:       (u32, u32),
+    palette_version:    u64,
+
+    /// Seconds passed to material shaders through the `time` uniform
+    pub time:   f32,
+    /// How the atlas is loaded by `init`
+    pub atlas_options:  TextureOptions,
+    /// Whether sprites record their depth, off for translucent sprites drawn
+    /// over the scene
+    pub depth_write:    bool,
+}
+
+impl<B: RenderBackend + Default> Default for SpriteRenderer<B> {
+    fn default() -> Self {
+        Self::with_backend(B::default())
+    }
 }
 
 impl SpriteRenderer {
@@ -65,168 +180,252 @@ impl SpriteRenderer {
     pub fn new() -> Self {
         Self::default()
     }
+}
+
+impl<B: RenderBackend> SpriteRenderer<B> {
+    /// Creates an empty SpriteRenderer drawing through `backend`. `init` must
+    /// be called before use.
+    pub fn with_backend(backend: B) -> Self {
+        Self {
+            backend,
+            sprites: None,
+            tex: None,
+            tex_size: (0, 0),
+            palette_tex: None,
+            palette_size: (0, 0),
+            palette_version: 0,
+            time: 0.0,
+            atlas_options: TextureOptions::default(),
+            depth_write: true,
+        }
+    }
+
+    pub fn backend(&self) -> &B { &self.backend }
+    pub fn backend_mut(&mut self) -> &mut B { &mut self.backend }
 
-    /// Loads the atlas and initializes the SpriteRenderer's OpenGL objects.
+    /// Loads the atlas and prepares the backend for drawing sprites.
     /// 
-    /// This can _only_ be called after the OpenGL bindings have been loaded.
+    /// With the OpenGlBackend this can _only_ be called after the OpenGL
+    /// bindings have been loaded.
     pub fn init(&mut self, atlas: &[u8]) -> Result<(), EngineError> {
-        if self.initialized { return Ok(()) }
+        if self.sprites.is_some() { return Ok(()) }
 
-        // Prevent running this function too early.
-        if !gl::Viewport::is_loaded() {
-            let msg = format!("{}\n{}",
-                "SpriteRenderer::init called before gl bindings were loaded.",
-                "init() should only be called by the engine."
-            );
-            return Err(EngineError::RendererInit(msg));
+        self.backend.init(&[PipelineKind::Sprites])?;
+
+        // Decode the atlas and load it to the backend
+        let (tex, size) = texture::load_into(&mut self.backend, atlas, self.atlas_options)?;
+        let sprites = self.backend.create_buffer();
+        self.backend.label_texture(tex, "sprite atlas");
+        self.backend.label_buffer(sprites, "sprites");
+
+        self.tex = Some(tex);
+        self.tex_size = size;
+        self.sprites = Some(sprites);
+        Ok(())
+    }
+
+    /// Uploads the palettes used by indexed sprites, if they have changed
+    /// since the last upload.
+    pub fn set_palettes(&mut self, palettes: &Palettes) {
+        if self.sprites.is_none() || palettes.version() == self.palette_version { return; }
+        self.palette_version = palettes.version();
+        if palettes.is_empty() { return; }
+
+        let size = (palettes.width() as u32, palettes.len() as u32);
+        match self.palette_tex {
+            Some(tex) if size == self.palette_size => {
+                self.backend.update_texture(tex, palettes.data());
+            },
+            previous => {
+                if let Some(tex) = previous { self.backend.destroy_texture(tex); }
+                let desc = TextureDesc::new(size.0, size.1);
+                self.palette_tex = self.backend.create_texture(desc, palettes.data()).ok();
+                self.palette_size = size;
+                if let Some(tex) = self.palette_tex {
+                    self.backend.label_texture(tex, "sprite palettes");
+                }
+            },
         }
+    }
+
+    /// Loads a passed set of RenderSprites to the screen. 
+    pub fn render(&mut self, sprites: &[RenderSprite], window_size: (f32, f32), cam: &Camera){
+        self.draw(sprites, window_size, cam, None);
+    }
+
+    /// Draws a passed set of RenderSprites using a Material's shader and values.
+    pub fn render_material(&mut self, sprites: &[RenderSprite], window_size: (f32, f32), 
+                           cam: &Camera, material: &Material) {
+        self.draw(sprites, window_size, cam, Some(material));
+    }
 
-        // Load the image file into memory and format using stb_image
-        let atlas_img = match stb::image::load_from_memory(atlas){
-            // Accept unsigned byte formatted image
-            LoadResult::ImageU8(img) => img,
-            // Error on any other result
-            _ => {
-                let msg = format!("{}\n{}",
-                        "Failed to load texture atlas.",
-                        "Ensure the atlas is an RGBA PNG."
-                    );  
-                return Err(EngineError::RendererInit(msg));
+    fn draw(&mut self, sprites: &[RenderSprite], window_size: (f32, f32), 
+            cam: &Camera, material: Option<&Material>) {
+        let (buffer, tex) = match (self.sprites, self.tex) {
+            (Some(buffer), Some(tex)) => (buffer, tex),
+            _ => return,
+        };
+        self.backend.resize_screen(window_size.0 as u32, window_size.1 as u32);
+
+        let pipeline = Pipeline::Sprites {
+            sheet: SheetLayout {
+                width: self.tex_size.0,
+                tile_width: TILE_WIDTH,
+                border: self.atlas_options.border(),
             },
+            palettes: self.palette_tex,
+            material,
+            time: self.time,
         };
- 
-        // Build shader programs
-        self.shader = shader::program_from_sources(
-            include_str!("../../../assets/shaders/sprite/vert.glsl").into(),
-            include_str!("../../../assets/shaders/sprite/frag.glsl").into(),
-            Some(include_str!("../../../assets/shaders/sprite/geom.glsl").into())
-        ).unwrap();
- 
-        unsafe {
-            gl::UseProgram(self.shader);
-            
-            // Generate OpenGL objects/buffers
-            gl::GenVertexArrays(1, &mut self.vao as *mut GLuint);
-            gl::GenBuffers(1, &mut self.abo as *mut GLuint);
-            gl::GenTextures(1, &mut self.tex as *mut GLuint);
-            
-            // Binding
-            gl::BindVertexArray(self.vao);
-            gl::BindTexture(gl::TEXTURE_2D, self.tex);
-
-            // Load the texture to the GPU
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-            gl::TexImage2D(
-                gl::TEXTURE_2D, 0, gl::RGBA as i32, 
-                atlas_img.width as i32, atlas_img.height as i32, 0,
-                gl::RGBA, gl::UNSIGNED_BYTE,
-                atlas_img.data.as_ptr() as *const GLvoid
-            );
-            
+        self.backend.stream(buffer, VertexData::Sprites(sprites));
+        self.backend.draw(&DrawCall::new(pipeline, buffer, sprites.len(), cam.view_projection(window_size))
+            .texture(tex)
+            .blend(self.atlas_options.blend_mode())
+            .depth_write(self.depth_write));
+    }
+}
 
-            // Set up the attribute pointers
-            let stride = size_of::<RenderSprite>() as i32;
-            // Transform
-                // Translation
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.abo);
-            gl::EnableVertexAttribArray(0);
-            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, 0 as *const GLvoid); 
-
-                // Scale
-            let scale_offset = size_of::<f32>() as i32 * 3;
-            gl::EnableVertexAttribArray(1);
-            gl::VertexAttribPointer(1, 2, gl::FLOAT, gl::FALSE, stride, 
-                                    scale_offset as *const GLvoid); 
-                // Rotation    
-            let rotation_offset = scale_offset + (size_of::<f32>() as i32) * 2;
-            gl::EnableVertexAttribArray(2);
-            gl::VertexAttribPointer(2, 2, gl::FLOAT, gl::FALSE, stride, 
-                                    rotation_offset as *const GLvoid); 
-            
-            // Color 
-            let color_offset = rotation_offset + (size_of::<f32>() as i32);
-            gl::EnableVertexAttribArray(3);
-            gl::VertexAttribPointer(3, 4, gl::FLOAT, gl::FALSE, stride, 
-                                    color_offset as *const GLvoid);  
-
-            // Sprite ID
-            let id_offset = color_offset + (size_of::<f32>() as i32) * 4;
-            gl::EnableVertexAttribArray(4);
-            gl::VertexAttribIPointer(4, 1, gl::UNSIGNED_INT, stride, 
-                                     id_offset as *const GLvoid); 
-            // Sprite Data
-            let data_offset = id_offset + (size_of::<u32>() as i32);
-            gl::EnableVertexAttribArray(5);
-            gl::VertexAttribIPointer(5, 1, gl::UNSIGNED_INT, stride, 
-                                     data_offset as *const GLvoid); 
-
-            // Find and store the uniform locations
-            self.uniform_locations[0] = shader::get_uniform_location(
-                self.shader, "view_projection"); 
-            self.uniform_locations[1] = shader::get_uniform_location(
-                self.shader, "sheet_width");                       
-            self.uniform_locations[2] = shader::get_uniform_location(
-                self.shader, "sheet_tile_w");           
-
-            // Unbinding
-            gl::BindTexture(gl::TEXTURE_2D, 0);
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-            gl::BindVertexArray(0);
-            gl::UseProgram(0);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::backend::SoftwareBackend;
 
-        self.initialized = true;
-        Ok(())
+    const RED:      (u8, u8, u8, u8) = (255, 0, 0, 255);
+    const GREEN:    (u8, u8, u8, u8) = (0, 255, 0, 255);
+    const BLUE:     (u8, u8, u8, u8) = (0, 0, 255, 255);
+    const CLEAR:    (u8, u8, u8, u8) = (0, 0, 0, 255);
+    const SCREEN:   (f32, f32) = (20.0, 20.0);
+
+    /// A 250x10 atlas of 10px tiles: red, green, and palette index 1.
+    fn atlas() -> Vec<u8> {
+        let (width, height) = (250u16, 10u16);
+        // Uncompressed true-color TGA, stored from the top row down
+        let mut tga = vec![0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        tga.extend_from_slice(&width.to_le_bytes());
+        tga.extend_from_slice(&height.to_le_bytes());
+        tga.extend_from_slice(&[32, 0x28]);
+        for _ in 0..height {
+            for x in 0..width {
+                let (r, g, b, a) = match x / 10 {
+                    0 => RED,
+                    1 => GREEN,
+                    2 => (1, 0, 0, 255),
+                    _ => (0, 0, 0, 0),
+                };
+                tga.extend_from_slice(&[b, g, r, a]);
+            }
+        }
+        tga
     }
-    
-    /// Loads a passed set of RenderSprites to the screen. 
-    pub fn render(&self, sprites: &[RenderSprite], window_size: (f32, f32), cam: (f32, f32, f32)){
 
-        if !self.initialized { return; }
-        unsafe {
-            gl::Enable(gl::BLEND);
-            gl::Enable(gl::DEPTH_TEST);
+    fn renderer() -> SpriteRenderer<SoftwareBackend> {
+        let mut renderer = SpriteRenderer::with_backend(SoftwareBackend::new(20, 20));
+        renderer.init(&atlas()).unwrap();
+        renderer.backend_mut().clear((0.0, 0.0, 0.0, 1.0));
+        renderer
+    }
 
-            gl::UseProgram(self.shader);
-            gl::BindVertexArray(self.vao);
-            gl::BindTexture(gl::TEXTURE_2D, self.tex);
-            
-            let (winx, winy) = window_size;
-            gl::Viewport(0, 0, winx as i32, winy as i32);
-            
-            // Set uniforms
-                // view_projection
-            let projection = glm::ortho(0.0, winx, 0.0, winy, -25.0, 25.0);
-            let view: Mat4 = glm::translation(&Vec3::new(-cam.0, -cam.1, -cam.2));
-            let view_projection = projection * view;
-            gl::UniformMatrix4fv(self.uniform_locations[0], 1, gl::FALSE, 
-                                 view_projection.as_ptr());
-                
-                // sheet_width
-            gl::Uniform1i(self.uniform_locations[1], 250);
-                // sheet_tile_w
-            gl::Uniform1i(self.uniform_locations[2], 10);
-
-            // Transfer sprite data
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.abo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER, 
-                (size_of::<RenderSprite>() * sprites.len()) as GLsizeiptr,
-                sprites.as_ptr() as *const GLvoid,
-                gl::DYNAMIC_DRAW
-            );
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-
-            // Render to main framebuffer
-            gl::DrawArrays(gl::POINTS, 0, sprites.len() as i32);
-         
-            // Unbind
-            gl::BindTexture(gl::TEXTURE_2D, 0);
-            gl::BindVertexArray(0);
-            gl::UseProgram(0);
+    /// A sprite covering the 10px square with its bottom-left corner at `corner`.
+    fn sprite(id: u32, corner: (f32, f32), z: f32) -> RenderSprite {
+        RenderSprite {
+            translation: (corner.0 + 5.0, corner.1 + 2.5, z),
+            sprite_id: id,
+            ..Default::default()
         }
- 
     }
-}
 
+    #[test]
+    fn sprites_are_drawn_from_their_tile() {
+        let mut renderer = renderer();
+        let sprites = [sprite(0, (0.0, 0.0), 0.0), sprite(1, (10.0, 10.0), 0.0)];
+        renderer.render(&sprites, SCREEN, &Camera::default());
+
+        let image = renderer.backend_mut().read_pixels();
+        assert_eq!(image.pixel(0, 19), Some(RED));
+        assert_eq!(image.pixel(9, 10), Some(RED));
+        assert_eq!(image.pixel(10, 9), Some(GREEN));
+        assert_eq!(image.pixel(19, 0), Some(GREEN));
+        assert_eq!(image.pixel(5, 5), Some(CLEAR));
+        assert_eq!(image.pixel(15, 15), Some(CLEAR));
+    }
+
+    #[test]
+    fn sprites_follow_the_camera() {
+        let mut renderer = renderer();
+        let cam = Camera { pos: (10.0, 0.0, 0.0), zoom: 1.0 };
+        renderer.render(&[sprite(0, (10.0, 0.0), 0.0)], SCREEN, &cam);
+
+        let image = renderer.backend_mut().read_pixels();
+        assert_eq!(image.pixel(5, 15), Some(RED));
+        assert_eq!(image.pixel(15, 15), Some(CLEAR));
+    }
+
+    #[test]
+    fn sprite_colors_tint_and_blend() {
+        let mut renderer = renderer();
+        let tinted = RenderSprite { color: (1.0, 1.0, 1.0, 0.5), ..sprite(0, (0.0, 0.0), 0.0) };
+        renderer.render(&[tinted], SCREEN, &Camera::default());
+
+        let image = renderer.backend_mut().read_pixels();
+        assert_eq!(image.pixel(5, 15), Some((128, 0, 0, 255)));
+    }
+
+    #[test]
+    fn extruded_premultiplied_atlases_are_drawn_from_their_tile() {
+        let mut renderer = SpriteRenderer::with_backend(SoftwareBackend::new(20, 20));
+        renderer.atlas_options = TextureOptions::new().extrude(10, 10, 1).premultiplied(true);
+        renderer.init(&atlas()).unwrap();
+        renderer.backend_mut().clear((0.0, 0.0, 0.0, 1.0));
+
+        let tinted = RenderSprite { color: (1.0, 1.0, 1.0, 0.5), ..sprite(1, (10.0, 10.0), 0.0) };
+        renderer.render(&[sprite(0, (0.0, 0.0), 0.0), tinted], SCREEN, &Camera::default());
+
+        let image = renderer.backend_mut().read_pixels();
+        assert_eq!(image.pixel(0, 19), Some(RED));
+        assert_eq!(image.pixel(9, 10), Some(RED));
+        assert_eq!(image.pixel(10, 9), Some((0, 128, 0, 255)));
+        assert_eq!(image.pixel(19, 0), Some((0, 128, 0, 255)));
+    }
+
+    #[test]
+    fn nearer_sprites_are_drawn_over_farther_ones() {
+        let mut renderer = renderer();
+        let sprites = [sprite(0, (0.0, 0.0), 1.0), sprite(1, (0.0, 0.0), 0.0)];
+        renderer.render(&sprites, SCREEN, &Camera::default());
+
+        let image = renderer.backend_mut().read_pixels();
+        assert_eq!(image.pixel(5, 15), Some(RED));
+    }
+
+    #[test]
+    fn sprites_without_depth_writes_hide_nothing_drawn_later() {
+        let mut renderer = renderer();
+        renderer.depth_write = false;
+        let fog = RenderSprite { color: (1.0, 1.0, 1.0, 0.5), ..sprite(0, (0.0, 0.0), 1.0) };
+        renderer.render(&[fog], SCREEN, &Camera::default());
+        renderer.depth_write = true;
+        renderer.render(&[sprite(1, (0.0, 0.0), 0.0)], SCREEN, &Camera::default());
+
+        let image = renderer.backend_mut().read_pixels();
+        assert_eq!(image.pixel(5, 15), Some(GREEN));
+    }
+
+    #[test]
+    fn indexed_sprites_are_colored_by_their_palette() {
+        let mut renderer = renderer();
+        let mut palettes = Palettes::new();
+        palettes.set("first", &[(0, 0, 0, 0), RED]).unwrap();
+        let row = palettes.set("second", &[(0, 0, 0, 0), BLUE]).unwrap();
+        renderer.set_palettes(&palettes);
+
+        let indexed = RenderSprite {
+            sprite_flags: FLAG_INDEXED,
+            reserved: row,
+            ..sprite(2, (0.0, 0.0), 0.0)
+        };
+        renderer.render(&[indexed], SCREEN, &Camera::default());
+
+        let image = renderer.backend_mut().read_pixels();
+        assert_eq!(image.pixel(5, 15), Some(BLUE));
+    }
+}
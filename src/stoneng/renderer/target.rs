@@ -0,0 +1,106 @@
+#![allow(dead_code)]
+
+use crate::EngineError;
+use crate::renderer::backend::{RenderBackend, OpenGlBackend, TargetHandle, TextureHandle};
+
+/// An off-screen target that a frame is drawn to, with a color texture and a
+/// depth and stencil buffer.
+///
+/// The target is drawn to by binding it before the rest of the frame's
+/// renderers draw, which target whatever was bound before them. Passes over
+/// the finished frame, like the `PostProcessRenderer` and `UpscaleRenderer`,
+/// draw through the target's own backend with `backend_mut`.
+///
+/// Drawing goes through a `RenderBackend`, by default OpenGL, in which case the
+/// target must only be used _after_ the OpenGL bindings have been loaded and
+/// only on the main thread. The backend's objects are freed when the target
+/// is dropped.
+#[derive(Debug)]
+pub struct RenderTarget<B: RenderBackend = OpenGlBackend> {
+    backend:    B,
+    target:     Option<TargetHandle>,
+    width:      u32,
+    height:     u32,
+}
+impl<B: RenderBackend + Default> Default for RenderTarget<B> {
+    fn default() -> Self {
+        Self::with_backend(B::default())
+    }
+}
+
+impl RenderTarget {
+    /// Creates an empty RenderTarget. `init` must be called before use.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<B: RenderBackend> RenderTarget<B> {
+    /// Creates an empty RenderTarget drawing through `backend`. `init` must
+    /// be called before use.
+    pub fn with_backend(backend: B) -> Self {
+        Self { backend, target: None, width: 0, height: 0 }
+    }
+
+    pub fn backend(&self) -> &B { &self.backend }
+    pub fn backend_mut(&mut self) -> &mut B { &mut self.backend }
+
+    /// Creates the target at the given size.
+    ///
+    /// With the OpenGlBackend this can _only_ be called after the OpenGL
+    /// bindings have been loaded.
+    pub fn init(&mut self, width: u32, height: u32) -> Result<(), EngineError> {
+        if self.target.is_some() { return Ok(()) }
+
+        self.backend.init(&[])?;
+        let (width, height) = (width.max(1), height.max(1));
+        self.target = Some(self.backend.create_target(width, height)?);
+        self.width = width;
+        self.height = height;
+        Ok(())
+    }
+
+    /// Reallocates the target if the size has changed.
+    pub fn resize(&mut self, width: u32, height: u32) {
+        let target = match self.target {
+            Some(target) => target,
+            None => return,
+        };
+        let (width, height) = (width.max(1), height.max(1));
+        if (width, height) == (self.width, self.height) { return; }
+        self.backend.resize_target(target, width, height);
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Names the target in driver messages and debuggers.
+    pub fn label(&mut self, name: &str) {
+        if let Some(target) = self.target { self.backend.label_target(target, name); }
+    }
+
+    /// Binds the target for drawing.
+    pub fn bind(&mut self) {
+        if let Some(target) = self.target { self.backend.bind_target(Some(target)); }
+    }
+
+    /// Reverts drawing to whatever was bound before `bind`, for the
+    /// OpenGlBackend the window's framebuffer.
+    pub fn unbind(&mut self) {
+        self.backend.bind_target(None);
+    }
+
+    /// Clears the color, depth and stencil of the bound target.
+    pub fn clear(&mut self, color: (f32, f32, f32, f32)) {
+        self.backend.clear(color);
+    }
+
+    /// The target itself, for binding it through `backend_mut`.
+    pub fn handle(&self) -> Option<TargetHandle> { self.target }
+
+    /// The color attachment, for sampling the target in later passes.
+    pub fn texture(&self) -> Option<TextureHandle> {
+        self.target.map(|target| self.backend.target_texture(target))
+    }
+
+    pub fn size(&self) -> (u32, u32) { (self.width, self.height) }
+}
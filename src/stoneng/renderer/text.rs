@@ -1,20 +1,22 @@
 #![allow(dead_code)]
 
 use crate::EngineError;
-use crate::shader;
-
-use stb::image::LoadResult;
-use std::{
-    path::Path,
-    mem::size_of,
+use crate::shader::VertexAttribute;
+use crate::renderer::Camera;
+use crate::renderer::texture::{self, TextureOptions};
+use crate::renderer::backend::{
+    RenderBackend, OpenGlBackend, PipelineKind, Pipeline, DrawCall, VertexData,
+    TextureHandle, BufferHandle,
 };
 
-use glm::{Vec2, Vec3, Vec4};
+use std::mem::offset_of;
 use gl::types::*;
 
 /// Used to differentiate between drawing to the screen and using 
 /// ingame positions. This is important for UI vs ingame text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum VectorSpace {
+    #[default]
     World,
     Screen,
 }
@@ -46,7 +48,7 @@ impl From<String> for RenderString {
 /// A system that perhaps only transmits the glyphs and character number
 /// as attributes would be more performant
 #[repr(C)]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct RenderChar {
     pub position:      (f32, f32, f32),
     // pub offset:      (f32, f32) TODO implement
@@ -54,6 +56,14 @@ pub struct RenderChar {
     pub color:          (f32, f32, f32, f32),
     pub glyph:          GLbyte,
 }
+/// The attributes of a RenderChar, as read by `text/vert.glsl`.
+pub const CHAR_LAYOUT: [VertexAttribute; 4] = [
+    VertexAttribute::new(0, 3, gl::FLOAT, offset_of!(RenderChar, position)),
+    VertexAttribute::new(1, 1, gl::FLOAT, offset_of!(RenderChar, size)),
+    VertexAttribute::new(2, 4, gl::FLOAT, offset_of!(RenderChar, color)),
+    VertexAttribute::new(3, 1, gl::BYTE, offset_of!(RenderChar, glyph)),
+];
+
 impl RenderChar {
     pub fn new( position: (f32,f32,f32), 
                 size:  f32, 
@@ -68,32 +78,26 @@ impl RenderChar {
 /// Strings can be rendered in either world or screen space based on a parameter
 /// of `render`.
 ///
-/// As the renderer naturally relies on OpenGL to operate, it must only be used
-/// _after_ the OpenGL bindings have been loaded and only on the main thread.
-#[derive(Clone, Copy)]
-pub struct TextRenderer {
-    initialized: bool,
-
-    shader:     GLuint,
-    vao:        GLuint,
-    abo:        GLuint,
-    tex:        GLuint,
-    uniform_locations:  [GLint; 3],
+/// Drawing goes through a `RenderBackend`, by default OpenGL, in which case the
+/// renderer must only be used _after_ the OpenGL bindings have been loaded and
+/// only on the main thread. The backend's objects are freed when the renderer
+/// is dropped.
+#[derive(Debug)]
+pub struct TextRenderer<B: RenderBackend = OpenGlBackend> {
+    backend:    B,
+    chars:      Option<BufferHandle>,
+    tex:        Option<TextureHandle>,
 
     glyph_size: u32,
     atlas_width: u32,
     kerning: f32,
+
+    /// How the font image is loaded by `init`
+    pub font_options: TextureOptions,
 }
-impl Default for TextRenderer {
+impl<B: RenderBackend + Default> Default for TextRenderer<B> {
     fn default() -> Self {
-        Self {
-            initialized: false,
-            shader: 0, vao: 0, abo: 0, tex: 0,
-            uniform_locations: [0; 3],
-            glyph_size: 0,
-            atlas_width: 0,
-            kerning: -2.0,    
-        }
+        Self::with_backend(B::default())
     }
 }
 
@@ -102,115 +106,57 @@ impl TextRenderer {
     pub fn new() -> Self {
         Self::default()
     }
+}
+
+impl<B: RenderBackend> TextRenderer<B> {
+    /// Creates an empty TextRenderer drawing through `backend`. `init` must be
+    /// called before use.
+    pub fn with_backend(backend: B) -> Self {
+        Self {
+            backend,
+            chars: None,
+            tex: None,
+            glyph_size: 0,
+            atlas_width: 0,
+            kerning: -2.0,    
+            font_options: TextureOptions::default(),
+        }
+    }
+
+    pub fn backend(&self) -> &B { &self.backend }
+    pub fn backend_mut(&mut self) -> &mut B { &mut self.backend }
 
-    /// Initializes OpenGL objects and loads the font texture to the GPU
+    /// Prepares the backend for drawing text and loads the font texture to it.
     ///
-    /// This can _only_ be called after the OpenGL bindings have been loaded.
+    /// With the OpenGlBackend this can _only_ be called after the OpenGL
+    /// bindings have been loaded.
     pub fn init(&mut self, font_img_bytes: &[u8], glyph_size: u32) -> Result<(), EngineError> {
         // Prevent double loading
-        if self.initialized { return Ok(()) }
+        if self.chars.is_some() { return Ok(()) }
 
-        // Prevent calling too early
-        if !gl::Viewport::is_loaded() {
-            let msg = format!("{}\n{}",
-                "TextRenderer::init called before gl bindings were loaded.",
-                "init() should only be called by the engine."
-            );
-            return Err(EngineError::RendererInit(msg));
-        }
+        self.backend.init(&[PipelineKind::Text])?;
 
-        // Load the font image into memory and format it using stb_image
-        let font_img = match stb::image::load_from_memory(font_img_bytes){
-            LoadResult::ImageU8(img) => img,
-            _ => {
-                let msg = format!("{}\n{}",
-                        "Failed to load the font image.",
-                        "Ensure the image is an RGBA PNG."
-                    );  
-                return Err(EngineError::RendererInit(msg));
-            },
-        };
+        // Decode the font image and load it to the backend
+        let (tex, size) = texture::load_into(&mut self.backend, font_img_bytes, self.font_options)?;
+        let chars = self.backend.create_buffer();
+        self.backend.label_texture(tex, "font atlas");
+        self.backend.label_buffer(chars, "text chars");
 
-        self.shader = shader::program_from_sources(
-            include_str!("../../../assets/shaders/text/vert.glsl").into(),
-            include_str!("../../../assets/shaders/text/frag.glsl").into(),
-            Some(include_str!("../../../assets/shaders/text/geom.glsl").into()),
-        ).unwrap();
-        
         // Record atlas metadata
         self.glyph_size = glyph_size;
-        self.atlas_width = font_img.width as u32;
-
-        unsafe {
-            gl::UseProgram(self.shader);
-            
-            // Generate OpenGL objects/buffers
-            gl::GenVertexArrays(1, &mut self.vao as *mut GLuint);
-            gl::GenBuffers(1, &mut self.abo as *mut GLuint);
-            gl::GenTextures(1, &mut self.tex as *mut GLuint);
-
-            // Binding
-            gl::BindVertexArray(self.vao);
-            gl::BindTexture(gl::TEXTURE_2D, self.tex);
-
-            // Load the texture to the GPU
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
-            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
-            gl::TexImage2D(
-                gl::TEXTURE_2D, 0, gl::RGBA as i32, 
-                font_img.width as i32, font_img.height as i32, 0,
-                gl::RGBA, gl::UNSIGNED_BYTE,
-                font_img.data.as_ptr() as *const GLvoid
-            );
-            
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.abo);
-            
-            // Set up the attribute pointers
-            let stride = size_of::<RenderChar>() as i32;
-               // Translation
-            gl::EnableVertexAttribArray(0);
-            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, 0 as *const GLvoid); 
-
-                // Size
-            let size_offset = (size_of::<f32>() as i32) * 3;
-            gl::EnableVertexAttribArray(1);
-            gl::VertexAttribPointer(1, 1, gl::FLOAT, gl::FALSE, stride, 
-                                    size_offset as *const GLvoid); 
-            
-                // Color 
-            let color_offset = size_offset + (size_of::<f32>() as i32);
-            gl::EnableVertexAttribArray(2);
-            gl::VertexAttribPointer(2, 4, gl::FLOAT, gl::FALSE, stride, 
-                                    color_offset as *const GLvoid); 
-
-                // Char
-            let char_offset = color_offset + (size_of::<f32>() as i32) * 4;
-            gl::EnableVertexAttribArray(3);
-            gl::VertexAttribIPointer(3, 1, gl::BYTE, stride, char_offset as *const GLvoid);
-        
-
-            // Uniform locations
-            self.uniform_locations[0] = shader::get_uniform_location(
-                self.shader, "view_projection");
-            self.uniform_locations[1] = shader::get_uniform_location(
-                self.shader, "glyph_size");
-            self.uniform_locations[2] = shader::get_uniform_location(
-                self.shader, "atlas_width");
+        self.atlas_width = size.0;
 
-            // Unbind states
-            gl::BindTexture(gl::TEXTURE_2D, 0);
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-            gl::BindVertexArray(0);
-            gl::UseProgram(0);
-        }
-        
-        self.initialized = true;
+        self.tex = Some(tex);
+        self.chars = Some(chars);
         Ok(())
     }
 
     /// Draws a set of RenderStrings to the screen.
-    pub fn render(&self, strings: &[RenderString], window_size: (f32, f32), cam: (f32, f32, f32)){
-        if !self.initialized { return; }
+    pub fn render(&mut self, strings: &[RenderString], window_size: (f32, f32), cam: &Camera){
+        let (buffer, tex) = match (self.chars, self.tex) {
+            (Some(buffer), Some(tex)) => (buffer, tex),
+            _ => return,
+        };
         
         // Build a set of render chars using the passed render strings
         let mut chars: Vec<RenderChar> = Vec::new();
@@ -225,48 +171,13 @@ impl TextRenderer {
             }
         }
 
-        unsafe {
-            // Setup OpenGL state
-            gl::Enable(gl::BLEND);
-            gl::Enable(gl::DEPTH_TEST);
-            gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE);
-            gl::BlendEquation(gl::FUNC_ADD);
- 
-            gl::UseProgram(self.shader);
-            gl::BindVertexArray(self.vao);
-            gl::BindTexture(gl::TEXTURE_2D, self.tex);
-            
-            let (winx, winy) = window_size;
-            gl::Viewport(0, 0, winx as i32, winy as i32);
-
-            // Uniforms
-            let view: glm::Mat4 = glm::translation(&glm::Vec3::new(-cam.0, -cam.1, -cam.2));
-            let projection = glm::ortho(0.0, winx, 0.0, winy, -25.0, 25.0);
-            let view_projection = projection * view;
-            gl::UniformMatrix4fv(self.uniform_locations[0], 1, gl::FALSE, 
-                                 view_projection.as_ptr());
-            
-                // Send texture's glyph layout
-            gl::Uniform1f(self.uniform_locations[1], self.glyph_size as GLfloat);
-            gl::Uniform1f(self.uniform_locations[2], self.atlas_width as GLfloat);
-
-            // Vertex data
-            gl::BindBuffer(gl::ARRAY_BUFFER, self.abo);
-            gl::BufferData(
-                gl::ARRAY_BUFFER,
-                (size_of::<RenderChar>() * chars.len()) as GLsizeiptr,
-                chars.as_ptr() as *const GLvoid,
-                gl::DYNAMIC_DRAW
-            );
-            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-
-            // Render to screen
-            gl::DrawArrays(gl::POINTS, 0, chars.len() as i32);
-
-            // Unbind
-            gl::BindTexture(gl::TEXTURE_2D, 0);
-            gl::BindVertexArray(0);
-            gl::UseProgram(0);
-        }
+        self.backend.resize_screen(window_size.0 as u32, window_size.1 as u32);
+        let pipeline = Pipeline::Text {
+            glyph_size: self.glyph_size as f32,
+            atlas_width: self.atlas_width as f32,
+        };
+        self.backend.stream(buffer, VertexData::Chars(&chars));
+        self.backend.draw(&DrawCall::new(pipeline, buffer, chars.len(), cam.view_projection(window_size))
+            .texture(tex));
     }
 }
@@ -0,0 +1,312 @@
+//! Loading images into textures through a `RenderBackend`.
+//!
+//! Images are decoded with stb_image and converted to 8-bit RGBA, whatever
+//! their channels: grayscale, grayscale with alpha, RGB and RGBA are all
+//! accepted. 16-bit PNGs are reduced to 8 bits per channel by the decoder, and
+//! HDR images are clamped and encoded as sRGB.
+//!
+//! How each texture is prepared and sampled is set with `TextureOptions`,
+//! which defaults to the engine's pixel art settings: `NEAREST` filtering,
+//! clamped edges, no mipmaps and straight alpha.
+
+use crate::EngineError;
+use crate::renderer::backend::{BlendMode, Filter, Image, RenderBackend, TextureDesc, TextureHandle};
+
+use stb::image::LoadResult;
+use gl::types::*;
+
+/// How texture coordinates outside of [0, 1] are resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapMode {
+    /// Repeats the edge texels
+    #[default]
+    ClampToEdge,
+    /// Tiles the texture
+    Repeat,
+    /// Tiles the texture, mirroring every other tile
+    MirroredRepeat,
+}
+impl WrapMode {
+    pub(crate) fn to_gl(self) -> GLint {
+        (match self {
+            WrapMode::ClampToEdge       => gl::CLAMP_TO_EDGE,
+            WrapMode::Repeat            => gl::REPEAT,
+            WrapMode::MirroredRepeat    => gl::MIRRORED_REPEAT,
+        }) as GLint
+    }
+}
+
+/// Repeats the edge pixels of each tile of an atlas outwards, see `extrude_tiles`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extrusion {
+    pub tile_width:     u32,
+    pub tile_height:    u32,
+    /// Pixels added on each side of every tile
+    pub border:         u32,
+}
+
+/// How a texture is prepared and sampled.
+///
+/// # Example
+/// ```
+/// # use stoneng::renderer::{backend::Filter, texture::{TextureOptions, WrapMode}};
+/// let options = TextureOptions::new()
+///     .filter(Filter::Linear)
+///     .wrap(WrapMode::Repeat)
+///     .mipmaps(true)
+///     .premultiplied(true);
+/// assert_eq!(options.wrap_s, WrapMode::Repeat);
+/// assert!(options.mipmaps && !options.srgb);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TextureOptions {
+    /// Filtering when the texture is drawn smaller than its size
+    pub min_filter:     Filter,
+    /// Filtering when the texture is drawn larger than its size
+    pub mag_filter:     Filter,
+    pub wrap_s:         WrapMode,
+    pub wrap_t:         WrapMode,
+    /// Generates mipmaps, sampled with the min filter
+    pub mipmaps:        bool,
+    /// Multiplies colors by their alpha before upload, to avoid dark fringes
+    /// when filtering. Such textures are drawn with `BlendMode::Premultiplied`,
+    /// as the sprite and nine-slice renderers do for their atlas.
+    pub premultiplied:  bool,
+    /// Stores the texture as sRGB, so that it is converted to linear color
+    /// when sampled. Only wanted where the result is encoded again, e.g. with
+    /// `GL_FRAMEBUFFER_SRGB`, as the engine otherwise blends in sRGB.
+    pub srgb:           bool,
+    /// Pads each tile of an atlas before upload, which the sprite and
+    /// nine-slice renderers account for with `SheetLayout::border`.
+    pub extrude:        Option<Extrusion>,
+}
+
+impl TextureOptions {
+    /// The engine's defaults for pixel art, see `TextureOptions`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets both the min and mag filters.
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.min_filter = filter;
+        self.mag_filter = filter;
+        self
+    }
+
+    pub fn min_filter(mut self, filter: Filter) -> Self {
+        self.min_filter = filter;
+        self
+    }
+
+    pub fn mag_filter(mut self, filter: Filter) -> Self {
+        self.mag_filter = filter;
+        self
+    }
+
+    /// Sets the wrap mode of both axes.
+    pub fn wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap_s = wrap;
+        self.wrap_t = wrap;
+        self
+    }
+
+    pub fn mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    pub fn premultiplied(mut self, premultiplied: bool) -> Self {
+        self.premultiplied = premultiplied;
+        self
+    }
+
+    pub fn srgb(mut self, srgb: bool) -> Self {
+        self.srgb = srgb;
+        self
+    }
+
+    /// Extrudes the edges of each `tile_width` by `tile_height` tile by
+    /// `border` pixels before upload, see `extrude_tiles`.
+    pub fn extrude(mut self, tile_width: u32, tile_height: u32, border: u32) -> Self {
+        self.extrude = Some(Extrusion { tile_width, tile_height, border });
+        self
+    }
+
+    /// The padding extrusion adds around each tile, 0 without extrusion.
+    pub fn border(&self) -> u32 {
+        self.extrude.map_or(0, |extrusion| extrusion.border)
+    }
+
+    /// How textures loaded with these options blend over their target.
+    pub fn blend_mode(&self) -> BlendMode {
+        if self.premultiplied { BlendMode::Premultiplied } else { BlendMode::Alpha }
+    }
+
+    pub(crate) fn min_filter_gl(&self) -> GLint {
+        (match (self.min_filter, self.mipmaps) {
+            (Filter::Nearest, false)    => gl::NEAREST,
+            (Filter::Linear, false)     => gl::LINEAR,
+            (Filter::Nearest, true)     => gl::NEAREST_MIPMAP_NEAREST,
+            (Filter::Linear, true)      => gl::LINEAR_MIPMAP_LINEAR,
+        }) as GLint
+    }
+
+    pub(crate) fn mag_filter_gl(&self) -> GLint {
+        (match self.mag_filter {
+            Filter::Nearest => gl::NEAREST,
+            Filter::Linear  => gl::LINEAR,
+        }) as GLint
+    }
+}
+
+/// Decodes an image file, e.g. a PNG, to RGBA.
+pub fn decode(bytes: &[u8]) -> Result<Image, EngineError> {
+    match stb::image::load_from_memory(bytes) {
+        LoadResult::ImageU8(img) => {
+            let data = to_rgba(img.depth, &img.data)?;
+            Ok(Image { width: img.width as u32, height: img.height as u32, data })
+        },
+        LoadResult::ImageF32(img) => {
+            // HDR images are decoded as linear color
+            let data: Vec<u8> = img.data.iter().enumerate().map(|(i, v)| {
+                let v = v.clamp(0.0, 1.0);
+                let alpha = img.depth % 2 == 0 && i % img.depth == img.depth - 1;
+                if alpha { (v * 255.0).round() as u8 } else { encode_srgb(v) }
+            }).collect();
+            let data = to_rgba(img.depth, &data)?;
+            Ok(Image { width: img.width as u32, height: img.height as u32, data })
+        },
+        LoadResult::Error(err) => Err(EngineError::TextureError(
+            format!("Failed to decode image: {}", err)
+        )),
+    }
+}
+
+/// Converts pixels of `channels` 8-bit channels to RGBA. One channel is
+/// grayscale, two are grayscale and alpha, three are RGB and four RGBA.
+///
+/// # Example
+/// ```
+/// # use stoneng::renderer::texture::to_rgba;
+/// assert_eq!(to_rgba(1, &[10]).unwrap(), vec![10, 10, 10, 255]);
+/// assert_eq!(to_rgba(2, &[10, 20]).unwrap(), vec![10, 10, 10, 20]);
+/// assert_eq!(to_rgba(3, &[1, 2, 3]).unwrap(), vec![1, 2, 3, 255]);
+/// assert!(to_rgba(5, &[0; 5]).is_err());
+/// ```
+pub fn to_rgba(channels: usize, data: &[u8]) -> Result<Vec<u8>, EngineError> {
+    let rgba = match channels {
+        1 => data.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        2 => data.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        3 => data.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+        4 => data.to_vec(),
+        _ => return Err(EngineError::TextureError(
+            format!("Unsupported image with {} channels", channels)
+        )),
+    };
+    Ok(rgba)
+}
+
+fn decode_srgb(value: f32) -> f32 {
+    if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+}
+
+fn encode_srgb(value: f32) -> u8 {
+    let value = if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Multiplies the colors of an RGBA image by their alpha. sRGB colors are
+/// multiplied as linear color.
+///
+/// # Example
+/// ```
+/// # use stoneng::renderer::{backend::Image, texture::premultiply_alpha};
+/// let mut img = Image { width: 2, height: 1, data: vec![200, 100, 50, 255, 200, 100, 50, 0] };
+/// premultiply_alpha(&mut img, false);
+/// assert_eq!(img.pixel(0, 0), Some((200, 100, 50, 255)));
+/// assert_eq!(img.pixel(1, 0), Some((0, 0, 0, 0)));
+/// ```
+pub fn premultiply_alpha(image: &mut Image, srgb: bool) {
+    for pixel in image.data.chunks_exact_mut(4) {
+        let alpha = pixel[3] as f32 / 255.0;
+        for c in &mut pixel[..3] {
+            *c = if srgb {
+                encode_srgb(decode_srgb(*c as f32 / 255.0) * alpha)
+            } else {
+                (*c as f32 * alpha).round() as u8
+            };
+        }
+    }
+}
+
+/// Pads every `tile_width` by `tile_height` tile of an atlas with `border`
+/// copies of its edge pixels, so that filtering and mipmapping near a tile's
+/// edge doesn't pick up its neighbours.
+///
+/// Tiles of the result are `tile_width + 2 * border` pixels apart, and each
+/// tile's own pixels start `border` pixels into its cell. Sprites spanning
+/// several tiles are split by the padding, so this suits atlases of
+/// single-tile sprites.
+///
+/// # Example
+/// ```
+/// # use stoneng::renderer::{backend::Image, texture::extrude_tiles};
+/// let red = [255, 0, 0, 255];
+/// let blue = [0, 0, 255, 255];
+/// let atlas = Image { width: 2, height: 1, data: [red, blue].concat() };
+/// let padded = extrude_tiles(&atlas, 1, 1, 1);
+/// assert_eq!((padded.width, padded.height), (6, 3));
+/// assert_eq!(padded.pixel(0, 0), Some((255, 0, 0, 255)));
+/// assert_eq!(padded.pixel(2, 2), Some((255, 0, 0, 255)));
+/// assert_eq!(padded.pixel(3, 1), Some((0, 0, 255, 255)));
+/// ```
+pub fn extrude_tiles(image: &Image, tile_width: u32, tile_height: u32, border: u32) -> Image {
+    let (tile_w, tile_h) = (tile_width.max(1), tile_height.max(1));
+    let (tiles_x, tiles_y) = (image.width / tile_w, image.height / tile_h);
+    let (pitch_x, pitch_y) = (tile_w + 2 * border, tile_h + 2 * border);
+    let (width, height) = (tiles_x * pitch_x, tiles_y * pitch_y);
+
+    // Maps a padded coordinate to the source, clamping into its tile
+    let source = |coord: u32, pitch: u32, tile: u32| {
+        let local = (coord % pitch) as i64 - border as i64;
+        (coord / pitch) * tile + local.clamp(0, tile as i64 - 1) as u32
+    };
+
+    let mut data = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        let sy = source(y, pitch_y, tile_h);
+        for x in 0..width {
+            let sx = source(x, pitch_x, tile_w);
+            let i = ((sy * image.width + sx) * 4) as usize;
+            data.extend_from_slice(&image.data[i..i + 4]);
+        }
+    }
+    Image { width, height, data }
+}
+
+/// Applies the options that change an image's data before upload, its
+/// extrusion and premultiplication.
+fn prepare(image: &mut Image, options: &TextureOptions) {
+    if let Some(extrusion) = options.extrude {
+        *image = extrude_tiles(image, extrusion.tile_width, extrusion.tile_height, extrusion.border);
+    }
+    if options.premultiplied {
+        premultiply_alpha(image, options.srgb);
+    }
+}
+
+/// Decodes an image file, e.g. a PNG, and uploads it to a backend. Returns the
+/// texture along with its size, including any extrusion.
+pub fn load_into<B: RenderBackend>(backend: &mut B, bytes: &[u8], options: TextureOptions)
+        -> Result<(TextureHandle, (u32, u32)), EngineError> {
+    let mut image = decode(bytes)?;
+    prepare(&mut image, &options);
+    let desc = TextureDesc::new(image.width, image.height).options(options);
+    let texture = backend.create_texture(desc, &image.data)?;
+    Ok((texture, (image.width, image.height)))
+}
@@ -0,0 +1,117 @@
+#![allow(dead_code)]
+
+use crate::EngineError;
+use crate::renderer::target::RenderTarget;
+use crate::renderer::backend::{
+    RenderBackend, PipelineKind, Pipeline, DrawCall, BlendMode, Quad, BufferHandle,
+};
+
+/// How a low resolution render is fit into the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScalingMode {
+    /// Scales by the largest whole number that fits, keeping every art pixel
+    /// the same size. Any remaining space is filled with bars on all sides.
+    #[default]
+    PixelPerfect,
+    /// Scales as large as possible while keeping the aspect ratio, adding
+    /// letterboxing (top/bottom) or pillarboxing (left/right) bars as needed.
+    Fit,
+    /// Stretches to cover the whole window, ignoring the aspect ratio.
+    Stretch,
+}
+
+/// A rectangle of the window, in pixels from the bottom-left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ViewportRect {
+    pub x:      f32,
+    pub y:      f32,
+    pub width:  f32,
+    pub height: f32,
+}
+
+/// Finds the area of the window that a `target` sized image is drawn to.
+pub fn fit_viewport(target: (f32, f32), window: (f32, f32), mode: ScalingMode) -> ViewportRect {
+    let (tw, th) = (target.0.max(1.0), target.1.max(1.0));
+    let (ww, wh) = window;
+
+    let (width, height) = match mode {
+        ScalingMode::Stretch => (ww, wh),
+        ScalingMode::Fit => {
+            let scale = f32::min(ww / tw, wh / th);
+            (tw * scale, th * scale)
+        },
+        ScalingMode::PixelPerfect => {
+            // Never scale below 1x, even if it means cropping the image
+            let scale = f32::min(ww / tw, wh / th).floor().max(1.0);
+            (tw * scale, th * scale)
+        },
+    };
+
+    ViewportRect {
+        x: ((ww - width) / 2.0).floor(),
+        y: ((wh - height) / 2.0).floor(),
+        width,
+        height,
+    }
+}
+
+/// Draws a render target's texture to the window using a `ScalingMode`.
+///
+/// Drawing goes through the backend of the target, which owns the renderer's
+/// objects and frees them when it is dropped.
+#[derive(Default, Debug)]
+pub struct UpscaleRenderer {
+    quad:   Option<BufferHandle>,
+}
+
+impl UpscaleRenderer {
+    /// Creates an empty UpscaleRenderer. `init` must be called before use.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prepares the target's backend to draw the target to the window.
+    ///
+    /// With the OpenGlBackend this can _only_ be called after the OpenGL
+    /// bindings have been loaded.
+    pub fn init<B: RenderBackend>(&mut self, target: &mut RenderTarget<B>) -> Result<(), EngineError> {
+        if self.quad.is_some() { return Ok(()) }
+
+        let backend = target.backend_mut();
+        backend.init(&[PipelineKind::Quads])?;
+        let quad = backend.create_buffer();
+        backend.label_buffer(quad, "upscale quad");
+
+        self.quad = Some(quad);
+        Ok(())
+    }
+
+    /// Draws the target to the screen of its backend, for the OpenGlBackend
+    /// the window's framebuffer, inside of `rect`.
+    ///
+    /// The rest of the window is cleared to `bar_color`.
+    pub fn render<B: RenderBackend>(&mut self, target: &mut RenderTarget<B>, window_size: (f32, f32),
+                                    rect: ViewportRect, bar_color: (f32, f32, f32, f32)) {
+        let (quad, texture) = match (self.quad, target.texture()) {
+            (Some(quad), Some(texture)) => (quad, texture),
+            _ => return,
+        };
+        let backend = target.backend_mut();
+        backend.resize_screen(window_size.0 as u32, window_size.1 as u32);
+        backend.bind_target(None);
+
+        // Clear the bars
+        backend.clear(bar_color);
+
+        // The rect in clip space, sampling the target from its bottom row up
+        let (ww, wh) = (window_size.0.max(1.0), window_size.1.max(1.0));
+        let min = (rect.x / ww * 2.0 - 1.0, rect.y / wh * 2.0 - 1.0);
+        let max = ((rect.x + rect.width) / ww * 2.0 - 1.0, (rect.y + rect.height) / wh * 2.0 - 1.0);
+        let rect = Quad::rect(min, max, 0.0, (0.0, 1.0), (1.0, 0.0), (1.0, 1.0, 1.0, 1.0));
+        backend.stream_quads(quad, &[rect]);
+        backend.draw(&DrawCall::new(Pipeline::Quads, quad, 1, glm::Mat4::identity())
+            .texture(texture)
+            .blend(BlendMode::Opaque)
+            .depth_test(false));
+    }
+}
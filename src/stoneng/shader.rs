@@ -1,7 +1,12 @@
 #![allow(unused_variables, dead_code, unused_imports)]
 use crate::error::EngineError;
+use crate::renderer::{context::GlVersion, gpu::{GlProgram, GlShader}};
 use gl::types::*;
-use std::{ fs, ffi::{CString, CStr} };
+use std::{ fs, ffi::{CString, CStr}, path::PathBuf };
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use crate::log;
+use glm::{Vec2, Vec3, Vec4, Mat4};
 
 #[derive(Debug, Clone)]
 pub enum ShaderType {
@@ -10,6 +15,17 @@ pub enum ShaderType {
     GeometryShader = gl::GEOMETRY_SHADER as isize,
 }
 
+impl ShaderType {
+    /// Names sources compiled without a file name
+    fn label(&self) -> &'static str {
+        match self {
+            ShaderType::VertexShader    => "vertex shader",
+            ShaderType::FragmentShader  => "fragment shader",
+            ShaderType::GeometryShader  => "geometry shader",
+        }
+    }
+}
+
 /// Returns the position of a shader program's uniform.
 ///
 /// The name must contain no null bytes or will result in an error
@@ -21,6 +37,384 @@ pub fn get_uniform_location(program: GLuint, name: &str) -> GLint {
     }
 }
 
+/// A typed value for a shader uniform.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UniformValue {
+    Float(f32),
+    Int(i32),
+    Vec2(f32, f32),
+    Vec3(f32, f32, f32),
+    Vec4(f32, f32, f32, f32),
+}
+
+/// Sets a uniform of the currently bound program.
+///
+/// Locations of -1 (unknown uniforms) are silently ignored by OpenGL.
+pub fn set_uniform(location: GLint, value: &UniformValue) {
+    unsafe {
+        match *value {
+            UniformValue::Float(x)          => gl::Uniform1f(location, x),
+            UniformValue::Int(x)            => gl::Uniform1i(location, x),
+            UniformValue::Vec2(x, y)        => gl::Uniform2f(location, x, y),
+            UniformValue::Vec3(x, y, z)     => gl::Uniform3f(location, x, y, z),
+            UniformValue::Vec4(x, y, z, w)  => gl::Uniform4f(location, x, y, z, w),
+        }
+    }
+}
+
+/// An active uniform or vertex attribute of a linked program.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShaderVariable {
+    pub location:   GLint,
+    /// The GLSL type, e.g. `gl::FLOAT_VEC2`
+    pub kind:       GLenum,
+    /// The length of arrays, 1 otherwise
+    pub size:       GLint,
+}
+
+/// A vertex attribute stored in a `#[repr(C)]` struct, see `set_vertex_layout`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VertexAttribute {
+    pub location:   GLuint,
+    /// Number of components, from 1 to 4
+    pub components: GLint,
+    /// The type of each component, e.g. `gl::FLOAT`. Integer types are read
+    /// as integers by the shader, rather than normalized.
+    pub kind:       GLenum,
+    /// Offset of the field in the struct, see `std::mem::offset_of!`
+    pub offset:     usize,
+}
+
+impl VertexAttribute {
+    pub const fn new(location: GLuint, components: GLint, kind: GLenum, offset: usize) -> Self {
+        Self { location, components, kind, offset }
+    }
+
+    fn is_integer(&self) -> bool {
+        matches!(self.kind, gl::BYTE | gl::UNSIGNED_BYTE | gl::SHORT 
+                          | gl::UNSIGNED_SHORT | gl::INT | gl::UNSIGNED_INT)
+    }
+
+    /// Size in bytes
+    fn size(&self) -> usize {
+        let component = match self.kind {
+            gl::BYTE | gl::UNSIGNED_BYTE    => 1,
+            gl::SHORT | gl::UNSIGNED_SHORT  => 2,
+            _                               => 4,
+        };
+        component * self.components as usize
+    }
+}
+
+/// Points the bound vertex array's attributes into the bound buffer, which
+/// holds structs `stride` bytes long. Attributes advance every `divisor`
+/// instances, or every vertex for 0.
+///
+/// # Safety
+/// A vertex array and array buffer must be bound.
+pub unsafe fn set_vertex_layout(layout: &[VertexAttribute], stride: usize, divisor: GLuint) {
+    for attribute in layout {
+        let offset = attribute.offset as *const GLvoid;
+        gl::EnableVertexAttribArray(attribute.location);
+        if attribute.is_integer() {
+            gl::VertexAttribIPointer(attribute.location, attribute.components, attribute.kind,
+                                     stride as GLint, offset);
+        } else {
+            gl::VertexAttribPointer(attribute.location, attribute.components, attribute.kind,
+                                    gl::FALSE, stride as GLint, offset);
+        }
+        if divisor > 0 {
+            gl::VertexAttribDivisor(attribute.location, divisor);
+        }
+    }
+}
+
+/// The scalar type and component count of a GLSL vector or scalar type.
+fn glsl_components(kind: GLenum) -> Option<(GLenum, GLint)> {
+    Some(match kind {
+        gl::FLOAT               => (gl::FLOAT, 1),
+        gl::FLOAT_VEC2          => (gl::FLOAT, 2),
+        gl::FLOAT_VEC3          => (gl::FLOAT, 3),
+        gl::FLOAT_VEC4          => (gl::FLOAT, 4),
+        gl::INT                 => (gl::INT, 1),
+        gl::INT_VEC2            => (gl::INT, 2),
+        gl::INT_VEC3            => (gl::INT, 3),
+        gl::INT_VEC4            => (gl::INT, 4),
+        gl::UNSIGNED_INT        => (gl::UNSIGNED_INT, 1),
+        gl::UNSIGNED_INT_VEC2   => (gl::UNSIGNED_INT, 2),
+        gl::UNSIGNED_INT_VEC3   => (gl::UNSIGNED_INT, 3),
+        gl::UNSIGNED_INT_VEC4   => (gl::UNSIGNED_INT, 4),
+        _ => return None,
+    })
+}
+
+fn is_sampler(kind: GLenum) -> bool {
+    matches!(kind, gl::SAMPLER_2D | gl::SAMPLER_3D | gl::SAMPLER_CUBE 
+                 | gl::SAMPLER_2D_ARRAY | gl::SAMPLER_2D_SHADOW 
+                 | gl::INT_SAMPLER_2D | gl::UNSIGNED_INT_SAMPLER_2D)
+}
+
+/// The names of the uniforms a GLSL source declares, active or not.
+fn declared_uniforms(source: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    for statement in source.split(';') {
+        // Uniform blocks are not reflected as plain uniforms
+        if statement.contains('{') { continue; }
+        let mut words = statement.split_whitespace();
+        if !words.any(|word| word == "uniform") { continue; }
+        let declarators = words.collect::<Vec<_>>().join(" ");
+        for (i, declarator) in declarators.split(',').enumerate() {
+            // The first declarator follows the type, `vec2 a, b[2]`
+            let name = match i {
+                0 => declarator.split_whitespace().last(),
+                _ => declarator.split_whitespace().next(),
+            };
+            if let Some(name) = name.and_then(|name| name.split('[').next()) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// A linked shader program, along with its active uniforms and attributes.
+///
+/// Uniforms are set by name with the typed setters, which look up locations
+/// found when the program was linked. Names the program doesn't have, or
+/// setters of the wrong type, are reported once per program and ignored.
+/// Uniforms that are declared but optimized out by the driver are ignored
+/// silently when the program was built by a `Preprocessor`.
+///
+/// The program is deleted when the ShaderProgram is dropped.
+#[derive(Debug, Default)]
+pub struct ShaderProgram {
+    program:    GlProgram,
+    uniforms:   HashMap<String, ShaderVariable>,
+    attributes: HashMap<String, ShaderVariable>,
+    /// Uniforms declared by the sources, but not active after linking
+    inactive:   HashSet<String>,
+    /// Uniforms already reported as unknown or mistyped
+    reported:   Mutex<HashSet<String>>,
+}
+
+impl ShaderProgram {
+    /// Takes ownership of a linked program, reading its active uniforms and
+    /// attributes.
+    ///
+    /// This can _only_ be called after the OpenGL bindings have been loaded.
+    pub fn from_id(id: GLuint) -> Self {
+        Self::reflect(id, &[])
+    }
+
+    fn reflect(id: GLuint, declared: &[String]) -> Self {
+        let mut program = Self { program: GlProgram::adopt(id), ..Default::default() };
+        if id == 0 { return program; }
+
+        unsafe {
+            let mut count: GLint = 0;
+            let mut max_len: GLint = 0;
+            gl::GetProgramiv(id, gl::ACTIVE_UNIFORMS, &mut count);
+            gl::GetProgramiv(id, gl::ACTIVE_UNIFORM_MAX_LENGTH, &mut max_len);
+            for index in 0..count as GLuint {
+                let (name, size, kind) = active_variable(id, index, max_len, gl::GetActiveUniform);
+                // Arrays are named by their first element
+                let base = name.strip_suffix("[0]").unwrap_or(&name).to_string();
+                for element in 0..size {
+                    let element_name = format!("{}[{}]", base, element);
+                    let location = get_uniform_location(id, &element_name);
+                    let variable = ShaderVariable { location, kind, size: size - element };
+                    if element == 0 { program.uniforms.insert(base.clone(), variable); }
+                    if size > 1 { program.uniforms.insert(element_name, variable); }
+                }
+            }
+
+            gl::GetProgramiv(id, gl::ACTIVE_ATTRIBUTES, &mut count);
+            gl::GetProgramiv(id, gl::ACTIVE_ATTRIBUTE_MAX_LENGTH, &mut max_len);
+            for index in 0..count as GLuint {
+                let (name, size, kind) = active_variable(id, index, max_len, gl::GetActiveAttrib);
+                // Built-ins such as gl_VertexID are listed by some drivers
+                if name.starts_with("gl_") { continue; }
+                let cname = CString::new(name.as_str()).unwrap_or_default();
+                let location = gl::GetAttribLocation(id, cname.as_ptr());
+                program.attributes.insert(name, ShaderVariable { location, kind, size });
+            }
+        }
+
+        program.inactive = declared.iter()
+            .filter(|name| !program.uniforms.contains_key(*name))
+            .cloned()
+            .collect();
+        program
+    }
+
+    /// The OpenGL program id, 0 if the program failed to build.
+    pub fn id(&self) -> GLuint { self.program.id() }
+
+    /// Names the program in driver messages and debuggers.
+    pub fn label(&self, label: &str) { self.program.label(label) }
+
+    /// Gives up ownership of the program, returning its id without deleting it.
+    pub fn into_id(self) -> GLuint { self.program.into_raw() }
+
+    /// Makes this the program used for drawing, and that uniforms are set on.
+    pub fn bind(&self) {
+        unsafe { gl::UseProgram(self.id()); }
+    }
+
+    pub fn uniform(&self, name: &str) -> Option<&ShaderVariable> { self.uniforms.get(name) }
+    pub fn attribute(&self, name: &str) -> Option<&ShaderVariable> { self.attributes.get(name) }
+
+    /// Whether the program has an active uniform by this name. This is used
+    /// to only set optional uniforms, such as those offered to material and
+    /// post-processing shaders, without being reported.
+    pub fn has_uniform(&self, name: &str) -> bool { self.uniforms.contains_key(name) }
+
+    /// The location of a uniform, or -1 if it has none, which OpenGL ignores.
+    /// Unknown names are reported once.
+    pub fn location(&self, name: &str) -> GLint {
+        match self.uniforms.get(name) {
+            Some(uniform) => uniform.location,
+            None => {
+                if !self.inactive.contains(name) {
+                    self.report(name, "is not a uniform of the program");
+                }
+                -1
+            },
+        }
+    }
+
+    /// The location of a uniform if it has a type accepted by a setter.
+    fn typed_location(&self, name: &str, accepts: impl Fn(GLenum) -> bool, setter: &str) -> GLint {
+        match self.uniforms.get(name) {
+            Some(uniform) if !accepts(uniform.kind) => {
+                self.report(name, &format!("can not be set by {} (type 0x{:X})", setter, uniform.kind));
+                -1
+            },
+            _ => self.location(name),
+        }
+    }
+
+    fn report(&self, name: &str, problem: &str) {
+        let mut reported = self.reported.lock().unwrap_or_else(|err| err.into_inner());
+        if reported.insert(name.to_string()) {
+            log::warn("shader", format!("Program {}: uniform '{}' {}", self.id(), name, problem));
+        }
+    }
+
+    /// Sets a uniform of this program, which must be bound.
+    pub fn set_float(&self, name: &str, value: f32) {
+        let location = self.typed_location(name, |kind| kind == gl::FLOAT, "set_float");
+        unsafe { gl::Uniform1f(location, value); }
+    }
+
+    /// Sets a uniform of this program, which must be bound. Bools and
+    /// samplers are also set as ints.
+    pub fn set_int(&self, name: &str, value: i32) {
+        let location = self.typed_location(name, 
+            |kind| kind == gl::INT || kind == gl::BOOL || is_sampler(kind), "set_int");
+        unsafe { gl::Uniform1i(location, value); }
+    }
+
+    /// Sets a uniform of this program, which must be bound.
+    pub fn set_vec2(&self, name: &str, value: Vec2) {
+        let location = self.typed_location(name, |kind| kind == gl::FLOAT_VEC2, "set_vec2");
+        unsafe { gl::Uniform2f(location, value.x, value.y); }
+    }
+
+    /// Sets a uniform of this program, which must be bound.
+    pub fn set_vec3(&self, name: &str, value: Vec3) {
+        let location = self.typed_location(name, |kind| kind == gl::FLOAT_VEC3, "set_vec3");
+        unsafe { gl::Uniform3f(location, value.x, value.y, value.z); }
+    }
+
+    /// Sets a uniform of this program, which must be bound.
+    pub fn set_vec4(&self, name: &str, value: Vec4) {
+        let location = self.typed_location(name, |kind| kind == gl::FLOAT_VEC4, "set_vec4");
+        unsafe { gl::Uniform4f(location, value.x, value.y, value.z, value.w); }
+    }
+
+    /// Sets a uniform of this program, which must be bound.
+    pub fn set_mat4(&self, name: &str, value: &Mat4) {
+        let location = self.typed_location(name, |kind| kind == gl::FLOAT_MAT4, "set_mat4");
+        unsafe { gl::UniformMatrix4fv(location, 1, gl::FALSE, value.as_ptr()); }
+    }
+
+    /// Points a sampler uniform of this program, which must be bound, at a
+    /// texture unit (0 for `gl::TEXTURE0`).
+    pub fn set_sampler(&self, name: &str, unit: u32) {
+        let location = self.typed_location(name, is_sampler, "set_sampler");
+        unsafe { gl::Uniform1i(location, unit as GLint); }
+    }
+
+    /// Sets a uniform of this program, which must be bound, by its value's type.
+    pub fn set_uniform(&self, name: &str, value: &UniformValue) {
+        match *value {
+            UniformValue::Float(x)          => self.set_float(name, x),
+            UniformValue::Int(x)            => self.set_int(name, x),
+            UniformValue::Vec2(x, y)        => self.set_vec2(name, Vec2::new(x, y)),
+            UniformValue::Vec3(x, y, z)     => self.set_vec3(name, Vec3::new(x, y, z)),
+            UniformValue::Vec4(x, y, z, w)  => self.set_vec4(name, Vec4::new(x, y, z, w)),
+        }
+    }
+
+    /// Checks a vertex layout against the program's active attributes. Every
+    /// attribute the program reads must be in the layout, with a matching
+    /// number and kind of components, and within the `stride` of the struct.
+    ///
+    /// Attributes the program doesn't read are ignored, as drivers are free
+    /// to optimize them out.
+    pub fn check_layout(&self, layout: &[VertexAttribute], stride: usize) 
+            -> Result<(), EngineError> {
+        let mut problems = Vec::new();
+        for (name, attribute) in self.attributes.iter() {
+            let provided = layout.iter()
+                .find(|provided| provided.location as GLint == attribute.location);
+            let (provided, (scalar, components)) = match (provided, glsl_components(attribute.kind)) {
+                (Some(provided), Some(components)) => (provided, components),
+                (None, _) => {
+                    problems.push(format!("'{}' at location {} is not in the layout", 
+                                          name, attribute.location));
+                    continue;
+                },
+                // Matrix attributes span several locations, and aren't checked
+                (Some(_), None) => continue,
+            };
+            if provided.components != components {
+                problems.push(format!("'{}' has {} components, the layout gives {}",
+                                      name, components, provided.components));
+            }
+            if (scalar != gl::FLOAT) != provided.is_integer() {
+                problems.push(format!("'{}' is {}, the layout gives 0x{:X} components", name,
+                                      if scalar == gl::FLOAT { "floating point" } else { "an integer" },
+                                      provided.kind));
+            }
+            if provided.offset + provided.size() > stride {
+                problems.push(format!("'{}' ends past the {} byte stride", name, stride));
+            }
+        }
+
+        if problems.is_empty() { return Ok(()); }
+        problems.sort();
+        Err(EngineError::RendererInit(format!(
+            "Vertex layout does not match shader program {}:\n  {}", self.id(), problems.join("\n  "))))
+    }
+}
+
+/// Reads the name, size and type of an active uniform or attribute.
+unsafe fn active_variable(program: GLuint, index: GLuint, max_len: GLint,
+                          query: unsafe fn(GLuint, GLuint, GLsizei, *mut GLsizei, 
+                                           *mut GLint, *mut GLenum, *mut GLchar))
+                          -> (String, GLint, GLenum) {
+    let mut buffer = vec![0u8; max_len.max(1) as usize];
+    let mut len: GLsizei = 0;
+    let mut size: GLint = 0;
+    let mut kind: GLenum = 0;
+    query(program, index, max_len, &mut len, &mut size, &mut kind, 
+          buffer.as_mut_ptr() as *mut GLchar);
+    buffer.truncate(len.max(0) as usize);
+    (String::from_utf8_lossy(&buffer).into_owned(), size, kind)
+}
+
 /// Builds an OpenGL shader program using GLSL sources.
 ///
 /// The shader program is created, compiled and linked. If no geometry shader
@@ -28,41 +422,18 @@ pub fn get_uniform_location(program: GLuint, name: &str) -> GLint {
 /// Each shader's source code must be free of null bytes or there will errors
 /// upon conversion to CStrings (a requirement of OpenGL C bindings).
 ///
-/// Shaders are detached and deleted after program is linked.
+/// Shaders are detached and deleted after program is linked. Sources are
+/// expanded by a default `Preprocessor`, see `Preprocessor::program` for
+/// defines and the engine's own shaders.
 pub fn program_from_sources(vert_source: String, 
                             frag_source: String, 
                             geom_source: Option<String>)
                             -> Result<GLuint, EngineError> {
-    // Compile mandatory shaders
-    let vert_shader = compile_source(vert_source, ShaderType::VertexShader)?;
-    let frag_shader = compile_source(frag_source, ShaderType::FragmentShader)?;
-    // Optionally compile the geometry shader
-    let geom_shader = match geom_source {
-        Some(source) => {
-            // Wrap the result in Some, otherwise return the error
-            match compile_source(source, ShaderType::GeometryShader) {
-                Ok(shader) => Some(shader),
-                Err(err) => return Err(err),
-            }
-        },
-        // Forward the None value to linking
-        None => None,
-    };
-    
-    // Link the program and save the Result
-    let program = link_program(vert_shader, frag_shader, geom_shader);
-    
-    // Cleanup OpenGL objects
-    unsafe {
-        gl::DeleteShader(vert_shader);
-        gl::DeleteShader(frag_shader);
-        if let Some(shader) = geom_shader {
-            gl::DeleteShader(shader);
-        }
-    }
-
-    // Forward the linking Result
-    program
+    Preprocessor::new().program_from_sources(
+        (ShaderType::VertexShader.label(), &vert_source),
+        (ShaderType::FragmentShader.label(), &frag_source),
+        geom_source.as_deref().map(|source| (ShaderType::GeometryShader.label(), source)),
+    ).map(ShaderProgram::into_id)
 }
 
 /// Creates a shader program and links shaders to it.
@@ -106,7 +477,7 @@ fn link_program(vert_shader: GLuint,
             // Convert CString to String
             let error = error_log.to_string_lossy().into_owned();
             // Return as linking error
-            println!("=====Linking=====\n{}", error);
+            gl::DeleteProgram(program);
             return Err(EngineError::ShaderLink(error));
         }
         
@@ -121,15 +492,545 @@ fn link_program(vert_shader: GLuint,
 }
 
 
+/// Declared after the `#version` line of GLSL ES shaders, which have no
+/// default precision for floats in fragment shaders.
+const ES_PRECISION: &str =
+    "precision highp float; precision highp int; precision highp sampler2D;";
+
+/// The GLSL sources under `assets/shaders`, by their path from there.
+const BUILTIN_SOURCES: &[(&str, &str)] = &[
+    ("debug/vert.glsl",         include_str!("../../assets/shaders/debug/vert.glsl")),
+    ("debug/frag.glsl",         include_str!("../../assets/shaders/debug/frag.glsl")),
+    ("include/dither.glsl",     include_str!("../../assets/shaders/include/dither.glsl")),
+    ("include/glyph.glsl",      include_str!("../../assets/shaders/include/glyph.glsl")),
+    ("include/light.glsl",      include_str!("../../assets/shaders/include/light.glsl")),
+    ("include/lighting.glsl",   include_str!("../../assets/shaders/include/lighting.glsl")),
+    ("include/nine_slice.glsl", include_str!("../../assets/shaders/include/nine_slice.glsl")),
+    ("include/quad.glsl",       include_str!("../../assets/shaders/include/quad.glsl")),
+    ("include/sprite.glsl",     include_str!("../../assets/shaders/include/sprite.glsl")),
+    ("lightmap/vert.glsl",      include_str!("../../assets/shaders/lightmap/vert.glsl")),
+    ("lightmap/geom.glsl",      include_str!("../../assets/shaders/lightmap/geom.glsl")),
+    ("lightmap/frag.glsl",      include_str!("../../assets/shaders/lightmap/frag.glsl")),
+    ("material/dissolve.glsl",  include_str!("../../assets/shaders/material/dissolve.glsl")),
+    ("material/flash.glsl",     include_str!("../../assets/shaders/material/flash.glsl")),
+    ("material/outline.glsl",   include_str!("../../assets/shaders/material/outline.glsl")),
+    ("material/water.glsl",     include_str!("../../assets/shaders/material/water.glsl")),
+    ("nine_slice/vert.glsl",    include_str!("../../assets/shaders/nine_slice/vert.glsl")),
+    ("nine_slice/geom.glsl",    include_str!("../../assets/shaders/nine_slice/geom.glsl")),
+    ("nine_slice/frag.glsl",    include_str!("../../assets/shaders/nine_slice/frag.glsl")),
+    ("post/vert.glsl",          include_str!("../../assets/shaders/post/vert.glsl")),
+    ("post/bloom.glsl",         include_str!("../../assets/shaders/post/bloom.glsl")),
+    ("post/chromatic.glsl",     include_str!("../../assets/shaders/post/chromatic.glsl")),
+    ("post/color_grade.glsl",   include_str!("../../assets/shaders/post/color_grade.glsl")),
+    ("post/scanlines.glsl",     include_str!("../../assets/shaders/post/scanlines.glsl")),
+    ("post/vignette.glsl",      include_str!("../../assets/shaders/post/vignette.glsl")),
+    ("quad/vert.glsl",          include_str!("../../assets/shaders/quad/vert.glsl")),
+    ("quad/frag.glsl",          include_str!("../../assets/shaders/quad/frag.glsl")),
+    ("shadow/vert.glsl",        include_str!("../../assets/shaders/shadow/vert.glsl")),
+    ("shadow/frag.glsl",        include_str!("../../assets/shaders/shadow/frag.glsl")),
+    ("shadowmask/vert.glsl",    include_str!("../../assets/shaders/shadowmask/vert.glsl")),
+    ("shadowmask/frag.glsl",    include_str!("../../assets/shaders/shadowmask/frag.glsl")),
+    ("shadowmask/tint.glsl",    include_str!("../../assets/shaders/shadowmask/tint.glsl")),
+    ("sprite/vert.glsl",        include_str!("../../assets/shaders/sprite/vert.glsl")),
+    ("sprite/geom.glsl",        include_str!("../../assets/shaders/sprite/geom.glsl")),
+    ("sprite/frag.glsl",        include_str!("../../assets/shaders/sprite/frag.glsl")),
+    ("text/vert.glsl",          include_str!("../../assets/shaders/text/vert.glsl")),
+    ("text/geom.glsl",          include_str!("../../assets/shaders/text/geom.glsl")),
+    ("text/frag.glsl",          include_str!("../../assets/shaders/text/frag.glsl")),
+];
+
+/// Returns a GLSL source shipped with the engine by its path under
+/// `assets/shaders`, e.g. `"sprite/vert.glsl"`.
+pub fn builtin_source(path: &str) -> Option<&'static str> {
+    BUILTIN_SOURCES.iter()
+        .find(|(builtin, _)| *builtin == path)
+        .map(|(_, source)| *source)
+}
+
+/// Prepares GLSL sources for compilation on the current context.
+///
+/// - `#include "name"` is replaced by the named file, searched for in the
+///   sources given to `include`, then the engine's `assets/shaders/include/`,
+///   then each `include_dir`. A file is only included once per shader.
+///   Includes in `#ifdef`, `#ifndef` and `#else` branches that won't be
+///   compiled, going by the `define`s and the sources' own `#define`s, are
+///   skipped. Branches of `#if` and `#elif` expressions are always expanded.
+/// - `#define`s given to `define` are declared before the source, for
+///   variants of a shader selected with `#ifdef`.
+/// - The `#version` line is replaced with the context's, see `GlVersion`.
+///   For GLSL ES, default precisions are declared and `in`/`out` interface
+///   blocks, which GLSL ES 3.00 lacks, become plain variables named
+///   `BLOCK_member`. Blocks are matched between stages by name, so this
+///   keeps them linking.
+///
+/// Line numbers in compile errors are mapped back to the files they came from.
+///
+/// # Example
+/// ```
+/// # use stoneng::{shader::Preprocessor, renderer::context::GlVersion};
+/// let preprocessor = Preprocessor::new()
+///     .version(GlVersion::parse("OpenGL ES 3.0").unwrap())
+///     .define("INSTANCED", "1")
+///     .include("tint.glsl", "uniform vec4 tint;");
+///
+/// let source = "#version 410 core
+/// #include \"tint.glsl\"
+/// in GS_OUT {
+///     vec2 tex_coord; // sprite uv
+/// } gs_out;
+/// out vec4 color;
+/// void main() { color = tint * vec4(gs_out.tex_coord, 0.0, 1.0); }";
+///
+/// let processed = preprocessor.process("tinted.glsl", source).unwrap();
+/// assert!(processed.source.starts_with("#version 300 es\nprecision highp float;"));
+/// assert!(processed.source.contains("#define INSTANCED 1\n"));
+/// assert!(processed.source.contains("in vec2 GS_OUT_tex_coord;"));
+/// assert!(processed.source.contains("tint * vec4(GS_OUT_tex_coord, 0.0, 1.0)"));
+///
+/// // Errors point back to the included file, and the line in the original
+/// assert_eq!(processed.map_log("0:5(14): error: syntax error"),
+///            "tint.glsl:1(14): error: syntax error");
+/// assert_eq!(processed.map_log("0(8) : error C1008: undefined variable"),
+///            "tinted.glsl(5) : error C1008: undefined variable");
+///
+/// // Includes in skipped branches don't count towards including a file once
+/// let source = "#ifndef INSTANCED
+/// #include \"tint.glsl\"
+/// #endif
+/// #include \"tint.glsl\"";
+/// let processed = preprocessor.process("variant.glsl", source).unwrap();
+/// assert!(processed.source.ends_with("#endif\nuniform vec4 tint;\n"));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Preprocessor {
+    defines:        Vec<(String, String)>,
+    includes:       HashMap<String, String>,
+    include_dirs:   Vec<PathBuf>,
+    version:        Option<GlVersion>,
+}
+
+impl Preprocessor {
+    pub fn new() -> Self { Self::default() }
+
+    /// Declares `#define name value` before the source.
+    pub fn define(mut self, name: &str, value: &str) -> Self {
+        self.defines.retain(|(defined, _)| defined != name);
+        self.defines.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Makes a source available to `#include` by name, ahead of any files.
+    pub fn include(mut self, name: &str, source: &str) -> Self {
+        self.includes.insert(name.to_string(), source.to_string());
+        self
+    }
+
+    /// Searches a directory for includes not otherwise found.
+    pub fn include_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.include_dirs.push(dir.into());
+        self
+    }
+
+    /// Targets a version other than the current context's.
+    pub fn version(mut self, version: GlVersion) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Expands a source, named for errors.
+    pub fn process(&self, name: &str, source: &str) -> Result<ProcessedSource, EngineError> {
+        let mut expansion = Expansion {
+            defined: self.defines.iter().map(|(name, _)| name.clone()).collect(),
+            ..Default::default()
+        };
+        self.expand(name, source, &mut expansion)?;
+
+        // Injected lines all point at the source's #version line
+        let (version_line, own_header) = expansion.version.take()
+            .map(|(line, header)| (line, Some(header)))
+            .unwrap_or((1, None));
+        let version = self.version.or_else(GlVersion::current);
+        let mut header: Vec<String> = Vec::new();
+        match (version, own_header) {
+            (Some(version), _) => {
+                header.push(version.glsl_header().to_string());
+                if version.es { header.push(ES_PRECISION.to_string()); }
+            },
+            (None, Some(own_header)) => header.push(own_header),
+            (None, None) => {},
+        }
+        for (name, value) in &self.defines {
+            header.push(format!("#define {} {}", name, value));
+        }
+
+        let mut lines = vec![(0, version_line); header.len()];
+        lines.append(&mut expansion.origins);
+        header.append(&mut expansion.lines);
+        let mut source = header.join("\n");
+        source.push('\n');
+        if version.map(|version| version.es).unwrap_or(false) {
+            source = flatten_interface_blocks(&source);
+        }
+
+        Ok(ProcessedSource { source, files: expansion.files, lines })
+    }
+
+    /// Expands a source and compiles it, see `compile_source`.
+    pub fn compile(&self, name: &str, source: &str, shader_type: ShaderType)
+            -> Result<GLuint, EngineError> {
+        self.process(name, source)?.compile(shader_type)
+    }
+
+    /// Builds a program from the engine's shaders, by their paths under
+    /// `assets/shaders`, see `builtin_source`.
+    pub fn program(&self, vert_path: &str, frag_path: &str, geom_path: Option<&str>)
+            -> Result<ShaderProgram, EngineError> {
+        fn builtin(path: &str) -> Result<(&str, &'static str), EngineError> {
+            builtin_source(path)
+                .map(|source| (path, source))
+                .ok_or_else(|| EngineError::ShaderCompile(format!("No built-in shader {}", path)))
+        }
+        self.program_from_sources(builtin(vert_path)?, 
+                                  builtin(frag_path)?, 
+                                  geom_path.map(builtin).transpose()?)
+    }
+
+    /// Builds a program from `(name, source)` pairs, see `program_from_sources`.
+    pub fn program_from_sources(&self, 
+                                vert: (&str, &str), 
+                                frag: (&str, &str), 
+                                geom: Option<(&str, &str)>)
+                                -> Result<ShaderProgram, EngineError> {
+        // Declared uniforms are kept so that those optimized out aren't reported
+        let mut declared = Vec::new();
+        // Owned, so that shaders compiled before a failure are deleted too
+        let mut compile = |(name, source): (&str, &str), shader_type| {
+            let processed = self.process(name, source)?;
+            declared.extend(declared_uniforms(&processed.source));
+            processed.compile(shader_type).map(GlShader::adopt)
+        };
+        // Compile mandatory shaders
+        let vert_shader = compile(vert, ShaderType::VertexShader)?;
+        let frag_shader = compile(frag, ShaderType::FragmentShader)?;
+        // Optionally compile the geometry shader
+        let geom_shader = match geom {
+            Some(geom) => Some(compile(geom, ShaderType::GeometryShader)?),
+            None => None,
+        };
+
+        // Link the program, the shaders being deleted once they drop
+        let program = link_program(vert_shader.id(), frag_shader.id(),
+                                   geom_shader.as_ref().map(GlShader::id));
+
+        // Forward the linking Result
+        program.map(|program| {
+            let program = ShaderProgram::reflect(program, &declared);
+            program.label(&format!("{} + {}", vert.0, frag.0));
+            program
+        })
+    }
+
+    /// Appends a file's lines, replacing includes, and notes the main file's
+    /// `#version` line.
+    fn expand(&self, name: &str, source: &str, expansion: &mut Expansion)
+            -> Result<(), EngineError> {
+        let file = expansion.files.len();
+        expansion.files.push(name.to_string());
+        expansion.included.insert(name.to_string());
+
+        for (i, line) in strip_comments(source).lines().enumerate() {
+            let number = i as u32 + 1;
+            let directive = line.trim();
+            if directive.starts_with("#version") {
+                // Replaced by the header, blanked to keep the line count
+                if file == 0 && expansion.version.is_none() {
+                    expansion.version = Some((number, directive.to_string()));
+                }
+                expansion.push("", file, number);
+            } else if let Some(target) = directive.strip_prefix("#include") {
+                if !expansion.active() { continue; }
+                let target = target.trim();
+                let target = target.strip_prefix('"').and_then(|t| t.strip_suffix('"'))
+                    .or_else(|| target.strip_prefix('<').and_then(|t| t.strip_suffix('>')))
+                    .ok_or_else(|| EngineError::ShaderCompile(
+                        format!("{}:{}: malformed #include", name, number)))?;
+                if expansion.included.contains(target) { continue; }
+                let included = self.resolve(target).ok_or_else(|| EngineError::ShaderCompile(
+                    format!("{}:{}: could not find include \"{}\"", name, number, target)))?;
+                self.expand(target, &included, expansion)?;
+            } else {
+                expansion.follow(directive);
+                expansion.push(line, file, number);
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve(&self, name: &str) -> Option<String> {
+        self.includes.get(name).cloned()
+            .or_else(|| builtin_source(&format!("include/{}", name)).map(String::from))
+            .or_else(|| self.include_dirs.iter()
+                .find_map(|dir| fs::read_to_string(dir.join(name)).ok()))
+    }
+}
+
+/// Lines collected while expanding includes.
+#[derive(Default)]
+struct Expansion {
+    lines:      Vec<String>,
+    /// The file index and line number each line came from
+    origins:    Vec<(usize, u32)>,
+    files:      Vec<String>,
+    included:   HashSet<String>,
+    /// The main file's `#version` line and its number
+    version:    Option<(u32, String)>,
+    /// Macros defined at the current line
+    defined:    HashSet<String>,
+    /// The enclosing `#if` blocks, innermost last
+    conditionals: Vec<Conditional>,
+}
+
+/// An `#if` block being expanded. Branches are `None` where the expansion
+/// can't tell if they're compiled, as for `#if` expressions.
+#[derive(Debug, Clone, Copy)]
+struct Conditional {
+    /// Whether the current branch is compiled
+    branch:     Option<bool>,
+    /// Whether any branch so far was compiled
+    taken:      Option<bool>,
+}
+
+impl Expansion {
+    fn push(&mut self, line: &str, file: usize, number: u32) {
+        self.lines.push(line.to_string());
+        self.origins.push((file, number));
+    }
+
+    /// Whether the current line may be compiled, i.e. no enclosing branch is
+    /// known to be skipped.
+    fn active(&self) -> bool {
+        self.conditionals.iter().all(|c| c.branch != Some(false))
+    }
+
+    /// Follows the conditional and macro directives that decide which
+    /// includes are expanded.
+    fn follow(&mut self, directive: &str) {
+        let mut words = match directive.strip_prefix('#') {
+            Some(directive) => directive.split_whitespace(),
+            None => return,
+        };
+        let keyword = words.next().unwrap_or("");
+        // Function-like macros are named up to their parameters
+        let name = words.next().and_then(|name| name.split('(').next()).unwrap_or("");
+        match keyword {
+            "ifdef" | "ifndef" => {
+                let branch = Some(self.defined.contains(name) == (keyword == "ifdef"));
+                self.conditionals.push(Conditional { branch, taken: branch });
+            },
+            "if" => self.conditionals.push(Conditional { branch: None, taken: None }),
+            "elif" => if let Some(c) = self.conditionals.last_mut() {
+                c.branch = if c.taken == Some(true) { Some(false) } else { None };
+                c.taken = c.taken.filter(|&taken| taken);
+            },
+            "else" => if let Some(c) = self.conditionals.last_mut() {
+                c.branch = c.taken.map(|taken| !taken);
+                c.taken = Some(true);
+            },
+            "endif" => { self.conditionals.pop(); },
+            "define" if self.active() => { self.defined.insert(name.to_string()); },
+            "undef" if self.active() => { self.defined.remove(name); },
+            _ => {},
+        }
+    }
+}
+
+/// A source expanded by a `Preprocessor`, ready to compile.
+#[derive(Debug, Clone)]
+pub struct ProcessedSource {
+    pub source: String,
+    files:      Vec<String>,
+    /// The file index and line number of each line of the source
+    lines:      Vec<(usize, u32)>,
+}
+
+impl ProcessedSource {
+    /// Compiles the source, with errors pointing into the original files.
+    pub fn compile(&self, shader_type: ShaderType) -> Result<GLuint, EngineError> {
+        compile_glsl(self.source.clone(), &shader_type).map_err(|error| {
+            EngineError::ShaderCompile(self.map_log(&error))
+        })
+    }
+
+    /// The file and line a line of the source (counting from 1) came from.
+    pub fn origin(&self, line: u32) -> Option<(&str, u32)> {
+        let (file, number) = *self.lines.get((line as usize).checked_sub(1)?)?;
+        Some((&self.files[file], number))
+    }
+
+    /// Rewrites the line numbers of a compile log to the original files.
+    ///
+    /// Drivers report lines as `0:12` (Mesa, AMD, Apple) or `0(12)` (NVIDIA),
+    /// the 0 being the index of the source string.
+    pub fn map_log(&self, log: &str) -> String {
+        let bytes = log.as_bytes();
+        let mut mapped = String::with_capacity(log.len());
+        let mut last = 0;
+        let mut i = 0;
+        while i + 2 < bytes.len() {
+            let starts_word = i == 0 || bytes[i - 1].is_ascii_whitespace();
+            let colon = bytes[i + 1] == b':';
+            if !(starts_word && bytes[i] == b'0' && (colon || bytes[i + 1] == b'(')) {
+                i += 1;
+                continue;
+            }
+            let digits = log[i + 2..].bytes().take_while(u8::is_ascii_digit).count();
+            let end = i + 2 + digits;
+            let closed = colon || bytes.get(end) == Some(&b')');
+            let origin = log[i + 2..end].parse().ok().and_then(|line| self.origin(line));
+            match origin {
+                Some((file, line)) if closed => {
+                    mapped.push_str(&log[last..i]);
+                    if colon {
+                        mapped.push_str(&format!("{}:{}", file, line));
+                        last = end;
+                    } else {
+                        mapped.push_str(&format!("{}({})", file, line));
+                        last = end + 1;
+                    }
+                    i = last;
+                },
+                _ => i += 1,
+            }
+        }
+        mapped.push_str(&log[last..]);
+        mapped
+    }
+}
+
+/// Replaces comments with whitespace, keeping newlines.
+fn strip_comments(source: &str) -> String {
+    let mut stripped = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('/', Some('/')) => {
+                while let Some(&next) = chars.peek() {
+                    if next == '\n' { break; }
+                    chars.next();
+                }
+            },
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = ' ';
+                for next in chars.by_ref() {
+                    if next == '\n' { stripped.push('\n'); }
+                    if last == '*' && next == '/' { break; }
+                    last = next;
+                }
+                stripped.push(' ');
+            },
+            _ => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+/// Turns `in`/`out` interface blocks into plain variables, see `Preprocessor`.
+fn flatten_interface_blocks(source: &str) -> String {
+    let mut flattened = String::with_capacity(source.len());
+    let mut renames: Vec<(String, String)> = Vec::new();
+    let mut rest = source;
+
+    while let Some(open) = rest.find('{') {
+        let head: Vec<&str> = rest[..open].split_whitespace().rev().take(2).collect();
+        let close = rest[open..].find('}').map(|i| open + i);
+        let end = close.and_then(|close| rest[close..].find(';').map(|i| close + i));
+        let block = match (head.as_slice(), close, end) {
+            ([name, storage @ ("in" | "out")], Some(close), Some(end)) => {
+                let instance = rest[close + 1..end].trim();
+                let is_ident = |s: &str| !s.is_empty()
+                    && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                // Arrays of blocks only appear in geometry shaders
+                if is_ident(name) && is_ident(instance) {
+                    Some((*storage, *name, instance, close, end))
+                } else { None }
+            },
+            _ => None,
+        };
+
+        let (storage, name, instance, close, end) = match block {
+            Some(block) => block,
+            None => {
+                flattened.push_str(&rest[..=open]);
+                rest = &rest[open + 1..];
+                continue;
+            },
+        };
+
+        // Everything before the storage qualifier is kept
+        let before = rest[..open].trim_end();
+        let before = before[..before.len() - name.len()].trim_end();
+        flattened.push_str(&before[..before.len() - storage.len()]);
+
+        for member in rest[open + 1..close].split(';') {
+            let words: Vec<&str> = member.split_whitespace().collect();
+            let (member_name, decl) = match words.split_last() {
+                Some(split) => split,
+                None => continue,
+            };
+            // Interpolation qualifiers come before the storage qualifier
+            let (interp, ty): (Vec<&str>, Vec<&str>) = decl.iter()
+                .partition(|w| matches!(**w, "flat" | "smooth" | "centroid"));
+            for word in interp { flattened.push_str(word); flattened.push(' '); }
+            flattened.push_str(&format!("{} {} {}_{}; ", storage, ty.join(" "), name, member_name));
+        }
+        // Keep the block's lines, so later line numbers are unchanged
+        for _ in rest[open..=end].matches('\n') { flattened.push('\n'); }
+
+        renames.push((format!("{}.", instance), format!("{}_", name)));
+        rest = &rest[end + 1..];
+    }
+    flattened.push_str(rest);
+
+    for (from, to) in renames {
+        flattened = replace_prefix(&flattened, &from, &to);
+    }
+    flattened
+}
+
+/// Replaces `from` where it starts an identifier.
+fn replace_prefix(source: &str, from: &str, to: &str) -> String {
+    let mut replaced = String::with_capacity(source.len());
+    let mut last = 0;
+    for (i, _) in source.match_indices(from) {
+        let starts_ident = source[..i].chars().next_back()
+            .map(|c| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(true);
+        if !starts_ident { continue; }
+        replaced.push_str(&source[last..i]);
+        replaced.push_str(to);
+        last = i + from.len();
+    }
+    replaced.push_str(&source[last..]);
+    replaced
+}
+
 /// Compiles an OpenGL glsl shader from source code and returns the shader id.
 ///
 /// This is mostly used as a helper for 'program_from_*' which can be used
 /// to build an OpenGL shader program.
 /// The source should not contain any null bytes or it will result in an
 /// error while converting the source into a CString.
+///
+/// Sources are first expanded for the current context by a default
+/// `Preprocessor`, so may include the engine's shader includes.
 pub fn compile_source(source: String, shader_type: ShaderType)
         -> Result<GLuint, EngineError> {
-    
+    Preprocessor::new().compile(shader_type.label(), &source, shader_type)
+}
+
+/// Compiles a source as is, returning the log on errors.
+fn compile_glsl(source: String, shader_type: &ShaderType) -> Result<GLuint, String> {
     //println!("================={:?}================\n{}", shader_type, source);
     
     // Virtually all this code is unsafe as it deals with CStrings and
@@ -140,7 +1041,7 @@ pub fn compile_source(source: String, shader_type: ShaderType)
         let csource = match CString::new(source) {
             Ok(cstring) => cstring,
             Err(err) => {
-                return Err(EngineError::ShaderCompile("Failed to build CString".into()));
+                return Err("Failed to build CString".into());
             }
         };
 
@@ -167,12 +1068,9 @@ pub fn compile_source(source: String, shader_type: ShaderType)
                                 std::ptr::null_mut(),
                                 error_log.as_ptr() as *mut GLchar);
 
-            // Convert CString to String
-            let error = error_log.to_string_lossy().into_owned();
-            // Return as compile error
-            println!("========= {:?} Compile Error =========\n{}\n==========================", 
-                     &shader_type, error);
-            return Err(EngineError::ShaderCompile(error));
+            // Convert CString to String and return it as the error
+            gl::DeleteShader(shader);
+            return Err(error_log.to_string_lossy().into_owned());
         }
     }
     
@@ -190,6 +1088,6 @@ pub fn compile_file(path: String, shader_type: ShaderType)
         -> Result<GLuint, EngineError> {
     
     // Load the data and provide to compile_from_source
-    let shader_data = fs::read_to_string(path).expect("Failed to load shader file.");
-    compile_source(shader_data, shader_type)
+    let shader_data = fs::read_to_string(&path).expect("Failed to load shader file.");
+    Preprocessor::new().compile(&path, &shader_data, shader_type)
 }